@@ -0,0 +1,91 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use songbird::{
+    constants::*,
+    driver::bench_internals::{task_message::UdpTxMessage, udp_tx_runner},
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{net::UdpSocket, runtime::Builder};
+
+// Simulate a mixer which writes one packet per 20ms, but misses its deadline
+// every so often, and measure the timing of packets as seen by a listener.
+// The "time" reported for each input is the largest error in the gap between
+// two received packets: prerendering should hide the mixer's stalls.
+
+const FRAMES: u32 = 25;
+const STALL_EVERY: u32 = 10;
+const STALL: Duration = Duration::from_millis(30);
+
+async fn worst_gap_error(prerender_frames: u32) -> Duration {
+    let rx = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let tx = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    tx.connect(rx.local_addr().unwrap()).await.unwrap();
+
+    let (msg_tx, msg_rx) = flume::unbounded();
+    tokio::spawn(udp_tx_runner(msg_rx, 0, Arc::new(tx), None));
+
+    std::thread::spawn(move || {
+        let start = Instant::now();
+
+        for i in 0..FRAMES {
+            let deadline = start + TIMESTEP_LENGTH * i;
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+
+            if i % STALL_EVERY == STALL_EVERY - 1 {
+                std::thread::sleep(STALL);
+            }
+
+            let packet = vec![0u8; 64];
+            let _ = msg_tx.send(if prerender_frames == 0 {
+                UdpTxMessage::Packet(packet)
+            } else {
+                UdpTxMessage::Scheduled(packet, deadline + TIMESTEP_LENGTH * prerender_frames)
+            });
+        }
+
+        let _ = msg_tx.send(UdpTxMessage::Poison);
+    });
+
+    let mut buf = [0u8; 64];
+    let mut last = None;
+    let mut worst = Duration::default();
+
+    for _ in 0..FRAMES {
+        rx.recv(&mut buf).await.unwrap();
+        let now = Instant::now();
+
+        if let Some(last) = last {
+            let error = (now - last).abs_diff(TIMESTEP_LENGTH);
+
+            worst = worst.max(error);
+        }
+
+        last = Some(now);
+    }
+
+    worst
+}
+
+fn jitter(c: &mut Criterion) {
+    let rt = Builder::new_current_thread().enable_all().build().unwrap();
+
+    let mut group = c.benchmark_group("Packet Timing Error (30ms stall per 10 frames)");
+    group.sample_size(10);
+
+    for prerender_frames in &[0u32, 1, 2, 4] {
+        group.bench_with_input(
+            BenchmarkId::new("Prerendered Frames", prerender_frames),
+            prerender_frames,
+            |b, i| {
+                b.iter_custom(|iters| (0..iters).map(|_| rt.block_on(worst_gap_error(*i))).sum())
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, jitter);
+criterion_main!(benches);
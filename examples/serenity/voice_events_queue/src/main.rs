@@ -494,7 +494,15 @@ async fn queue(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
             },
         };
 
-        handler.enqueue_source(source.into());
+        if let Err(why) = handler.try_enqueue_source(source.into()) {
+            check_msg(
+                msg.channel_id
+                    .say(&ctx.http, format!("Could not queue song: {}", why))
+                    .await,
+            );
+
+            return Ok(());
+        }
 
         check_msg(
             msg.channel_id
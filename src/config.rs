@@ -1,5 +1,14 @@
 #[cfg(feature = "driver-core")]
-use super::driver::{retry::Retry, CryptoMode, DecodeMode};
+use super::constants::MONO_FRAME_SIZE;
+#[cfg(feature = "driver-core")]
+use super::{
+    driver::{retry::Retry, CryptoMode, CryptoObserver, DecodeMode, FrameLength, MixLevel},
+    input::InitLimit,
+};
+#[cfg(feature = "driver-core")]
+use flume::Sender;
+#[cfg(feature = "driver-core")]
+use std::sync::Arc;
 
 use std::time::Duration;
 
@@ -19,6 +28,15 @@ pub struct Config {
     /// [`CryptoMode::Normal`]: CryptoMode::Normal
     pub crypto_mode: CryptoMode,
     #[cfg(feature = "driver-core")]
+    /// Hook told of the encryption mode agreed upon by each new voice connection.
+    ///
+    /// This runs on every connection and reconnection which negotiates a new
+    /// session, e.g. for logging, or alerting if a deprecated mode is ever used.
+    /// Resumed sessions keep their earlier mode, and are not reported again.
+    ///
+    /// Defaults to `None`.
+    pub crypto_observer: Option<Arc<dyn CryptoObserver>>,
+    #[cfg(feature = "driver-core")]
     /// Configures whether decoding and decryption occur for all received packets.
     ///
     /// If voice receiving voice packets, generally you should choose [`DecodeMode::Decode`].
@@ -34,6 +52,53 @@ pub struct Config {
     /// [`DecodeMode::Pass`]: DecodeMode::Pass
     /// [user speaking events]: crate::events::CoreEvent::SpeakingUpdate
     pub decode_mode: DecodeMode,
+    #[cfg(feature = "driver-core")]
+    /// Computational complexity (`0`–`10`) used when encoding audio with Opus.
+    ///
+    /// Higher values spend more CPU time per packet for better audio quality at
+    /// a given bitrate: low-power hosts, or bots serving many calls, may prefer
+    /// lower values. This only affects tracks which must be re-encoded, i.e., any
+    /// which are not passed through as Opus.
+    ///
+    /// Defaults to `None`, which keeps libopus's own default (currently `9`).
+    pub encoder_complexity: Option<u8>,
+    #[cfg(feature = "driver-core")]
+    /// Configures whether Opus encoding includes in-band forward error correction (FEC).
+    ///
+    /// FEC adds redundant data to each packet, allowing listeners to recover from
+    /// isolated packet loss at the cost of some audio quality for a given bitrate.
+    /// This only takes effect if [`encoder_packet_loss`] is non-zero.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`encoder_packet_loss`]: Config::encoder_packet_loss
+    pub encoder_fec: bool,
+    #[cfg(feature = "driver-core")]
+    /// Expected percentage (`0`–`100`) of voice packets lost in transit.
+    ///
+    /// The Opus encoder uses this to decide how much redundancy to include
+    /// when [`encoder_fec`] is enabled.
+    ///
+    /// Defaults to `0`.
+    ///
+    /// [`encoder_fec`]: Config::encoder_fec
+    pub encoder_packet_loss: u8,
+    #[cfg(feature = "driver-core")]
+    /// Duration of audio encoded into each voice packet.
+    ///
+    /// **Discord only accepts 20ms frames**: other lengths are meant for custom
+    /// receivers, e.g., when recording the driver's output locally, and disable Opus
+    /// passthrough. Longer frames are assembled from several 20ms mixer steps, and
+    /// so add latency equal to their length. Packets are sent less often, but each
+    /// is larger: high bitrates may not fit in a single packet with 60ms frames.
+    /// 10ms frames split each mixer step into two packets, doubling packet overhead.
+    /// [`rtp_timestamp_step`] is applied once per 20ms of audio, scaled to the
+    /// length of each frame.
+    ///
+    /// Defaults to [`FrameLength::Ms20`].
+    ///
+    /// [`rtp_timestamp_step`]: Config::rtp_timestamp_step
+    pub frame_length: FrameLength,
     #[cfg(feature = "gateway-core")]
     /// Configures the amount of time to wait for Discord to reply with connection information
     /// if [`Call::join`]/[`join_gateway`] are used.
@@ -48,6 +113,68 @@ pub struct Config {
     /// [`join_gateway`]: crate::Call::join_gateway
     pub gateway_timeout: Option<Duration>,
     #[cfg(feature = "driver-core")]
+    /// Limits how many lazy or restarting [`Restartable`] inputs may be creating their
+    /// sources (e.g., running `youtube-dl` and probing its output) at the same time.
+    ///
+    /// The limit is shared by all drivers made from clones of this `Config`, such as
+    /// every call created by one [`Songbird`] manager. Each source creation holds a
+    /// permit, including those caused by seeks and by a queue's [preloading]: deep
+    /// preloading of a large queue will wait on this limit, rather than spawning
+    /// all of its requests at once. Tracks produce silence while they wait.
+    ///
+    /// Defaults to `None`, which places no limit on concurrent source creation.
+    ///
+    /// [`Restartable`]: crate::input::Restartable
+    /// [`Songbird`]: crate::Songbird
+    /// [preloading]: crate::tracks::TrackQueue::set_preload_depth
+    pub input_init_limit: Option<InitLimit>,
+    #[cfg(feature = "driver-core")]
+    /// The largest number of tracks which the mixer will hold at once.
+    ///
+    /// Tracks added beyond this limit are stopped before they can play: [`Driver::try_play`]
+    /// reports this as [`TrackError::TooManyTracks`], while [`Driver::play`] (and methods
+    /// built on it) give no error. Either way, the track's [`TrackEvent::End`] handlers
+    /// still fire, as does [`CoreEvent::TrackCulled`] with [`CullReason::Stop`]. Replacing
+    /// all tracks via [`Driver::play_only`] is unaffected, as is any track already playing.
+    ///
+    /// Defaults to `None`, allowing any number of concurrent tracks.
+    ///
+    /// [`Driver::try_play`]: crate::driver::Driver::try_play
+    /// [`Driver::play`]: crate::driver::Driver::play
+    /// [`Driver::play_only`]: crate::driver::Driver::play_only
+    /// [`TrackError::TooManyTracks`]: crate::tracks::TrackError::TooManyTracks
+    /// [`TrackEvent::End`]: crate::events::TrackEvent::End
+    /// [`CoreEvent::TrackCulled`]: crate::events::CoreEvent::TrackCulled
+    /// [`CullReason::Stop`]: crate::events::context_data::CullReason::Stop
+    pub max_concurrent_tracks: Option<usize>,
+    #[cfg(feature = "driver-core")]
+    /// Longest time any track may play for before it is automatically stopped.
+    ///
+    /// This is a safety net against, e.g., live streams which never end. Tracks
+    /// are measured by their play time, rather than the length of their input, so
+    /// sources without a known duration are still stopped. Stopped tracks fire
+    /// [`TrackEvent::End`] as usual, and a [`TrackQueue`] will advance past them.
+    ///
+    /// This applies to every track in the driver, alongside any per-track limit
+    /// given to [`TrackQueue::add_with_max_duration`]: whichever is shorter takes
+    /// effect, so per-track limits can only lower this cap.
+    ///
+    /// Defaults to `None`.
+    ///
+    /// [`TrackEvent::End`]: crate::events::TrackEvent::End
+    /// [`TrackQueue`]: crate::tracks::TrackQueue
+    /// [`TrackQueue::add_with_max_duration`]: crate::tracks::TrackQueue::add_with_max_duration
+    pub max_track_duration: Option<Duration>,
+    #[cfg(feature = "driver-core")]
+    /// Channel on which to report the loudness of each frame of mixed audio.
+    ///
+    /// When set, the mixer measures every frame it encodes, and sends the result
+    /// without waiting: if a bounded channel is full, that frame's level is dropped.
+    /// Frames which are passed through as Opus without mixing are not measured.
+    ///
+    /// Defaults to `None`, as this adds work to every mixer cycle.
+    pub mix_levels: Option<Sender<MixLevel>>,
+    #[cfg(feature = "driver-core")]
     /// Number of concurrently active tracks to allocate memory for.
     ///
     /// This should be set at, or just above, the maximum number of tracks
@@ -61,6 +188,68 @@ pub struct Config {
     /// the capacity of the track store.
     pub preallocated_tracks: usize,
     #[cfg(feature = "driver-core")]
+    /// Number of 20ms frames of audio to mix ahead of when they are sent.
+    ///
+    /// Mixed packets are held by the UDP transmit task, and sent at regular
+    /// intervals once their scheduled time arrives. This allows the mixer to fall
+    /// up to `prerender_frames` frames behind schedule (e.g., on a heavily loaded
+    /// host) without causing gaps in the audio heard by listeners.
+    ///
+    /// This adds 20ms of latency per frame to all audio. Track events (such as
+    /// [`TrackEvent::End`]) and track positions are driven by the mixer, and so will
+    /// run ahead of what listeners hear by the same amount.
+    ///
+    /// Defaults to `0`, where each packet is sent as soon as it is mixed.
+    ///
+    /// [`TrackEvent::End`]: crate::events::TrackEvent::End
+    pub prerender_frames: usize,
+    #[cfg(feature = "driver-core")]
+    /// Configures whether the RTP marker bit is set on the first voice packet
+    /// sent after a period of silence.
+    ///
+    /// This marks the start of each "talkspurt", as suggested by [RFC 3551]. Discord
+    /// does not require this, but some custom receivers may make use of it.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [RFC 3551]: https://tools.ietf.org/html/rfc3551#section-4.1
+    pub rtp_marker_after_silence: bool,
+    #[cfg(feature = "driver-core")]
+    /// Amount by which the RTP timestamp advances after each 20ms voice packet.
+    ///
+    /// Defaults to `960`, i.e., one packet's worth of samples per channel at 48kHz,
+    /// as Discord expects.
+    pub rtp_timestamp_step: u32,
+    #[cfg(feature = "driver-core")]
+    /// Number of Opus silence frames sent once the driver has no more audio to play.
+    ///
+    /// Discord recommends sending five such frames before transmission stops, to
+    /// avoid unintended Opus interpolation in listeners' decoders. This happens
+    /// whenever all tracks end, are paused, or are removed, e.g. by [`Driver::stop`]
+    /// or when a [`TrackQueue`] is stopped or runs out of tracks.
+    ///
+    /// Defaults to `5`.
+    ///
+    /// [`Driver::stop`]: crate::driver::Driver::stop
+    /// [`TrackQueue`]: crate::tracks::TrackQueue
+    pub silence_frames_on_stop: u8,
+    #[cfg(feature = "driver-core")]
+    /// Number of recent intervals between sent voice packets to record, for
+    /// diagnosing choppy audio.
+    ///
+    /// When non-zero, the UDP transmit task notes the time between each pair of
+    /// voice packets as it sends them, which can be read back using
+    /// [`Driver::send_timing`] and [`Driver::send_intervals`]. Gaps of over a second
+    /// (e.g., while nothing is playing) are treated as pauses, and are not recorded.
+    /// Recorded intervals are discarded on each new connection, which also applies
+    /// any change to this value.
+    ///
+    /// Defaults to `0`, where no timings are recorded.
+    ///
+    /// [`Driver::send_timing`]: crate::driver::Driver::send_timing
+    /// [`Driver::send_intervals`]: crate::driver::Driver::send_intervals
+    pub send_timing_window: usize,
+    #[cfg(feature = "driver-core")]
     /// Connection retry logic for the [`Driver`].
     ///
     /// This controls how many times the [`Driver`] should retry any connections,
@@ -82,12 +271,40 @@ impl Default for Config {
             #[cfg(feature = "driver-core")]
             crypto_mode: CryptoMode::Normal,
             #[cfg(feature = "driver-core")]
+            crypto_observer: None,
+            #[cfg(feature = "driver-core")]
             decode_mode: DecodeMode::Decrypt,
+            #[cfg(feature = "driver-core")]
+            encoder_complexity: None,
+            #[cfg(feature = "driver-core")]
+            encoder_fec: false,
+            #[cfg(feature = "driver-core")]
+            encoder_packet_loss: 0,
+            #[cfg(feature = "driver-core")]
+            frame_length: FrameLength::Ms20,
             #[cfg(feature = "gateway-core")]
             gateway_timeout: Some(Duration::from_secs(10)),
             #[cfg(feature = "driver-core")]
+            input_init_limit: None,
+            #[cfg(feature = "driver-core")]
+            max_concurrent_tracks: None,
+            #[cfg(feature = "driver-core")]
+            max_track_duration: None,
+            #[cfg(feature = "driver-core")]
+            mix_levels: None,
+            #[cfg(feature = "driver-core")]
             preallocated_tracks: 1,
             #[cfg(feature = "driver-core")]
+            prerender_frames: 0,
+            #[cfg(feature = "driver-core")]
+            rtp_marker_after_silence: false,
+            #[cfg(feature = "driver-core")]
+            rtp_timestamp_step: MONO_FRAME_SIZE as u32,
+            #[cfg(feature = "driver-core")]
+            silence_frames_on_stop: 5,
+            #[cfg(feature = "driver-core")]
+            send_timing_window: 0,
+            #[cfg(feature = "driver-core")]
             driver_retry: Default::default(),
             #[cfg(feature = "driver-core")]
             driver_timeout: Some(Duration::from_secs(10)),
@@ -103,18 +320,108 @@ impl Config {
         self
     }
 
+    /// Sets this `Config`'s hook for observing negotiated encryption modes.
+    pub fn crypto_observer(mut self, crypto_observer: Option<Arc<dyn CryptoObserver>>) -> Self {
+        self.crypto_observer = crypto_observer;
+        self
+    }
+
     /// Sets this `Config`'s received packet decryption/decoding behaviour.
     pub fn decode_mode(mut self, decode_mode: DecodeMode) -> Self {
         self.decode_mode = decode_mode;
         self
     }
 
+    /// Sets this `Config`'s Opus encoder complexity.
+    pub fn encoder_complexity(mut self, encoder_complexity: Option<u8>) -> Self {
+        self.encoder_complexity = encoder_complexity;
+        self
+    }
+
+    /// Sets whether this `Config` enables Opus in-band forward error correction.
+    pub fn encoder_fec(mut self, encoder_fec: bool) -> Self {
+        self.encoder_fec = encoder_fec;
+        self
+    }
+
+    /// Sets this `Config`'s expected packet loss percentage.
+    pub fn encoder_packet_loss(mut self, encoder_packet_loss: u8) -> Self {
+        self.encoder_packet_loss = encoder_packet_loss;
+        self
+    }
+
+    /// Sets the duration of audio this `Config` encodes into each packet.
+    pub fn frame_length(mut self, frame_length: FrameLength) -> Self {
+        self.frame_length = frame_length;
+        self
+    }
+
+    /// Limits this `Config` to creating at most `max` [`Restartable`] sources at once.
+    ///
+    /// Each call creates a new limit: see [`input_init_limit`] to share one limit
+    /// between several configs.
+    ///
+    /// [`Restartable`]: crate::input::Restartable
+    /// [`input_init_limit`]: Config::input_init_limit
+    pub fn max_concurrent_input_init(mut self, max: usize) -> Self {
+        self.input_init_limit = Some(InitLimit::new(max));
+        self
+    }
+
+    /// Sets the largest number of tracks the mixer may hold at once in this `Config`.
+    pub fn max_concurrent_tracks(mut self, max_concurrent_tracks: Option<usize>) -> Self {
+        self.max_concurrent_tracks = max_concurrent_tracks;
+        self
+    }
+
+    /// Sets the longest time any track may play for in this `Config`.
+    pub fn max_track_duration(mut self, max_track_duration: Option<Duration>) -> Self {
+        self.max_track_duration = max_track_duration;
+        self
+    }
+
+    /// Sets this `Config`'s channel for reporting mixed audio levels.
+    pub fn mix_levels(mut self, mix_levels: Option<Sender<MixLevel>>) -> Self {
+        self.mix_levels = mix_levels;
+        self
+    }
+
     /// Sets this `Config`'s number of tracks to preallocate.
     pub fn preallocated_tracks(mut self, preallocated_tracks: usize) -> Self {
         self.preallocated_tracks = preallocated_tracks;
         self
     }
 
+    /// Sets this `Config`'s number of audio frames to mix ahead of time.
+    pub fn prerender_frames(mut self, prerender_frames: usize) -> Self {
+        self.prerender_frames = prerender_frames;
+        self
+    }
+
+    /// Sets whether this `Config` marks the first RTP packet after silence.
+    pub fn rtp_marker_after_silence(mut self, rtp_marker_after_silence: bool) -> Self {
+        self.rtp_marker_after_silence = rtp_marker_after_silence;
+        self
+    }
+
+    /// Sets this `Config`'s RTP timestamp increment per packet.
+    pub fn rtp_timestamp_step(mut self, rtp_timestamp_step: u32) -> Self {
+        self.rtp_timestamp_step = rtp_timestamp_step;
+        self
+    }
+
+    /// Sets the number of silence frames this `Config` sends after audio stops.
+    pub fn silence_frames_on_stop(mut self, silence_frames_on_stop: u8) -> Self {
+        self.silence_frames_on_stop = silence_frames_on_stop;
+        self
+    }
+
+    /// Sets the number of packet send intervals this `Config` records.
+    pub fn send_timing_window(mut self, send_timing_window: usize) -> Self {
+        self.send_timing_window = send_timing_window;
+        self
+    }
+
     /// Sets this `Config`'s timeout for establishing a voice connection.
     pub fn driver_timeout(mut self, driver_timeout: Option<Duration>) -> Self {
         self.driver_timeout = driver_timeout;
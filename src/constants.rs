@@ -52,6 +52,13 @@ pub const CHILD_BUFFER_LEN: usize = AUDIO_FRAME_RATE / 2;
 /// Set a safe amount below the Ethernet MTU to avoid fragmentation/rejection.
 pub const VOICE_PACKET_MAX: usize = 1460;
 
+#[cfg(feature = "driver-core")]
+/// Worst-case number of bytes added to a voice packet's payload by encryption,
+/// across all [`CryptoMode`]s.
+///
+/// [`CryptoMode`]: crate::driver::CryptoMode
+pub const MAX_CRYPTO_OVERHEAD: usize = crate::driver::CryptoMode::MAX_OVERHEAD;
+
 /// Delay between sends of UDP keepalive frames.
 ///
 /// Passive monitoring of Discord itself shows that these fire every 5 seconds
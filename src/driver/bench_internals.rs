@@ -3,6 +3,6 @@
 //! Included if using the `"internals"` feature flag.
 //! You should not and/or cannot use these as part of a normal application.
 
-pub use super::tasks::{message as task_message, mixer};
+pub use super::tasks::{message as task_message, mixer, udp_tx::runner as udp_tx_runner};
 
-pub use super::crypto::CryptoState;
+pub use super::{crypto::CryptoState, send_timing::SendTiming, test_harness};
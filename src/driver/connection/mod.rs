@@ -4,6 +4,7 @@ use super::{
     tasks::{message::*, udp_rx, udp_tx, ws as ws_task},
     Config,
     CryptoMode,
+    SendTiming,
 };
 use crate::{
     constants::*,
@@ -19,7 +20,11 @@ use crate::{
 use discortp::discord::{IpDiscoveryPacket, IpDiscoveryType, MutableIpDiscoveryPacket};
 use error::{Error, Result};
 use flume::Sender;
-use std::{net::IpAddr, str::FromStr, sync::Arc};
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+};
 #[cfg(not(feature = "tokio-02-marker"))]
 use tokio::{net::UdpSocket, spawn, time::timeout};
 #[cfg(feature = "tokio-02-marker")]
@@ -35,6 +40,8 @@ use ws::create_rustls_client;
 use ws::create_native_tls_client;
 
 pub(crate) struct Connection {
+    /// Our own address and port, as found by IP discovery.
+    pub(crate) external_addr: SocketAddr,
     pub(crate) info: ConnectionInfo,
     pub(crate) ssrc: u32,
     pub(crate) ws: Sender<WsMessage>,
@@ -46,11 +53,14 @@ impl Connection {
         interconnect: &Interconnect,
         config: &Config,
         idx: usize,
+        send_timing: &Arc<SendTiming>,
     ) -> Result<Connection> {
+        let connection = Connection::new_inner(info, interconnect, config, idx, send_timing);
+
         if let Some(t) = config.driver_timeout {
-            timeout(t, Connection::new_inner(info, interconnect, config, idx)).await?
+            timeout(t, connection).await?
         } else {
-            Connection::new_inner(info, interconnect, config, idx).await
+            connection.await
         }
     }
 
@@ -59,6 +69,7 @@ impl Connection {
         interconnect: &Interconnect,
         config: &Config,
         idx: usize,
+        send_timing: &Arc<SendTiming>,
     ) -> Result<Connection> {
         let url = generate_url(&mut info.endpoint)?;
 
@@ -138,7 +149,7 @@ impl Connection {
         udp.send(&bytes).await?;
 
         let (len, _addr) = udp.recv_from(&mut bytes).await?;
-        {
+        let external_addr = {
             let view =
                 IpDiscoveryPacket::new(&bytes[..len]).ok_or(Error::IllegalDiscoveryResponse)?;
 
@@ -173,10 +184,16 @@ impl Connection {
                     },
                 }))
                 .await?;
-        }
+
+            SocketAddr::new(address, view.get_port())
+        };
 
         let cipher = init_cipher(&mut client, config.crypto_mode).await?;
 
+        if let Some(observer) = &config.crypto_observer {
+            observer.negotiated(config.crypto_mode, &ready.modes);
+        }
+
         info!("Connected to: {}", info.endpoint);
 
         info!("WS heartbeat duration {}ms.", hello.heartbeat_interval,);
@@ -232,9 +249,12 @@ impl Connection {
             config.clone(),
             udp_rx,
         ));
-        spawn(udp_tx::runner(udp_sender_msg_rx, ssrc, udp_tx));
+        send_timing.reset(config.send_timing_window);
+        let send_timing = (config.send_timing_window > 0).then(|| send_timing.clone());
+        spawn(udp_tx::runner(udp_sender_msg_rx, ssrc, udp_tx, send_timing));
 
         Ok(Connection {
+            external_addr,
             info,
             ssrc,
             ws: ws_msg_tx,
@@ -363,5 +383,5 @@ where
     T: for<'a> PartialEq<&'a str>,
     It: IntoIterator<Item = T>,
 {
-    modes.into_iter().any(|s| s == mode.to_request_str())
+    mode.is_available() && modes.into_iter().any(|s| s == mode.to_request_str())
 }
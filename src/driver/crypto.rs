@@ -2,7 +2,8 @@
 use byteorder::{NetworkEndian, WriteBytesExt};
 use discortp::{rtp::RtpPacket, MutablePacket};
 use rand::Rng;
-use std::num::Wrapping;
+use serde::{Deserialize, Serialize};
+use std::{fmt, num::Wrapping};
 use xsalsa20poly1305::{
     aead::{AeadInPlace, Error as CryptoError},
     Nonce,
@@ -33,6 +34,32 @@ pub enum CryptoMode {
     Lite,
 }
 
+/// Receives the outcome of each encryption mode negotiation with Discord.
+///
+/// Installed via [`Config::crypto_observer`].
+///
+/// [`Config::crypto_observer`]: crate::Config::crypto_observer
+pub trait CryptoObserver: Send + Sync {
+    /// Called once a voice connection has agreed to encrypt packets using `chosen`.
+    ///
+    /// This is not called when the driver resumes an existing session after losing
+    /// its Websocket connection, as the session keeps its previously negotiated mode.
+    ///
+    /// `offered` holds the names of every mode advertised by the voice server (as
+    /// given by [`CryptoMode::to_request_str`]), including any unknown to this build.
+    ///
+    /// This runs within the driver's connection task, and **must not** block.
+    ///
+    /// [`CryptoMode::to_request_str`]: CryptoMode::to_request_str
+    fn negotiated(&self, chosen: CryptoMode, offered: &[String]);
+}
+
+impl fmt::Debug for dyn CryptoObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CryptoObserver")
+    }
+}
+
 impl From<CryptoState> for CryptoMode {
     fn from(val: CryptoState) -> Self {
         use CryptoState::*;
@@ -45,6 +72,55 @@ impl From<CryptoState> for CryptoMode {
 }
 
 impl CryptoMode {
+    /// The largest number of additional bytes required by any mode,
+    /// compared to an unencrypted payload.
+    ///
+    /// This is the overhead of [`CryptoMode::Suffix`]: a 24B nonce and 16B tag.
+    pub const MAX_OVERHEAD: usize = Self::Suffix.payload_overhead();
+
+    /// Returns every mode supported by this build, in order of declaration.
+    pub fn all() -> &'static [CryptoMode] {
+        use CryptoMode::*;
+        &[Normal, Suffix, Lite]
+    }
+
+    /// Returns whether this build is able to encrypt and decrypt packets using this mode.
+    ///
+    /// Every XSalsa20Poly1305 variant is implemented in software, and so this is
+    /// currently always `true`. Modes reliant on optional features or on hardware
+    /// support should be checked here before being requested via [`Config`].
+    ///
+    /// [`Config`]: crate::Config
+    pub fn is_available(self) -> bool {
+        use CryptoMode::*;
+        match self {
+            Normal | Suffix | Lite => true,
+        }
+    }
+
+    /// Returns every mode which [is available] in this build, in order of declaration.
+    ///
+    /// [is available]: CryptoMode::is_available
+    pub fn available_modes() -> Vec<CryptoMode> {
+        Self::all()
+            .iter()
+            .copied()
+            .filter(|mode| mode.is_available())
+            .collect()
+    }
+
+    /// Returns the mode whose name (as it appears during negotiation) is `name`.
+    ///
+    /// This is the inverse of [`to_request_str`].
+    ///
+    /// [`to_request_str`]: CryptoMode::to_request_str
+    pub fn from_request_str(name: &str) -> Option<CryptoMode> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|mode| mode.to_request_str() == name)
+    }
+
     /// Returns the name of a mode as it will appear during negotiation.
     pub fn to_request_str(self) -> &'static str {
         use CryptoMode::*;
@@ -57,7 +133,7 @@ impl CryptoMode {
 
     /// Returns the number of bytes each nonce is stored as within
     /// a packet.
-    pub fn nonce_size(self) -> usize {
+    pub const fn nonce_size(self) -> usize {
         use CryptoMode::*;
         match self {
             Normal => RtpPacket::minimum_packet_size(),
@@ -68,13 +144,13 @@ impl CryptoMode {
 
     /// Returns the number of bytes occupied by the encryption scheme
     /// which fall before the payload.
-    pub fn payload_prefix_len(self) -> usize {
+    pub const fn payload_prefix_len(self) -> usize {
         TAG_SIZE
     }
 
     /// Returns the number of bytes occupied by the encryption scheme
     /// which fall after the payload.
-    pub fn payload_suffix_len(self) -> usize {
+    pub const fn payload_suffix_len(self) -> usize {
         use CryptoMode::*;
         match self {
             Normal => 0,
@@ -84,7 +160,7 @@ impl CryptoMode {
 
     /// Calculates the number of additional bytes required compared
     /// to an unencrypted payload.
-    pub fn payload_overhead(self) -> usize {
+    pub const fn payload_overhead(self) -> usize {
         self.payload_prefix_len() + self.payload_suffix_len()
     }
 
@@ -170,7 +246,7 @@ impl CryptoMode {
 }
 
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[non_exhaustive]
 pub enum CryptoState {
     Normal,
@@ -190,6 +266,32 @@ impl From<CryptoMode> for CryptoState {
 }
 
 impl CryptoState {
+    /// Creates a [`CryptoMode::Lite`] state whose next nonce will be `counter`.
+    ///
+    /// This allows a resumed session to continue from a counter saved via
+    /// [`counter`], rather than choosing a new random start point. Restoring
+    /// anything other than the most recent counter will reuse nonces under the
+    /// same key, which breaks the confidentiality of every packet sent with a
+    /// repeated nonce.
+    ///
+    /// [`counter`]: CryptoState::counter
+    pub fn lite_from(counter: u32) -> Self {
+        CryptoState::Lite(Wrapping(counter))
+    }
+
+    /// Returns the value of the next nonce to be written, for [`CryptoMode::Lite`].
+    ///
+    /// Other modes keep no counter, and return `None`. See [`lite_from`] for how this
+    /// may be used to avoid nonce reuse across a session resumption.
+    ///
+    /// [`lite_from`]: CryptoState::lite_from
+    pub fn counter(&self) -> Option<u32> {
+        match self {
+            CryptoState::Lite(i) => Some(i.0),
+            _ => None,
+        }
+    }
+
     /// Writes packet nonce into the body, if required, returning the new length.
     pub fn write_packet_nonce(
         &mut self,
@@ -204,13 +306,13 @@ impl CryptoState {
             Suffix => {
                 rand::thread_rng().fill(&mut packet.payload_mut()[payload_end..endpoint]);
             },
-            Lite(mut i) => {
+            Lite(i) => {
                 (&mut packet.payload_mut()[payload_end..endpoint])
                     .write_u32::<NetworkEndian>(i.0)
                     .expect(
                         "Nonce size is guaranteed to be sufficient to write u32 for lite tagging.",
                     );
-                i += Wrapping(1);
+                *i += Wrapping(1);
             },
             _ => {},
         }
@@ -218,8 +320,78 @@ impl CryptoState {
         endpoint
     }
 
+    /// Returns the bytes which will be written as the next packet's nonce suffix,
+    /// without advancing any internal counter.
+    ///
+    /// This is only known ahead of time for [`CryptoMode::Lite`]. [`CryptoMode::Suffix`]
+    /// generates a fresh random nonce for every packet, and [`CryptoMode::Normal`]
+    /// takes its nonce from each packet's RTP header: both return `None`.
+    pub fn peek_next_nonce(&self) -> Option<Vec<u8>> {
+        self.counter().map(|i| i.to_be_bytes().to_vec())
+    }
+
     /// Returns the underlying (stateless) type of the active crypto mode.
     pub fn kind(&self) -> CryptoMode {
         CryptoMode::from(*self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use discortp::{rtp::MutableRtpPacket, Packet};
+
+    #[test]
+    fn all_built_in_modes_are_available() {
+        assert!(CryptoMode::all().iter().all(|mode| mode.is_available()));
+        assert_eq!(CryptoMode::available_modes(), CryptoMode::all());
+    }
+
+    #[test]
+    fn request_strs_round_trip() {
+        for mode in CryptoMode::all() {
+            assert_eq!(
+                CryptoMode::from_request_str(mode.to_request_str()),
+                Some(*mode)
+            );
+        }
+
+        assert_eq!(
+            CryptoMode::from_request_str("xsalsa20_poly1305_bogus"),
+            None
+        );
+    }
+
+    #[test]
+    fn lite_nonce_advances_per_packet() {
+        let mut state = CryptoState::Lite(Wrapping(u32::MAX));
+        let mut buf = [0u8; 64];
+        let mut pkt = MutableRtpPacket::new(&mut buf[..]).unwrap();
+
+        assert_eq!(state.peek_next_nonce(), Some(vec![0xff; 4]));
+        assert_eq!(state.write_packet_nonce(&mut pkt, 0), 4);
+        assert_eq!(&pkt.payload()[..4], &[0xff; 4]);
+
+        assert_eq!(state.peek_next_nonce(), Some(vec![0; 4]));
+        state.write_packet_nonce(&mut pkt, 0);
+        assert_eq!(&pkt.payload()[..4], &[0; 4]);
+        assert_eq!(state.peek_next_nonce(), Some(vec![0, 0, 0, 1]));
+    }
+
+    #[test]
+    fn lite_counter_survives_restore() {
+        let mut state = CryptoState::lite_from(41);
+        let mut buf = [0u8; 64];
+        let mut pkt = MutableRtpPacket::new(&mut buf[..]).unwrap();
+
+        state.write_packet_nonce(&mut pkt, 0);
+
+        let restored = CryptoState::lite_from(state.counter().unwrap());
+        assert_eq!(restored, state);
+        assert_eq!(restored.counter(), Some(42));
+        assert_eq!(CryptoState::Suffix.counter(), None);
+
+        let saved = serde_json::to_string(&state).unwrap();
+        assert_eq!(serde_json::from_str::<CryptoState>(&saved).unwrap(), state);
+    }
+}
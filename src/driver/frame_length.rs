@@ -0,0 +1,47 @@
+use crate::constants::{STEREO_FRAME_SIZE, TIMESTEP_LENGTH};
+use std::time::Duration;
+
+/// Duration of audio held in each Opus packet sent by the driver.
+///
+/// Discord voice requires [`FrameLength::Ms20`]: other lengths are intended for
+/// custom receivers, such as local recording setups or experiments with packet
+/// overhead, and will not play correctly in Discord clients.
+///
+/// The mixer still processes audio in 20ms steps, so track positions and events
+/// are unaffected. Longer frames are built from several mixed steps before
+/// encoding, and so add their length in latency. 10ms frames split each mixed
+/// step in two, and send the second half 10ms after the first.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum FrameLength {
+    /// 10ms frames.
+    Ms10,
+    /// 20ms frames, as used by Discord.
+    Ms20,
+    /// 40ms frames.
+    Ms40,
+    /// 60ms frames.
+    Ms60,
+}
+
+impl FrameLength {
+    /// Returns the duration of audio held in each frame.
+    pub fn duration(self) -> Duration {
+        match self {
+            Self::Ms10 => TIMESTEP_LENGTH / 2,
+            Self::Ms20 => TIMESTEP_LENGTH,
+            Self::Ms40 => TIMESTEP_LENGTH * 2,
+            Self::Ms60 => TIMESTEP_LENGTH * 3,
+        }
+    }
+
+    /// Number of interleaved stereo samples which make up each frame.
+    pub(crate) fn samples(self) -> usize {
+        match self {
+            Self::Ms10 => STEREO_FRAME_SIZE / 2,
+            Self::Ms20 => STEREO_FRAME_SIZE,
+            Self::Ms40 => STEREO_FRAME_SIZE * 2,
+            Self::Ms60 => STEREO_FRAME_SIZE * 3,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+/// Loudness of a single frame of the driver's mixed audio output.
+///
+/// These are measured after all tracks are mixed and soft-clipped, immediately
+/// before Opus encoding. Values are linear sample magnitudes, where `1.0` is full
+/// scale.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct MixLevel {
+    /// Root-mean-square level across all samples (and channels) in the frame.
+    pub rms: f32,
+    /// Largest absolute sample value in the frame.
+    pub peak: f32,
+}
+
+impl MixLevel {
+    /// Measures the level of a buffer of interleaved audio samples.
+    pub(crate) fn measure(samples: &[f32]) -> Self {
+        if samples.is_empty() {
+            return Default::default();
+        }
+
+        let (sum_sq, peak) = samples.iter().fold((0.0, 0.0f32), |(sum_sq, peak), s| {
+            (sum_sq + s * s, peak.max(s.abs()))
+        });
+
+        Self {
+            rms: (sum_sq / samples.len() as f32).sqrt(),
+            peak,
+        }
+    }
+}
@@ -14,20 +14,30 @@ pub mod bench_internals;
 pub(crate) mod connection;
 mod crypto;
 mod decode_mode;
+mod frame_length;
+mod mix_level;
 pub mod retry;
+mod send_timing;
 pub(crate) mod tasks;
+#[cfg(any(test, feature = "internals"))]
+pub mod test_harness;
 
 use connection::error::{Error, Result};
-pub use crypto::CryptoMode;
 pub(crate) use crypto::CryptoState;
+pub use crypto::{CryptoMode, CryptoObserver};
 pub use decode_mode::DecodeMode;
+pub use frame_length::FrameLength;
+pub use mix_level::MixLevel;
+pub(crate) use send_timing::SendTiming;
+pub use send_timing::SendTimingStats;
 
 #[cfg(feature = "builtin-queue")]
 use crate::tracks::TrackQueue;
 use crate::{
+    constants::DEFAULT_BITRATE,
     events::EventData,
-    input::Input,
-    tracks::{self, Track, TrackHandle},
+    input::{cached::Memory, error::Result as InputResult, Input},
+    tracks::{Track, TrackError, TrackHandle, TrackResult},
     Config,
     ConnectionInfo,
     Event,
@@ -41,7 +51,16 @@ use core::{
     task::{Context, Poll},
 };
 use flume::{r#async::RecvFut, SendError, Sender};
-use tasks::message::CoreMessage;
+use parking_lot::Mutex;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tasks::{message::CoreMessage, mixer::FrameBudget};
 use tracing::instrument;
 
 /// The control object for a Discord voice connection, handling connection,
@@ -49,11 +68,28 @@ use tracing::instrument;
 ///
 /// When compiled with the `"builtin-queue"` feature, each driver includes a track queue
 /// as a convenience to prevent the additional overhead of per-guild state management.
+///
+/// Tracks are owned by the mixer rather than by any one connection, and are not advanced
+/// while the driver is disconnected. If a connection drops and is later reestablished
+/// (automatically, or via [`connect`]), each track resumes from the exact position it had
+/// reached when the connection was lost, without any seeking. This holds for all inputs,
+/// though non-seekable live sources (e.g., a piped radio stream) will resume from
+/// whatever stale audio was buffered at the time.
+///
+/// [`connect`]: Driver::connect
 #[derive(Clone, Debug)]
 pub struct Driver {
+    bitrate: Bitrate,
     config: Config,
+    connected: Arc<AtomicBool>,
+    external_addr: Arc<Mutex<Option<SocketAddr>>>,
+    frame_budget: Arc<FrameBudget>,
+    send_timing: Arc<SendTiming>,
+    join_sound: Option<Memory>,
     self_mute: bool,
     sender: Sender<CoreMessage>,
+    speaking: Arc<AtomicBool>,
+    track_count: Arc<AtomicUsize>,
     #[cfg(feature = "builtin-queue")]
     queue: TrackQueue,
 }
@@ -64,29 +100,88 @@ impl Driver {
     /// This will create the core voice tasks in the background.
     #[inline]
     pub fn new(config: Config) -> Self {
-        let sender = Self::start_inner(config.clone());
+        let connected = Arc::new(AtomicBool::new(false));
+        let external_addr = Arc::new(Mutex::new(None));
+        let frame_budget = Arc::new(FrameBudget::new());
+        let send_timing = Arc::new(SendTiming::new());
+        let speaking = Arc::new(AtomicBool::new(false));
+        let track_count = Arc::new(AtomicUsize::new(0));
+        let sender = Self::start_inner(
+            config.clone(),
+            connected.clone(),
+            external_addr.clone(),
+            frame_budget.clone(),
+            send_timing.clone(),
+            speaking.clone(),
+            track_count.clone(),
+        );
 
         Driver {
+            bitrate: DEFAULT_BITRATE,
             config,
+            connected,
+            external_addr,
+            frame_budget,
+            send_timing,
+            join_sound: None,
             self_mute: false,
             sender,
+            speaking,
+            track_count,
             #[cfg(feature = "builtin-queue")]
             queue: Default::default(),
         }
     }
 
-    fn start_inner(config: Config) -> Sender<CoreMessage> {
+    fn start_inner(
+        config: Config,
+        connected: Arc<AtomicBool>,
+        external_addr: Arc<Mutex<Option<SocketAddr>>>,
+        frame_budget: Arc<FrameBudget>,
+        send_timing: Arc<SendTiming>,
+        speaking: Arc<AtomicBool>,
+        track_count: Arc<AtomicUsize>,
+    ) -> Sender<CoreMessage> {
         let (tx, rx) = flume::unbounded();
 
-        tasks::start(config, rx, tx.clone());
+        tasks::start(
+            config,
+            rx,
+            tx.clone(),
+            connected,
+            external_addr,
+            frame_budget,
+            send_timing,
+            speaking,
+            track_count,
+        );
 
         tx
     }
 
     fn restart_inner(&mut self) {
-        self.sender = Self::start_inner(self.config.clone());
+        self.connected.store(false, Ordering::Release);
+        *self.external_addr.lock() = None;
+        self.frame_budget.set(None);
+        self.speaking.store(false, Ordering::Release);
+        self.track_count.store(0, Ordering::Release);
+        self.sender = Self::start_inner(
+            self.config.clone(),
+            self.connected.clone(),
+            self.external_addr.clone(),
+            self.frame_budget.clone(),
+            self.send_timing.clone(),
+            self.speaking.clone(),
+            self.track_count.clone(),
+        );
 
         self.mute(self.self_mute);
+        self.set_bitrate(self.bitrate);
+
+        if let Some(sound) = &self.join_sound {
+            let sound = sound.new_handle();
+            self.send(CoreMessage::SetJoinSound(Some(sound)));
+        }
     }
 
     /// Connects to a voice channel using the specified server.
@@ -119,6 +214,75 @@ impl Driver {
         self.send(CoreMessage::Disconnect);
     }
 
+    /// Returns whether the driver currently has an active voice connection.
+    ///
+    /// This becomes `true` before a call to [`connect`] resolves successfully, and
+    /// remains so until the connection is lost or [`leave`] is called. While a dropped
+    /// connection is being reestablished, this returns `false`.
+    ///
+    /// [`connect`]: Driver::connect
+    /// [`leave`]: Driver::leave
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+
+    /// Returns the external IP address and port which were found by IP discovery, and
+    /// advertised to Discord, for the current voice connection.
+    ///
+    /// This is useful when diagnosing one-way audio behind restrictive NATs. This is
+    /// `None` until a connection has completed IP discovery, and while there is no
+    /// active connection.
+    pub fn external_addr(&self) -> Option<SocketAddr> {
+        *self.external_addr.lock()
+    }
+
+    /// Returns the fraction of its 20ms frame budget that the mixer used to
+    /// produce the most recent packet.
+    ///
+    /// This counts the time spent mixing, encoding, encrypting and sending each packet,
+    /// but not the time the mixer spends waiting to send it on schedule.
+    ///
+    /// Values approaching (or exceeding) `1.0` mean that audio may soon stutter:
+    /// callers may want to lower the bitrate, encoder complexity, or number of
+    /// concurrent tracks in response. This is `None` while there is no active
+    /// connection, as no audio is being mixed.
+    pub fn frame_budget_usage(&self) -> Option<f32> {
+        self.frame_budget.get()
+    }
+
+    /// Returns whether the driver is currently sending audio.
+    ///
+    /// This becomes `true` on the first frame of audio mixed, and `false` once
+    /// audio has stopped and the trailing [silent frames] have been sent: silences
+    /// within a track shorter than this (100ms by default) do not affect it.
+    /// Changes are also reported via [`CoreEvent::DriverSpeaking`]. This is always
+    /// `false` while muted or disconnected.
+    ///
+    /// [silent frames]: Config::silence_frames_on_stop
+    /// [`CoreEvent::DriverSpeaking`]: crate::events::CoreEvent::DriverSpeaking
+    pub fn is_speaking(&self) -> bool {
+        self.speaking.load(Ordering::Acquire)
+    }
+
+    /// Returns statistics over the recent intervals between sent voice packets.
+    ///
+    /// This is `None` unless [`Config::send_timing_window`] is set, and until two
+    /// packets have been sent on the current connection.
+    ///
+    /// [`Config::send_timing_window`]: crate::Config::send_timing_window
+    pub fn send_timing(&self) -> Option<SendTimingStats> {
+        self.send_timing.stats()
+    }
+
+    /// Returns the recent intervals between sent voice packets, oldest first.
+    ///
+    /// See [`Config::send_timing_window`] for more details.
+    ///
+    /// [`Config::send_timing_window`]: crate::Config::send_timing_window
+    pub fn send_intervals(&self) -> Vec<Duration> {
+        self.send_timing.intervals()
+    }
+
     /// Sets whether the current connection is to be muted.
     ///
     /// If there is no live voice connection, then this only acts as a settings
@@ -145,6 +309,7 @@ impl Driver {
     #[instrument(skip(self))]
     pub fn play_source(&mut self, source: Input) -> TrackHandle {
         let (player, handle) = super::create_player(source);
+        self.count_new_track();
         self.send(CoreMessage::AddTrack(player));
 
         handle
@@ -159,6 +324,7 @@ impl Driver {
     #[instrument(skip(self))]
     pub fn play_only_source(&mut self, source: Input) -> TrackHandle {
         let (player, handle) = super::create_player(source);
+        self.count_new_track();
         self.send(CoreMessage::SetTrack(Some(player)));
 
         handle
@@ -176,9 +342,79 @@ impl Driver {
     /// [`play_source`]: Driver::play_source
     #[instrument(skip(self))]
     pub fn play(&mut self, track: Track) {
+        self.count_new_track();
         self.send(CoreMessage::AddTrack(track));
     }
 
+    /// Counts a track about to be sent to the mixer, which uncounts it once removed.
+    ///
+    /// Tracks are counted here rather than by the mixer, so that a burst of new
+    /// tracks sees every earlier one, even those still on their way to the mixer.
+    fn count_new_track(&self) {
+        self.track_count.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Reserves room for `count` new tracks, refusing them if they would take the mixer
+    /// past its [maximum number of tracks].
+    ///
+    /// This counts every track held by the mixer, including any it has yet to receive.
+    /// Each reserved track must then be sent via [`play_reserved`], or its room handed
+    /// back via [`release_track_room`].
+    ///
+    /// [maximum number of tracks]: Config::max_concurrent_tracks
+    /// [`play_reserved`]: Driver::play_reserved
+    /// [`release_track_room`]: Driver::release_track_room
+    pub(crate) fn reserve_track_room(&self, count: usize) -> TrackResult<()> {
+        reserve_track_room(&self.track_count, self.config.max_concurrent_tracks, count)
+    }
+
+    /// Hands back room for `count` tracks reserved via [`reserve_track_room`], which
+    /// will not be sent to the mixer.
+    ///
+    /// [`reserve_track_room`]: Driver::reserve_track_room
+    pub(crate) fn release_track_room(&self, count: usize) {
+        self.track_count.fetch_sub(count, Ordering::AcqRel);
+    }
+
+    /// Plays `track`, as in [`play`], using room already reserved via
+    /// [`reserve_track_room`].
+    ///
+    /// [`play`]: Driver::play
+    /// [`reserve_track_room`]: Driver::reserve_track_room
+    pub(crate) fn play_reserved(&mut self, track: Track) {
+        self.send(CoreMessage::AddTrack(track));
+    }
+
+    /// Returns a [`TrackSink`] for sending tracks to this driver's mixer later on.
+    pub(crate) fn track_sink(&self) -> TrackSink {
+        TrackSink {
+            sender: self.sender.clone(),
+            track_count: self.track_count.clone(),
+            max_tracks: self.config.max_concurrent_tracks,
+        }
+    }
+
+    /// Plays audio from a [`Track`] object, reporting whether the mixer accepted it.
+    ///
+    /// As with [`play`], but returns [`TrackError::TooManyTracks`] if the driver
+    /// already holds its [maximum number of tracks], where [`play`] stops the track
+    /// without any error. Returns [`TrackError::Finished`] if the driver's tasks
+    /// have exited.
+    ///
+    /// [`Track`]: crate::tracks::Track
+    /// [`play`]: Driver::play
+    /// [`TrackError::TooManyTracks`]: crate::tracks::TrackError::TooManyTracks
+    /// [`TrackError::Finished`]: crate::tracks::TrackError::Finished
+    /// [maximum number of tracks]: Config::max_concurrent_tracks
+    #[instrument(skip(self))]
+    pub async fn try_play(&mut self, track: Track) -> TrackResult<()> {
+        let (tx, rx) = flume::bounded(1);
+        self.count_new_track();
+        self.send(CoreMessage::AddTrackWithResult(track, tx));
+
+        rx.recv_async().await.map_err(|_| TrackError::Finished)?
+    }
+
     /// Exclusively plays audio from a [`Track`] object.
     ///
     /// This will be one half of the return value of [`create_player`].
@@ -192,6 +428,7 @@ impl Driver {
     /// [`play`]: Driver::play
     #[instrument(skip(self))]
     pub fn play_only(&mut self, track: Track) {
+        self.count_new_track();
         self.send(CoreMessage::SetTrack(Some(track)));
     }
 
@@ -202,11 +439,47 @@ impl Driver {
     /// Sensible values range between `Bits(512)` and `Bits(512_000)`
     /// bits per second.
     /// Alternatively, `Auto` and `Max` remain available.
+    ///
+    /// This takes effect from the next frame the mixer encodes, without
+    /// reconnecting: tracks passed through as Opus are unaffected.
     #[instrument(skip(self))]
     pub fn set_bitrate(&mut self, bitrate: Bitrate) {
+        self.bitrate = bitrate;
         self.send(CoreMessage::SetBitrate(bitrate))
     }
 
+    /// Returns the bitrate most recently set via [`set_bitrate`], which is
+    /// used to encode Opus packets sent along the channel being managed.
+    ///
+    /// [`set_bitrate`]: Driver::set_bitrate
+    #[instrument(skip(self))]
+    pub fn bitrate(&self) -> Bitrate {
+        self.bitrate
+    }
+
+    /// Sets a sound to be played each time this driver successfully connects
+    /// to a voice channel, or removes it if `None`.
+    ///
+    /// The sound is played as its own track: it is mixed alongside, rather than
+    /// replacing, any other audio (such as a queue's current track), and does not
+    /// affect queue state. It does not play on automatic reconnections after a
+    /// network error.
+    ///
+    /// To allow it to be replayed on every connection, `sound` is cached in memory
+    /// as a [`Memory`] source: this is intended for short clips only. This fails if
+    /// the cache cannot be created.
+    ///
+    /// [`Memory`]: crate::input::cached::Memory
+    #[instrument(skip(self))]
+    pub fn set_join_sound(&mut self, sound: Option<Input>) -> InputResult<()> {
+        let sound = sound.map(Memory::new).transpose()?;
+
+        self.join_sound = sound.as_ref().map(Memory::new_handle);
+        self.send(CoreMessage::SetJoinSound(sound));
+
+        Ok(())
+    }
+
     /// Stops playing audio from all sources, if any are set.
     #[instrument(skip(self))]
     pub fn stop(&mut self) {
@@ -276,25 +549,153 @@ impl Driver {
         &self.queue
     }
 
+    /// Replaces this driver's built-in queue with `queue`, returning the previous queue.
+    ///
+    /// Requires the `"builtin-queue"` feature.
+    ///
+    /// Swapping queues does not stop or alter any tracks. All tracks in the previous queue
+    /// remain in this driver, and that queue still advances through them as each ends:
+    /// call [`TrackQueue::stop`] on the returned queue to silence them. Likewise, `queue`
+    /// is used as-is, and should only hold tracks which were added to this driver.
+    ///
+    /// [`TrackQueue::stop`]: TrackQueue::stop
+    pub fn set_queue(&mut self, queue: TrackQueue) -> TrackQueue {
+        std::mem::replace(&mut self.queue, queue)
+    }
+
+    /// Detaches this driver's built-in queue, replacing it with a new, empty queue.
+    ///
+    /// Requires the `"builtin-queue"` feature.
+    ///
+    /// Unlike [`set_queue`], this [stops] every track in the detached queue, so that
+    /// its handlers no longer advance through this driver's tracks. The returned queue
+    /// is empty, but keeps its settings (e.g., volume and preload depth), and can be
+    /// reinstalled via [`set_queue`]. Tracks played directly on this driver are not
+    /// affected.
+    ///
+    /// The replacement queue has default settings, and is used by [`enqueue`] and
+    /// [`queue`] as normal: calling this again detaches (and stops) it in turn.
+    ///
+    /// [`set_queue`]: Driver::set_queue
+    /// [stops]: TrackQueue::stop
+    /// [`enqueue`]: Driver::enqueue
+    /// [`queue`]: Driver::queue
+    pub fn take_queue(&mut self) -> TrackQueue {
+        let queue = std::mem::take(&mut self.queue);
+        queue.stop();
+
+        queue
+    }
+
     /// Adds an audio [`Input`] to this driver's built-in queue.
     ///
     /// Requires the `"builtin-queue"` feature.
     ///
+    /// If the source cannot be queued, it is dropped and the reason is logged:
+    /// use [`try_enqueue_source`] to handle this instead.
+    ///
     /// [`Input`]: crate::input::Input
+    /// [`try_enqueue_source`]: Driver::try_enqueue_source
     pub fn enqueue_source(&mut self, source: Input) {
-        let (mut track, _) = tracks::create_player(source);
-        self.queue.add_raw(&mut track);
-        self.play(track);
+        let (track, _) = crate::tracks::create_player(source);
+        self.enqueue(track);
+    }
+
+    /// Adds an audio [`Input`] to this driver's built-in queue.
+    ///
+    /// Requires the `"builtin-queue"` feature.
+    ///
+    /// Returns all errors returned by [`try_enqueue`].
+    ///
+    /// [`Input`]: crate::input::Input
+    /// [`try_enqueue`]: Driver::try_enqueue
+    pub fn try_enqueue_source(&mut self, source: Input) -> TrackResult<()> {
+        let (track, _) = crate::tracks::create_player(source);
+        self.try_enqueue(track)
     }
 
     /// Adds an existing [`Track`] to this driver's built-in queue.
     ///
     /// Requires the `"builtin-queue"` feature.
     ///
+    /// This is equivalent to calling [`TrackQueue::add`] on [`queue`]: if the track
+    /// cannot be queued, it is dropped and the reason is logged. Use [`try_enqueue`]
+    /// to handle this instead.
+    ///
     /// [`Track`]: crate::tracks::Track
-    pub fn enqueue(&mut self, mut track: Track) {
-        self.queue.add_raw(&mut track);
-        self.play(track);
+    /// [`queue`]: Driver::queue
+    /// [`try_enqueue`]: Driver::try_enqueue
+    pub fn enqueue(&mut self, track: Track) {
+        let queue = self.queue.clone();
+
+        queue.add(track, self);
+    }
+
+    /// Adds an existing [`Track`] to this driver's built-in queue.
+    ///
+    /// Requires the `"builtin-queue"` feature.
+    ///
+    /// This is equivalent to calling [`TrackQueue::try_add`] on [`queue`], and returns
+    /// all of its errors, including refusals by the queue's [source filter].
+    ///
+    /// [`Track`]: crate::tracks::Track
+    /// [`queue`]: Driver::queue
+    /// [source filter]: TrackQueue::set_source_filter
+    pub fn try_enqueue(&mut self, track: Track) -> TrackResult<()> {
+        let queue = self.queue.clone();
+
+        queue.try_add(track, self)
+    }
+}
+
+/// Counts `count` new tracks, unless they would take the mixer past `max_tracks`.
+///
+/// The check and count are made as one atomic step, so that concurrent callers
+/// cannot both claim the last free room.
+fn reserve_track_room(
+    track_count: &AtomicUsize,
+    max_tracks: Option<usize>,
+    count: usize,
+) -> TrackResult<()> {
+    track_count
+        .fetch_update(
+            Ordering::AcqRel,
+            Ordering::Acquire,
+            |current| match max_tracks {
+                Some(max) if current + count > max => None,
+                _ => Some(current + count),
+            },
+        )
+        .map(|_| ())
+        .map_err(|_| TrackError::TooManyTracks)
+}
+
+/// Sends tracks to a [`Driver`]'s mixer without borrowing the driver, e.g., from
+/// within an event handler.
+///
+/// Unlike a [`Driver`], this may be freely cloned and dropped. It keeps to the
+/// track limit in place when it was made, and fails to send once the driver
+/// restarts its tasks.
+#[derive(Clone, Debug)]
+pub(crate) struct TrackSink {
+    sender: Sender<CoreMessage>,
+    track_count: Arc<AtomicUsize>,
+    max_tracks: Option<usize>,
+}
+
+impl TrackSink {
+    /// Plays `track`, as in [`Driver::play`].
+    ///
+    /// Returns [`TrackError::TooManyTracks`] if the mixer already holds its
+    /// maximum number of tracks, or [`TrackError::Finished`] if the driver's
+    /// tasks have exited.
+    pub(crate) fn play(&self, track: Track) -> TrackResult<()> {
+        reserve_track_room(&self.track_count, self.max_tracks, 1)?;
+
+        self.sender.send(CoreMessage::AddTrack(track)).map_err(|_| {
+            self.track_count.fetch_sub(1, Ordering::AcqRel);
+            TrackError::Finished
+        })
     }
 }
 
@@ -0,0 +1,155 @@
+use crate::constants::TIMESTEP_LENGTH;
+use parking_lot::Mutex;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Longest gap between two packets which is still counted as an interval,
+/// rather than a pause in transmission (e.g., while no tracks are playing).
+const MAX_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Summary of the intervals between recently sent voice packets.
+///
+/// Packets should be sent every 20ms: values which stray from this indicate
+/// that the mixer or UDP transmit task are not being scheduled on time, and
+/// that listeners may hear choppy or "robotic" audio.
+///
+/// See [`Config::send_timing_window`] for details on how these are recorded.
+///
+/// [`Config::send_timing_window`]: crate::Config::send_timing_window
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SendTimingStats {
+    /// Number of intervals included in these statistics.
+    pub samples: usize,
+    /// Mean time between packets.
+    pub mean: Duration,
+    /// Mean difference between each interval and the ideal 20ms.
+    pub jitter: Duration,
+    /// Longest time between packets.
+    pub max: Duration,
+}
+
+/// Record of the intervals between sent voice packets.
+///
+/// This is shared between a [`Driver`] and its UDP transmit task.
+///
+/// [`Driver`]: crate::driver::Driver
+#[derive(Debug, Default)]
+pub struct SendTiming(Mutex<SendTimingInner>);
+
+#[derive(Debug, Default)]
+struct SendTimingInner {
+    window: usize,
+    last_send: Option<Instant>,
+    intervals: VecDeque<Duration>,
+}
+
+impl SendTiming {
+    /// Creates an empty record, which ignores all packets until [`reset`] is called.
+    ///
+    /// [`reset`]: SendTiming::reset
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Discards all recorded intervals, keeping at most `window` from now on.
+    pub fn reset(&self, window: usize) {
+        let mut inner = self.0.lock();
+
+        inner.window = window;
+        inner.last_send = None;
+        inner.intervals.clear();
+    }
+
+    /// Notes that a packet was sent at `now`.
+    pub fn record(&self, now: Instant) {
+        let mut inner = self.0.lock();
+
+        if inner.window == 0 {
+            return;
+        }
+
+        if let Some(last) = inner.last_send.replace(now) {
+            let interval = now.saturating_duration_since(last);
+
+            if interval <= MAX_INTERVAL {
+                if inner.intervals.len() >= inner.window {
+                    inner.intervals.pop_front();
+                }
+
+                inner.intervals.push_back(interval);
+            }
+        }
+    }
+
+    /// Returns all recorded intervals, oldest first.
+    pub fn intervals(&self) -> Vec<Duration> {
+        self.0.lock().intervals.iter().copied().collect()
+    }
+
+    /// Summarises all recorded intervals, if there are any.
+    pub fn stats(&self) -> Option<SendTimingStats> {
+        let inner = self.0.lock();
+        let samples = inner.intervals.len();
+
+        if samples == 0 {
+            return None;
+        }
+
+        let total: Duration = inner.intervals.iter().sum();
+        let deviation: Duration = inner
+            .intervals
+            .iter()
+            .map(|i| {
+                i.checked_sub(TIMESTEP_LENGTH)
+                    .unwrap_or_else(|| TIMESTEP_LENGTH - *i)
+            })
+            .sum();
+
+        Some(SendTimingStats {
+            samples,
+            mean: total / samples as u32,
+            jitter: deviation / samples as u32,
+            max: inner.intervals.iter().copied().max().unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_summarise_recent_intervals() {
+        let timing = SendTiming::new();
+        let start = Instant::now();
+
+        // Disabled until a window is set.
+        timing.record(start);
+        timing.record(start + TIMESTEP_LENGTH);
+        assert_eq!(timing.stats(), None);
+
+        timing.reset(3);
+        for offset_ms in &[0, 20, 40, 70, 90] {
+            timing.record(start + Duration::from_millis(*offset_ms));
+        }
+
+        // The first interval has fallen out of the window.
+        let ms = Duration::from_millis;
+        assert_eq!(timing.intervals(), vec![ms(20), ms(30), ms(20)]);
+        assert_eq!(
+            timing.stats(),
+            Some(SendTimingStats {
+                samples: 3,
+                mean: Duration::from_nanos(23_333_333),
+                jitter: Duration::from_nanos(3_333_333),
+                max: ms(30),
+            })
+        );
+
+        // Long pauses are not counted as intervals.
+        timing.record(start + Duration::from_secs(5));
+        assert_eq!(timing.stats().unwrap().samples, 3);
+    }
+}
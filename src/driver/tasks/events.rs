@@ -87,6 +87,20 @@ pub(crate) async fn runner(_interconnect: Interconnect, evt_rx: Receiver<EventMe
                             global.fire_track_event(TrackEvent::Loop, i);
                         }
                     },
+                    Passthrough(usage) => {
+                        state.passthrough_usage = Some(usage);
+                    },
+                    Ready => {
+                        global.fire_track_event(TrackEvent::Ready, i);
+                    },
+                    Started(at) => {
+                        state.first_frame_at = Some(at);
+                        state.last_frame_at = Some(at);
+                        global.fire_track_event(TrackEvent::Started, i);
+                    },
+                    LastFrame(at) => {
+                        state.last_frame_at = Some(at);
+                    },
                     Total(new) => {
                         // Massive, unprecedented state changes.
                         *state = new;
@@ -3,7 +3,8 @@
 use crate::{
     driver::{connection::error::Error, Bitrate, Config},
     events::{context_data::DisconnectReason, EventData},
-    tracks::Track,
+    input::cached::Memory,
+    tracks::{Track, TrackResult},
     ConnectionInfo,
 };
 use flume::Sender;
@@ -17,7 +18,9 @@ pub enum CoreMessage {
     Disconnect,
     SetTrack(Option<Track>),
     AddTrack(Track),
+    AddTrackWithResult(Track, Sender<TrackResult<()>>),
     SetBitrate(Bitrate),
+    SetJoinSound(Option<Memory>),
     AddEvent(EventData),
     RemoveGlobalEvents,
     SetConfig(Config),
@@ -2,9 +2,9 @@
 
 use crate::{
     events::{CoreContext, EventData, EventStore},
-    tracks::{LoopState, PlayMode, TrackHandle, TrackState},
+    tracks::{LoopState, PassthroughUsage, PlayMode, TrackHandle, TrackState},
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub enum EventMessage {
     // Event related.
@@ -30,5 +30,10 @@ pub enum TrackStateChange {
     Position(Duration),
     // Bool indicates user-set.
     Loops(LoopState, bool),
+    Passthrough(PassthroughUsage),
+    Ready,
+    // Mixer timestamps of a track's first and most recent frames of audio.
+    Started(Instant),
+    LastFrame(Instant),
     Total(TrackState),
 }
@@ -4,7 +4,7 @@ use super::{Interconnect, UdpRxMessage, UdpTxMessage, WsMessage};
 
 use crate::{
     driver::{Bitrate, Config, CryptoState},
-    tracks::Track,
+    tracks::{Track, TrackResult},
 };
 use flume::Sender;
 use xsalsa20poly1305::XSalsa20Poly1305 as Cipher;
@@ -16,6 +16,19 @@ pub struct MixerConnection {
     pub udp_tx: Sender<UdpTxMessage>,
 }
 
+impl MixerConnection {
+    /// Counts the messages not yet received by the UDP receive and send
+    /// tasks, as `(rx, tx)`.
+    ///
+    /// As with [`Interconnect::backlog`], this is an instantaneous snapshot.
+    ///
+    /// [`Interconnect::backlog`]: super::Interconnect::backlog
+    #[cfg(any(test, feature = "internals"))]
+    pub fn udp_backlog(&self) -> (usize, usize) {
+        (self.udp_rx.len(), self.udp_tx.len())
+    }
+}
+
 impl Drop for MixerConnection {
     fn drop(&mut self) {
         let _ = self.udp_rx.send(UdpRxMessage::Poison);
@@ -25,6 +38,7 @@ impl Drop for MixerConnection {
 
 pub enum MixerMessage {
     AddTrack(Track),
+    AddTrackWithResult(Track, Sender<TrackResult<()>>),
     SetTrack(Option<Track>),
 
     SetBitrate(Bitrate),
@@ -24,7 +24,32 @@ pub struct Interconnect {
     pub mixer: Sender<MixerMessage>,
 }
 
+/// Number of messages waiting in each of an [`Interconnect`]'s channels.
+///
+/// [`Interconnect`]: Interconnect
+#[cfg(any(test, feature = "internals"))]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct InterconnectBacklog {
+    pub core: usize,
+    pub events: usize,
+    pub mixer: usize,
+}
+
 impl Interconnect {
+    /// Counts the messages not yet received by the core, event, and mixer tasks.
+    ///
+    /// This is an instantaneous snapshot, which may be outdated as soon as it is
+    /// returned. A count which grows steadily over several snapshots suggests that
+    /// the receiving task has stalled.
+    #[cfg(any(test, feature = "internals"))]
+    pub fn backlog(&self) -> InterconnectBacklog {
+        InterconnectBacklog {
+            core: self.core.len(),
+            events: self.events.len(),
+            mixer: self.mixer.len(),
+        }
+    }
+
     pub fn poison(&self) {
         let _ = self.events.send(EventMessage::Poison);
     }
@@ -1,6 +1,12 @@
 #![allow(missing_docs)]
 
+use std::time::Instant;
+
 pub enum UdpTxMessage {
     Packet(Vec<u8>), // TODO: do something cheaper.
+    /// A packet to be sent no earlier than the given time.
+    ///
+    /// These are always sent in the order they are received.
+    Scheduled(Vec<u8>, Instant),
     Poison,
 }
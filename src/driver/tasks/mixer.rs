@@ -1,7 +1,12 @@
 use super::{disposal, error::Result, message::*};
 use crate::{
     constants::*,
-    tracks::{PlayMode, Track},
+    driver::MixLevel,
+    events::{
+        context_data::{CullReason, TrackCullData},
+        CoreContext,
+    },
+    tracks::{PassthroughDecision, PassthroughUsage, PlayMode, Track, TrackError},
     Config,
 };
 use audiopus::{
@@ -10,6 +15,7 @@ use audiopus::{
     Application as CodingMode,
     Bitrate,
     Channels,
+    Result as OpusResult,
 };
 use discortp::{
     rtp::{MutableRtpPacket, RtpPacket},
@@ -18,14 +24,59 @@ use discortp::{
 use flume::{Receiver, Sender, TryRecvError};
 use rand::random;
 use spin_sleep::SpinSleeper;
-use std::time::Instant;
+use std::{
+    io::ErrorKind as IoErrorKind,
+    mem,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 #[cfg(not(feature = "tokio-02-marker"))]
 use tokio::runtime::Handle;
 #[cfg(feature = "tokio-02-marker")]
 use tokio_compat::runtime::Handle;
-use tracing::{debug, error, instrument};
+use tracing::{debug, error, instrument, warn};
 use xsalsa20poly1305::TAG_SIZE;
 
+/// Fraction of the frame budget (20ms) used by the mixer's most recent cycle.
+///
+/// This is shared between a [`Driver`] and its mixer thread.
+///
+/// [`Driver`]: crate::driver::Driver
+#[derive(Debug)]
+pub struct FrameBudget(AtomicU32);
+
+impl FrameBudget {
+    /// Marks that the mixer is not running cycles. This is a NaN,
+    /// so cannot collide with any real measurement.
+    const IDLE: u32 = u32::MAX;
+
+    pub fn new() -> Self {
+        Self(AtomicU32::new(Self::IDLE))
+    }
+
+    pub fn get(&self) -> Option<f32> {
+        match self.0.load(Ordering::Acquire) {
+            Self::IDLE => None,
+            bits => Some(f32::from_bits(bits)),
+        }
+    }
+
+    pub fn set(&self, usage: Option<f32>) {
+        let bits = usage.map_or(Self::IDLE, f32::to_bits);
+
+        self.0.store(bits, Ordering::Release);
+    }
+}
+
+impl Default for FrameBudget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct Mixer {
     pub async_handle: Handle,
     pub bitrate: Bitrate,
@@ -34,26 +85,43 @@ pub struct Mixer {
     pub deadline: Instant,
     pub disposer: Sender<DisposalMessage>,
     pub encoder: OpusEncoder,
+    pub frame_budget: Arc<FrameBudget>,
     pub interconnect: Interconnect,
+    pub marker_pending: bool,
     pub mix_rx: Receiver<MixerMessage>,
     pub muted: bool,
     pub packet: [u8; VOICE_PACKET_MAX],
+    pub pending_pcm: Vec<f32>,
     pub prevent_events: bool,
     pub silence_frames: u8,
     pub skip_sleep: bool,
     pub sleeper: SpinSleeper,
     pub soft_clip: SoftClip,
+    pub speaking: Arc<AtomicBool>,
+    pub ssrc_override: Option<u32>,
+    pub track_count: Arc<AtomicUsize>,
     pub tracks: Vec<Track>,
     pub ws: Option<Sender<WsMessage>>,
 }
 
-fn new_encoder(bitrate: Bitrate) -> Result<OpusEncoder> {
+fn new_encoder(bitrate: Bitrate, config: &Config) -> OpusResult<OpusEncoder> {
     let mut encoder = OpusEncoder::new(SAMPLE_RATE, Channels::Stereo, CodingMode::Audio)?;
     encoder.set_bitrate(bitrate)?;
+    apply_encoder_config(&mut encoder, config)?;
 
     Ok(encoder)
 }
 
+fn apply_encoder_config(encoder: &mut OpusEncoder, config: &Config) -> OpusResult<()> {
+    if let Some(complexity) = config.encoder_complexity {
+        encoder.set_complexity(complexity)?;
+    }
+    encoder.set_inband_fec(config.encoder_fec)?;
+    encoder.set_packet_loss_perc(config.encoder_packet_loss)?;
+
+    Ok(())
+}
+
 impl Mixer {
     pub fn new(
         mix_rx: Receiver<MixerMessage>,
@@ -62,7 +130,11 @@ impl Mixer {
         config: Config,
     ) -> Self {
         let bitrate = DEFAULT_BITRATE;
-        let encoder = new_encoder(bitrate)
+        let encoder = new_encoder(bitrate, &config)
+            .or_else(|e| {
+                error!("Failed to apply encoder config. Using defaults. {:?}", e);
+                new_encoder(bitrate, &Default::default())
+            })
             .expect("Failed to create encoder in mixing thread with known-good values.");
         let soft_clip = SoftClip::new(Channels::Stereo);
 
@@ -91,20 +163,45 @@ impl Mixer {
             deadline: Instant::now(),
             disposer,
             encoder,
+            frame_budget: Default::default(),
             interconnect,
+            marker_pending: true,
             mix_rx,
             muted: false,
             packet,
+            pending_pcm: Vec::new(),
             prevent_events: false,
             silence_frames: 0,
             skip_sleep: false,
             sleeper: Default::default(),
             soft_clip,
+            speaking: Default::default(),
+            ssrc_override: None,
+            track_count: Default::default(),
             tracks,
             ws: None,
         }
     }
 
+    /// Forces all outgoing packets to use the given SSRC, rather than the one
+    /// assigned by Discord when connecting. `None` restores the assigned SSRC
+    /// from the next connection onwards.
+    ///
+    /// This is intended for test harnesses which inspect or replay generated packets:
+    /// Discord will not forward audio whose SSRC does not match its assignment.
+    #[cfg(any(test, feature = "internals"))]
+    pub fn override_ssrc(&mut self, ssrc: Option<u32>) {
+        self.ssrc_override = ssrc;
+
+        if let Some(ssrc) = ssrc {
+            let mut rtp = MutableRtpPacket::new(&mut self.packet[..]).expect(
+                "Too few bytes in self.packet for RTP header.\
+                    (Blame: VOICE_PACKET_MAX?)",
+            );
+            rtp.set_ssrc(ssrc);
+        }
+    }
+
     fn run(&mut self) {
         let mut events_failure = false;
         let mut conn_failure = false;
@@ -181,6 +278,8 @@ impl Mixer {
 
             if conn_failure {
                 self.conn_active = None;
+                self.frame_budget.set(None);
+                self.set_speaking(false);
                 let sent = self.interconnect.core.send(CoreMessage::FullReconnect);
                 conn_failure = false;
 
@@ -200,17 +299,64 @@ impl Mixer {
         use MixerMessage::*;
 
         let error = match msg {
-            AddTrack(mut t) => {
-                t.source.prep_with_handle(self.async_handle.clone());
-                self.add_track(t)
-            },
+            AddTrack(mut t) =>
+                if self.at_track_limit() {
+                    warn!("Discarding track: mixer is at its track limit.");
+
+                    // Stopped tracks are culled before mixing, so this never plays,
+                    // but still fires its end events (e.g., to advance a queue).
+                    let out = self.add_track(t);
+                    if let Some(t) = self.tracks.last_mut() {
+                        t.stop();
+                    }
+                    out
+                } else {
+                    t.source.prep_with_handle(
+                        self.async_handle.clone(),
+                        self.config.input_init_limit.clone(),
+                    );
+                    self.add_track(t)
+                },
+            AddTrackWithResult(mut t, tx) =>
+                if self.at_track_limit() {
+                    let _ = tx.send(Err(TrackError::TooManyTracks));
+
+                    // As in `AddTrack`.
+                    let out = self.add_track(t);
+                    if let Some(t) = self.tracks.last_mut() {
+                        t.stop();
+                    }
+                    out
+                } else {
+                    t.source.prep_with_handle(
+                        self.async_handle.clone(),
+                        self.config.input_init_limit.clone(),
+                    );
+                    let out = self.add_track(t);
+                    let _ = tx.send(Ok(()));
+                    out
+                },
             SetTrack(t) => {
-                self.tracks.clear();
+                let culled: Vec<_> = self.tracks.drain(..).map(|t| t.uuid).collect();
+                self.release_tracks(culled.len());
 
                 let mut out = self.fire_event(EventMessage::RemoveAllTracks);
+                for uuid in culled {
+                    if let Err(e) = self.fire_event(EventMessage::FireCoreEvent(
+                        CoreContext::TrackCulled(TrackCullData {
+                            uuid,
+                            reason: CullReason::Stop,
+                        }),
+                    )) {
+                        out = Err(e);
+                    }
+                }
 
                 if let Some(mut t) = t {
-                    t.source.prep_with_handle(self.async_handle.clone());
+                    t.source.prep_with_handle(
+                        self.async_handle.clone(),
+                        self.config.input_init_limit.clone(),
+                    );
 
                     // Do this unconditionally: this affects local state infallibly,
                     // with the event installation being the remote part.
@@ -238,14 +384,18 @@ impl Mixer {
                     "Too few bytes in self.packet for RTP header.\
                         (Blame: VOICE_PACKET_MAX?)",
                 );
-                rtp.set_ssrc(ssrc);
+                rtp.set_ssrc(self.ssrc_override.unwrap_or(ssrc));
                 rtp.set_sequence(random::<u16>().into());
                 rtp.set_timestamp(random::<u32>().into());
+                self.marker_pending = true;
                 self.deadline = Instant::now();
+                self.pending_pcm.clear();
                 Ok(())
             },
             DropConn => {
                 self.conn_active = None;
+                self.frame_budget.set(None);
+                self.set_speaking(false);
                 Ok(())
             },
             ReplaceInterconnect(i) => {
@@ -264,6 +414,10 @@ impl Mixer {
                 self.rebuild_tracks()
             },
             SetConfig(new_config) => {
+                if new_config.frame_length != self.config.frame_length {
+                    self.pending_pcm.clear();
+                }
+
                 self.config = new_config.clone();
 
                 if self.tracks.capacity() < self.config.preallocated_tracks {
@@ -271,6 +425,10 @@ impl Mixer {
                         .reserve(self.config.preallocated_tracks - self.tracks.len());
                 }
 
+                if let Err(e) = apply_encoder_config(&mut self.encoder, &self.config) {
+                    error!("Failed to apply encoder config. {:?}", e);
+                }
+
                 if let Some(conn) = &self.conn_active {
                     conn_failure |= conn
                         .udp_rx
@@ -280,7 +438,7 @@ impl Mixer {
 
                 Ok(())
             },
-            RebuildEncoder => match new_encoder(self.bitrate) {
+            RebuildEncoder => match new_encoder(self.bitrate, &self.config) {
                 Ok(encoder) => {
                     self.encoder = encoder;
                     Ok(())
@@ -288,7 +446,7 @@ impl Mixer {
                 Err(e) => {
                     error!("Failed to rebuild encoder. Resetting bitrate. {:?}", e);
                     self.bitrate = DEFAULT_BITRATE;
-                    self.encoder = new_encoder(self.bitrate)
+                    self.encoder = new_encoder(self.bitrate, &Default::default())
                         .expect("Failed fallback rebuild of OpusEncoder with safe inputs.");
                     Ok(())
                 },
@@ -323,6 +481,25 @@ impl Mixer {
         }
     }
 
+    /// Uncounts tracks which have been discarded or removed, as counted when
+    /// they were sent to the mixer.
+    #[inline]
+    fn release_tracks(&self, count: usize) {
+        let _ = self
+            .track_count
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                Some(n.saturating_sub(count))
+            });
+    }
+
+    #[inline]
+    fn at_track_limit(&self) -> bool {
+        self.config
+            .max_concurrent_tracks
+            .map(|max| self.tracks.len() >= max)
+            .unwrap_or(false)
+    }
+
     #[inline]
     fn add_track(&mut self, mut track: Track) -> Result<()> {
         let evts = track.events.take().unwrap_or_default();
@@ -363,25 +540,56 @@ impl Mixer {
             // detect that on the tick later.
             // Changes to play state etc. MUST all be handled.
             track.process_commands(i, &self.interconnect);
+
+            if !track.ready && track.source.reader.is_ready() {
+                track.ready = true;
+                let _ = self
+                    .interconnect
+                    .events
+                    .send(EventMessage::ChangeState(i, TrackStateChange::Ready));
+            }
         }
 
         // TODO: do without vec?
         let mut i = 0;
         let mut to_remove = Vec::with_capacity(self.tracks.len());
+        let max_duration = self.config.max_track_duration;
         while i < self.tracks.len() {
             let track = self
                 .tracks
                 .get_mut(i)
                 .expect("Tried to remove an illegal track index.");
 
+            if matches!(max_duration, Some(max) if track.play_time >= max) {
+                track.stop();
+            }
+
             if track.playing.is_done() {
                 let p_state = track.playing();
+                let reason = match p_state {
+                    PlayMode::End if track.errored => CullReason::Error,
+                    PlayMode::End => CullReason::End,
+                    _ => CullReason::Stop,
+                };
                 let to_drop = self.tracks.swap_remove(i);
                 to_remove.push(i);
+                self.release_tracks(1);
+                if let Some(at) = to_drop.last_frame_at {
+                    self.fire_event(EventMessage::ChangeState(
+                        i,
+                        TrackStateChange::LastFrame(at),
+                    ))?;
+                }
                 self.fire_event(EventMessage::ChangeState(
                     i,
                     TrackStateChange::Mode(p_state),
                 ))?;
+                self.fire_event(EventMessage::FireCoreEvent(CoreContext::TrackCulled(
+                    TrackCullData {
+                        uuid: to_drop.uuid,
+                        reason,
+                    },
+                )))?;
                 let _ = self.disposer.send(DisposalMessage::Track(to_drop));
             } else {
                 i += 1;
@@ -399,6 +607,14 @@ impl Mixer {
         Ok(())
     }
 
+    /// Records the fraction of the frame budget spent working this cycle, which
+    /// excludes any time spent waiting for the deadline.
+    #[inline]
+    fn record_frame_budget(&self, busy: Duration) {
+        let usage = busy.as_secs_f32() / TIMESTEP_LENGTH.as_secs_f32();
+        self.frame_budget.set(Some(usage));
+    }
+
     #[inline]
     fn march_deadline(&mut self) {
         if self.skip_sleep {
@@ -412,7 +628,10 @@ impl Mixer {
     }
 
     pub fn cycle(&mut self) -> Result<()> {
+        let cycle_start = Instant::now();
+        let send_at = self.deadline + TIMESTEP_LENGTH * self.config.prerender_frames as u32;
         let mut mix_buffer = [0f32; STEREO_FRAME_SIZE];
+        let frame_samples = self.config.frame_length.samples();
 
         // Walk over all the audio files, combining into one audio frame according
         // to volume, play state, etc.
@@ -431,17 +650,28 @@ impl Mixer {
                 &mut self.tracks,
                 &self.interconnect,
                 self.prevent_events,
+                frame_samples == STEREO_FRAME_SIZE,
             )
         };
 
         self.soft_clip.apply(&mut mix_buffer[..])?;
 
+        if let (Some(levels), MixType::MixedPcm(_)) = (&self.config.mix_levels, &mix_len) {
+            let _ = levels.try_send(MixLevel::measure(&mix_buffer[..]));
+        }
+
         if self.muted {
             mix_len = MixType::MixedPcm(0);
         }
 
         if mix_len == MixType::MixedPcm(0) {
-            if self.silence_frames > 0 {
+            if self.silence_frames > 0 && frame_samples != STEREO_FRAME_SIZE {
+                self.silence_frames -= 1;
+
+                // SILENT_FRAME holds exactly 20ms, so other frames must encode silence.
+                mix_buffer = [0f32; STEREO_FRAME_SIZE];
+                mix_len = MixType::MixedPcm(STEREO_FRAME_SIZE);
+            } else if self.silence_frames > 0 {
                 self.silence_frames -= 1;
 
                 // Explicit "Silence" frame.
@@ -457,7 +687,16 @@ impl Mixer {
 
                 mix_len = MixType::Passthrough(SILENT_FRAME.len());
             } else {
-                // Per official guidelines, send 5x silence BEFORE we stop speaking.
+                if !self.pending_pcm.is_empty() {
+                    let pcm = self.take_pending_pcm();
+                    let sent =
+                        self.prep_and_send_packet(&pcm[..], MixType::MixedPcm(pcm.len()), send_at);
+                    self.return_pending_pcm(pcm);
+                    sent?;
+                }
+
+                // Per official guidelines, send 5x silence BEFORE we stop speaking
+                // (unless configured otherwise).
                 if let Some(ws) = &self.ws {
                     // NOTE: this should prevent a catastrophic thread pileup.
                     // A full reconnect might cause an inner closed connection.
@@ -466,22 +705,95 @@ impl Mixer {
                     let _ = ws.send(WsMessage::Speaking(false));
                 }
 
+                self.set_speaking(false);
+
+                self.marker_pending = true;
+
+                let busy = cycle_start.elapsed();
                 self.march_deadline();
+                self.record_frame_budget(busy);
 
                 return Ok(());
             }
         } else {
-            self.silence_frames = 5;
+            self.silence_frames = self.config.silence_frames_on_stop;
+            self.set_speaking(true);
         }
 
         if let Some(ws) = &self.ws {
             ws.send(WsMessage::Speaking(true))?;
         }
 
+        let mixed = cycle_start.elapsed();
         self.march_deadline();
-        self.prep_and_send_packet(mix_buffer, mix_len)?;
+        let send_start = Instant::now();
 
-        Ok(())
+        let sent = if frame_samples == STEREO_FRAME_SIZE {
+            self.prep_and_send_packet(&mix_buffer[..], mix_len, send_at)
+        } else if frame_samples < STEREO_FRAME_SIZE {
+            let frame_length = self.config.frame_length.duration();
+            let mut sent = Ok(());
+
+            for (i, part) in mix_buffer.chunks(frame_samples).enumerate() {
+                let at = send_at + frame_length * i as u32;
+                sent = self.prep_and_send_packet(part, MixType::MixedPcm(part.len()), at);
+
+                if sent.is_err() {
+                    break;
+                }
+            }
+
+            sent
+        } else {
+            self.pending_pcm.extend_from_slice(&mix_buffer[..]);
+
+            if self.pending_pcm.len() >= frame_samples {
+                let pcm = self.take_pending_pcm();
+                let sent =
+                    self.prep_and_send_packet(&pcm[..], MixType::MixedPcm(pcm.len()), send_at);
+                self.return_pending_pcm(pcm);
+                sent
+            } else {
+                Ok(())
+            }
+        };
+
+        // Encoding and encryption happen after the deadline, but still count.
+        self.record_frame_budget(mixed + send_start.elapsed());
+
+        sent
+    }
+
+    /// Takes all audio held for a longer [`FrameLength`], padding the frame with
+    /// silence if it is incomplete.
+    ///
+    /// [`FrameLength`]: crate::driver::FrameLength
+    fn take_pending_pcm(&mut self) -> Vec<f32> {
+        let mut pcm = mem::take(&mut self.pending_pcm);
+        pcm.resize(self.config.frame_length.samples(), 0.0);
+
+        pcm
+    }
+
+    /// Returns a buffer from [`take_pending_pcm`] once sent, to reuse its allocation.
+    ///
+    /// [`take_pending_pcm`]: Mixer::take_pending_pcm
+    fn return_pending_pcm(&mut self, mut pcm: Vec<f32>) {
+        pcm.clear();
+        self.pending_pcm = pcm;
+    }
+
+    /// Records whether the mixer is sending audio, firing [`CoreEvent::DriverSpeaking`]
+    /// if this has changed.
+    ///
+    /// [`CoreEvent::DriverSpeaking`]: crate::events::CoreEvent::DriverSpeaking
+    fn set_speaking(&mut self, speaking: bool) {
+        if self.speaking.swap(speaking, Ordering::AcqRel) != speaking {
+            // Failure of the event thread is detected on the next tick.
+            let _ = self.fire_event(EventMessage::FireCoreEvent(CoreContext::DriverSpeaking(
+                speaking,
+            )));
+        }
     }
 
     fn set_bitrate(&mut self, bitrate: Bitrate) -> Result<()> {
@@ -489,7 +801,12 @@ impl Mixer {
     }
 
     #[inline]
-    fn prep_and_send_packet(&mut self, buffer: [f32; 1920], mix_len: MixType) -> Result<()> {
+    fn prep_and_send_packet(
+        &mut self,
+        buffer: &[f32],
+        mix_len: MixType,
+        send_at: Instant,
+    ) -> Result<()> {
         let conn = self
             .conn_active
             .as_mut()
@@ -508,13 +825,13 @@ impl Mixer {
                 MixType::Passthrough(opus_len) => opus_len,
                 MixType::MixedPcm(_samples) => {
                     let total_payload_space = payload.len() - crypto_mode.payload_suffix_len();
-                    self.encoder.encode_float(
-                        &buffer[..STEREO_FRAME_SIZE],
-                        &mut payload[TAG_SIZE..total_payload_space],
-                    )?
+                    self.encoder
+                        .encode_float(buffer, &mut payload[TAG_SIZE..total_payload_space])?
                 },
             };
 
+            rtp.set_marker((self.marker_pending && self.config.rtp_marker_after_silence) as u8);
+
             let final_payload_size = conn
                 .crypto_state
                 .write_packet_nonce(&mut rtp, TAG_SIZE + payload_len);
@@ -531,15 +848,25 @@ impl Mixer {
         // TODO: This is dog slow, don't do this.
         // Can we replace this with a shared ring buffer + semaphore?
         // i.e., do something like double/triple buffering in graphics.
-        conn.udp_tx
-            .send(UdpTxMessage::Packet(self.packet[..index].to_vec()))?;
+        let packet = self.packet[..index].to_vec();
+        // The later halves of split 10ms frames must wait, even without prerendering.
+        conn.udp_tx.send(
+            if self.config.prerender_frames == 0 && send_at <= Instant::now() {
+                UdpTxMessage::Packet(packet)
+            } else {
+                UdpTxMessage::Scheduled(packet, send_at)
+            },
+        )?;
 
         let mut rtp = MutableRtpPacket::new(&mut self.packet[..]).expect(
             "FATAL: Too few bytes in self.packet for RTP header.\
                 (Blame: VOICE_PACKET_MAX?)",
         );
         rtp.set_sequence(rtp.get_sequence() + 1);
-        rtp.set_timestamp(rtp.get_timestamp() + MONO_FRAME_SIZE as u32);
+        let step = self.config.rtp_timestamp_step as usize * buffer.len() / STEREO_FRAME_SIZE;
+        rtp.set_timestamp(rtp.get_timestamp() + step as u32);
+        rtp.set_marker(0);
+        self.marker_pending = false;
 
         Ok(())
     }
@@ -558,34 +885,120 @@ fn mix_tracks<'a>(
     tracks: &mut Vec<Track>,
     interconnect: &Interconnect,
     prevent_events: bool,
+    allow_passthrough: bool,
 ) -> MixType {
     let mut len = 0;
+    let now = Instant::now();
 
     // Opus frame passthrough.
     // This requires that we have only one track, who has volume 1.0, and an
     // Opus codec type.
-    let do_passthrough = tracks.len() == 1 && {
-        let track = &tracks[0];
-        (track.volume - 1.0).abs() < f32::EPSILON && track.source.supports_passthrough()
-    };
+    let track_count = tracks.len();
+    let do_passthrough = track_count == 1
+        && passthrough_decision(&tracks[0], track_count, allow_passthrough).is_passthrough();
+
+    // Any playing ducking sources lower the volume of all other tracks.
+    let duck_gain = tracks
+        .iter()
+        .filter(|track| track.playing == PlayMode::Play)
+        .filter_map(|track| track.ducking)
+        .fold(1.0, f32::min);
 
     for (i, track) in tracks.iter_mut().enumerate() {
-        let vol = track.volume;
-        let stream = &mut track.source;
+        let vol = if track.ducking.is_some() {
+            track.volume
+        } else {
+            track.volume * duck_gain
+        };
+        if track.draining {
+            // Keep pace with a paused live source, so that it resumes from fresh audio.
+            let mut discard = [0f32; STEREO_FRAME_SIZE];
+            let _ = track.source.mix(&mut discard, 0.0);
+            continue;
+        }
 
         if track.playing != PlayMode::Play {
             continue;
         }
 
+        track.passthrough = Some(passthrough_decision(track, track_count, allow_passthrough));
+        let stream = &mut track.source;
+
         let (temp_len, opus_len) = if do_passthrough {
-            (0, track.source.read_opus_frame(opus_frame).ok())
+            match track.source.read_opus_frame(opus_frame) {
+                Ok(len) => (Some(0), Some(len)),
+                // As when decoding, a truncated input is just the end of the track.
+                Err(e) if e.kind() == IoErrorKind::UnexpectedEof => (Some(0), None),
+                Err(e) => {
+                    error!("Input died unexpectedly: {:?}", e);
+                    (None, None)
+                },
+            }
+        } else if track.effects.is_empty() {
+            (stream.mix_checked(mix_buffer, vol), None)
         } else {
-            (stream.mix(mix_buffer, vol), None)
+            let mut track_buffer = [0f32; STEREO_FRAME_SIZE];
+            let len = stream.mix_checked(&mut track_buffer, 1.0);
+
+            for effect in track.effects.iter_mut() {
+                effect.process(&mut track_buffer[..], 2);
+            }
+
+            for (mixed, sample) in mix_buffer.iter_mut().zip(&track_buffer[..]) {
+                *mixed += vol * sample;
+            }
+
+            (len, None)
         };
 
+        track.errored |= temp_len.is_none();
+        let temp_len = temp_len.unwrap_or(0);
+        track.decode_errors = track
+            .decode_errors
+            .saturating_add(track.source.take_decode_errors());
+
         len = len.max(temp_len);
+        if let Some(opus_len) = opus_len {
+            track.stats.frames_passed_through += 1;
+            track.packet_sizes.record(opus_len);
+        } else if temp_len > 0 {
+            track.stats.frames_decoded += 1;
+            // `temp_len` counts bytes of float PCM.
+            track.stats.samples_mixed += (temp_len / mem::size_of::<f32>()) as u64;
+        }
+
         if temp_len > 0 || opus_len.is_some() {
+            if track.first_frame_at.is_none() {
+                track.first_frame_at = Some(now);
+                if !prevent_events {
+                    let _ = interconnect
+                        .events
+                        .send(EventMessage::ChangeState(i, TrackStateChange::Started(now)));
+                }
+            }
+            track.last_frame_at = Some(now);
+
+            let usage = PassthroughUsage::record(track.passthrough_usage, opus_len.is_some());
+            if track.passthrough_usage != Some(usage) {
+                track.passthrough_usage = Some(usage);
+
+                // This changes at most twice per track, so we can afford to keep
+                // event handlers informed.
+                if !prevent_events {
+                    let _ = interconnect.events.send(EventMessage::ChangeState(
+                        i,
+                        TrackStateChange::Passthrough(usage),
+                    ));
+                }
+            }
+
             track.step_frame();
+
+            if track.stopping && !track.source.has_buffered_audio() {
+                track.stop();
+            }
+        } else if track.stopping {
+            track.stop();
         } else if track.do_loop() {
             if let Ok(time) = track.seek_time(Default::default()) {
                 // have to reproduce self.fire_event here
@@ -617,6 +1030,31 @@ fn mix_tracks<'a>(
     MixType::MixedPcm(len)
 }
 
+/// Explains whether `track` can have its Opus frames sent without re-encoding,
+/// when mixed with `track_count - 1` others.
+///
+/// `allow_passthrough` is false when the driver's frame length differs from the
+/// 20ms of each Opus frame.
+fn passthrough_decision(
+    track: &Track,
+    track_count: usize,
+    allow_passthrough: bool,
+) -> PassthroughDecision {
+    if !allow_passthrough {
+        PassthroughDecision::DecodedBecauseFrameLength
+    } else if track_count != 1 {
+        PassthroughDecision::DecodedBecauseMix
+    } else if !track.source.supports_passthrough() {
+        PassthroughDecision::DecodedBecauseResample
+    } else if !track.effects.is_empty() {
+        PassthroughDecision::DecodedBecauseEffect
+    } else if (track.volume - 1.0).abs() >= f32::EPSILON {
+        PassthroughDecision::DecodedBecauseVolume
+    } else {
+        PassthroughDecision::Passthrough
+    }
+}
+
 /// The mixing thread is a synchronous context due to its compute-bound nature.
 ///
 /// We pass in an async handle for the benefit of some Input classes (e.g., restartables)
@@ -627,10 +1065,860 @@ pub(crate) fn runner(
     mix_rx: Receiver<MixerMessage>,
     async_handle: Handle,
     config: Config,
+    frame_budget: Arc<FrameBudget>,
+    speaking: Arc<AtomicBool>,
+    track_count: Arc<AtomicUsize>,
 ) {
     let mut mixer = Mixer::new(mix_rx, async_handle, interconnect, config);
+    mixer.frame_budget = frame_budget;
+    mixer.speaking = speaking;
+    mixer.track_count = track_count;
 
     mixer.run();
 
+    mixer.frame_budget.set(None);
+    mixer.speaking.store(false, Ordering::Release);
+
     let _ = mixer.disposer.send(DisposalMessage::Poison);
 }
+
+#[cfg(all(test, not(feature = "tokio-02-marker")))]
+mod tests {
+    use super::*;
+    use crate::{
+        driver::{test_harness, FrameLength},
+        input::{
+            self,
+            cached::Memory,
+            codec::OpusDecoderState,
+            restartable::Restart,
+            Codec,
+            Container,
+            Input,
+            Reader,
+            Restartable,
+        },
+        test_utils::*,
+        tracks::{self, effects::Gain, PacketSizeStats, PlayStats},
+    };
+    use std::{convert::TryInto, io::Cursor, time::Duration};
+    use symphonia_core::io::ReadOnlySource;
+
+    #[test]
+    fn encoder_config_applies_to_reencoded_audio() {
+        let (_rt, mut mixer, _channels) = test_harness::runtime_with_mixer();
+
+        let config = Config::default()
+            .encoder_complexity(Some(2))
+            .encoder_fec(true)
+            .encoder_packet_loss(15);
+        mixer.handle_message(MixerMessage::SetConfig(config));
+
+        assert_eq!(mixer.encoder.complexity().unwrap(), 2);
+        assert!(mixer.encoder.inband_fec().unwrap());
+        assert_eq!(mixer.encoder.packet_loss_perc().unwrap(), 15);
+
+        mixer.handle_message(MixerMessage::RebuildEncoder);
+
+        assert_eq!(mixer.encoder.complexity().unwrap(), 2);
+        assert!(mixer.encoder.inband_fec().unwrap());
+        assert_eq!(mixer.encoder.packet_loss_perc().unwrap(), 15);
+    }
+
+    fn raw_track(data: Vec<u8>) -> Track {
+        let input = Input::new(true, data.into(), Codec::FloatPcm, Container::Raw, None);
+
+        tracks::create_player(input).0
+    }
+
+    fn mix_once(tracks: &mut Vec<Track>) -> Vec<f32> {
+        let ic = test_harness::disconnected_interconnect();
+
+        let mut opus_frame = [0u8; VOICE_PACKET_MAX];
+        let mut mix_buffer = [0f32; STEREO_FRAME_SIZE];
+        mix_tracks(&mut opus_frame, &mut mix_buffer, tracks, &ic, true, true);
+
+        mix_buffer.to_vec()
+    }
+
+    #[test]
+    fn ducking_track_attenuates_others_while_playing() {
+        let music = || raw_track(make_sine(10 * STEREO_FRAME_SIZE, true));
+        let announcement = || {
+            let mut track = raw_track(vec![0u8; 10 * STEREO_FRAME_BYTE_SIZE]);
+            track.set_ducking(true, 0.25);
+            track
+        };
+
+        let undisturbed = mix_once(&mut vec![music()]);
+        let ducked = mix_once(&mut vec![music(), announcement()]);
+
+        let mut paused = announcement();
+        paused.pause();
+        let restored = mix_once(&mut vec![music(), paused]);
+
+        for ((full, low), back) in undisturbed.iter().zip(&ducked).zip(&restored) {
+            assert!((full * 0.25 - low).abs() < f32::EPSILON);
+            assert!((full - back).abs() < f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn effects_modify_track_output() {
+        let track = || raw_track(make_sine(10 * STEREO_FRAME_SIZE, true));
+
+        let plain = mix_once(&mut vec![track()]);
+
+        let mut halved = track();
+        halved.add_effect(Box::new(Gain(0.5)));
+        let processed = mix_once(&mut vec![halved]);
+
+        assert!(plain.iter().any(|s| s.abs() > f32::EPSILON));
+        for (full, half) in plain.iter().zip(&processed) {
+            assert!((full * 0.5 - half).abs() < f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn corrupt_opus_frames_are_counted() {
+        // A code 3 packet must also give its frame count: this cannot be decoded.
+        let mut data = vec![];
+        for _ in 0..3 {
+            data.extend_from_slice(&[1, 0, 0xff]);
+        }
+        let input = Input::new(
+            true,
+            data.into(),
+            Codec::Opus(OpusDecoderState::new().unwrap()),
+            Container::Dca { first_frame: 0 },
+            None,
+        );
+        let mut track = tracks::create_player(input).0;
+        // Opus passthrough would skip decoding altogether.
+        track.set_volume(0.5);
+        let mut tracks = vec![track];
+
+        mix_once(&mut tracks);
+        mix_once(&mut tracks);
+        assert_eq!(tracks[0].decode_error_count(), 2);
+
+        tracks[0].reset_decode_errors();
+        mix_once(&mut tracks);
+        assert_eq!(tracks[0].decode_error_count(), 1);
+    }
+
+    #[test]
+    fn passthrough_packet_sizes_are_recorded() {
+        let packets = vec![vec![0xf8; 10], vec![0xf8; 30], vec![0xf8; 20]];
+        let input = input::opus_packets(packets, true, None).unwrap();
+        let mut tracks = vec![tracks::create_player(input).0];
+
+        for _ in 0..3 {
+            mix_once(&mut tracks);
+        }
+
+        let stats = tracks[0].packet_size_stats();
+        assert_eq!(stats.count, 3);
+        assert_eq!((stats.min, stats.max), (10, 30));
+        assert!((stats.mean - 20.0).abs() < f64::EPSILON);
+
+        // Decoded audio is not counted.
+        let mut decoded = vec![raw_track(make_sine(STEREO_FRAME_SIZE, true))];
+        mix_once(&mut decoded);
+        assert_eq!(decoded[0].packet_size_stats(), PacketSizeStats::default());
+    }
+
+    #[test]
+    fn play_stats_count_mixed_frames() {
+        let mut tracks = vec![raw_track(make_sine(3 * STEREO_FRAME_SIZE, true))];
+        assert_eq!(tracks[0].play_stats(), PlayStats::default());
+
+        mix_once(&mut tracks);
+        mix_once(&mut tracks);
+
+        let stats = tracks[0].play_stats();
+        assert_eq!(stats.frames_decoded, 2);
+        assert_eq!(stats.frames_passed_through, 0);
+        assert_eq!(stats.samples_mixed, 2 * STEREO_FRAME_SIZE as u64);
+    }
+
+    #[test]
+    fn paused_at_player_seeks_before_playing() {
+        let data = make_sine(10 * STEREO_FRAME_SIZE, true);
+        let input = Input::new(true, data.into(), Codec::FloatPcm, Container::Raw, None);
+        let mut tracks = vec![tracks::create_player_paused_at(input, Duration::from_millis(100)).0];
+        assert_eq!(tracks[0].playing(), PlayMode::Pause);
+
+        tracks[0].process_commands(0, &test_harness::disconnected_interconnect());
+        assert_eq!(tracks[0].position, Duration::from_millis(100));
+
+        tracks[0].play();
+        mix_once(&mut tracks);
+        assert_eq!(tracks[0].position, Duration::from_millis(120));
+    }
+
+    #[test]
+    fn live_pause_discards_audio_from_unseekable_sources() {
+        // One frame of silence, followed by a tone.
+        let live = |seekable: bool| {
+            let mut data = vec![0u8; STEREO_FRAME_BYTE_SIZE];
+            data.extend(make_sine(2 * STEREO_FRAME_SIZE, true));
+            let reader = if seekable {
+                data.into()
+            } else {
+                Reader::Extension(Box::new(ReadOnlySource::new(Cursor::new(data))))
+            };
+            let mut track = raw_track(vec![]);
+            track.source = Input::new(true, reader, Codec::FloatPcm, Container::Raw, None);
+            track.pause_live();
+            track
+        };
+
+        let mut tracks = vec![live(false)];
+        assert!(tracks[0].draining);
+        mix_once(&mut tracks);
+        assert_eq!(tracks[0].position, Duration::default());
+
+        tracks[0].play();
+        assert!(!tracks[0].draining);
+        assert!(mix_once(&mut tracks).iter().any(|s| s.abs() > f32::EPSILON));
+        assert_eq!(tracks[0].position, Duration::from_millis(20));
+
+        // Seekable inputs pause as normal, and resume from the silent frame.
+        let mut tracks = vec![live(true)];
+        assert!(!tracks[0].draining);
+        mix_once(&mut tracks);
+        tracks[0].play();
+        assert!(mix_once(&mut tracks).iter().all(|s| s.abs() < f32::EPSILON));
+    }
+
+    #[test]
+    fn mix_levels_report_sine_amplitude() {
+        let (_rt, mut mixer, _channels) = test_harness::runtime_with_mixer();
+
+        let (tx, rx) = flume::unbounded();
+        mixer.handle_message(MixerMessage::SetConfig(
+            Config::default().mix_levels(Some(tx)),
+        ));
+
+        // Half-amplitude, to stay clear of the soft clipper.
+        let mut track = raw_track(make_sine(10 * STEREO_FRAME_SIZE, true));
+        track.set_volume(0.5);
+        mixer.tracks.push(track);
+
+        mixer.cycle().unwrap();
+
+        let level = rx.try_recv().unwrap();
+        assert!((level.peak - 0.5).abs() < 0.01);
+        assert!((level.rms - 0.5 / 2f32.sqrt()).abs() < 0.01);
+    }
+
+    #[test]
+    fn passthrough_decision_explains_decoding() {
+        let opus_track = || {
+            let codec = Codec::Opus(OpusDecoderState::new().unwrap());
+            let input = Input::new(
+                true,
+                Vec::<u8>::new().into(),
+                codec,
+                Container::Dca { first_frame: 0 },
+                None,
+            );
+
+            tracks::create_player(input).0
+        };
+        let decision = |mut tracks: Vec<Track>| {
+            mix_once(&mut tracks);
+            tracks[0].state().passthrough
+        };
+
+        assert_eq!(
+            decision(vec![opus_track()]),
+            Some(PassthroughDecision::Passthrough)
+        );
+
+        let mut filtered = opus_track();
+        filtered.add_effect(Box::new(Gain(1.0)));
+        assert_eq!(
+            decision(vec![filtered]),
+            Some(PassthroughDecision::DecodedBecauseEffect)
+        );
+
+        let mut quiet = opus_track();
+        quiet.set_volume(0.5);
+        assert_eq!(
+            decision(vec![quiet]),
+            Some(PassthroughDecision::DecodedBecauseVolume)
+        );
+
+        assert_eq!(
+            decision(vec![raw_track(vec![0u8; STEREO_FRAME_BYTE_SIZE])]),
+            Some(PassthroughDecision::DecodedBecauseResample)
+        );
+
+        assert_eq!(
+            decision(vec![opus_track(), opus_track()]),
+            Some(PassthroughDecision::DecodedBecauseMix)
+        );
+    }
+
+    #[test]
+    fn passthrough_decision_explains_short_frames() {
+        let (_rt, mut mixer, _channels) = test_harness::runtime_with_mixer();
+        mixer.handle_message(MixerMessage::SetConfig(
+            Config::default().frame_length(FrameLength::Ms10),
+        ));
+
+        let codec = Codec::Opus(OpusDecoderState::new().unwrap());
+        let input = Input::new(
+            true,
+            Vec::<u8>::new().into(),
+            codec,
+            Container::Dca { first_frame: 0 },
+            None,
+        );
+        mixer.tracks.push(tracks::create_player(input).0);
+        mixer.cycle().unwrap();
+
+        assert_eq!(
+            mixer.tracks[0].state().passthrough,
+            Some(PassthroughDecision::DecodedBecauseFrameLength)
+        );
+    }
+
+    #[test]
+    fn frame_budget_tracks_active_cycles() {
+        let (_rt, mut mixer, _channels) = test_harness::runtime_with_mixer();
+
+        assert_eq!(mixer.frame_budget.get(), None);
+
+        mixer.cycle().unwrap();
+        assert!(mixer.frame_budget.get().unwrap() >= 0.0);
+
+        mixer.handle_message(MixerMessage::DropConn);
+        assert_eq!(mixer.frame_budget.get(), None);
+    }
+
+    #[test]
+    fn backlog_counts_unreceived_messages() {
+        let (_rt, mixer, _channels) = test_harness::runtime_with_mixer();
+
+        let before = mixer.interconnect.backlog();
+        let _ = mixer.interconnect.mixer.send(MixerMessage::RebuildEncoder);
+        let _ = mixer.interconnect.events.send(EventMessage::Tick);
+
+        let after = mixer.interconnect.backlog();
+        assert_eq!(after.mixer, before.mixer + 1);
+        assert_eq!(after.events, before.events + 1);
+        assert_eq!(after.core, before.core);
+    }
+
+    #[test]
+    fn stop_after_buffer_plays_out_decoded_audio() {
+        let mut state = OpusDecoderState::new().unwrap();
+        state.allow_passthrough = false;
+        state.current_frame = vec![0.25; 2 * STEREO_FRAME_SIZE];
+        let input = Input::new(
+            true,
+            Vec::<u8>::new().into(),
+            Codec::Opus(state),
+            Container::Dca { first_frame: 0 },
+            None,
+        );
+        let mut tracks = vec![tracks::create_player(input).0];
+
+        tracks[0].stop_after_buffer();
+        assert_eq!(tracks[0].playing(), PlayMode::Play);
+
+        let first = mix_once(&mut tracks);
+        assert!(first.iter().all(|s| (s - 0.25).abs() < f32::EPSILON));
+        assert_eq!(tracks[0].playing(), PlayMode::Play);
+
+        mix_once(&mut tracks);
+        assert_eq!(tracks[0].playing(), PlayMode::Stop);
+
+        let mut raw = raw_track(make_sine(10 * STEREO_FRAME_SIZE, true));
+        raw.stop_after_buffer();
+        assert_eq!(raw.playing(), PlayMode::Stop);
+    }
+
+    #[test]
+    fn culled_tracks_report_reason() {
+        struct Failing;
+
+        impl std::io::Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::ConnectionReset.into())
+            }
+        }
+
+        let (_rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+
+        let ended = raw_track(vec![]);
+        let mut errored = raw_track(vec![]);
+        errored.source = Input::float_pcm(
+            true,
+            Reader::Extension(Box::new(ReadOnlySource::new(Failing))),
+        );
+        let mut stopped = raw_track(make_sine(10 * STEREO_FRAME_SIZE, true));
+        stopped.stop();
+        let expected = vec![
+            (ended.uuid, CullReason::End),
+            (errored.uuid, CullReason::Error),
+            (stopped.uuid, CullReason::Stop),
+        ];
+        mixer.tracks.extend(vec![ended, errored, stopped]);
+
+        mixer.cycle().unwrap();
+        mixer.audio_commands_events().unwrap();
+        assert!(mixer.tracks.is_empty());
+
+        // Tracks replaced via `play_only` are also reported.
+        let replaced = raw_track(make_sine(10 * STEREO_FRAME_SIZE, true));
+        let replaced_uuid = replaced.uuid;
+        mixer.tracks.push(replaced);
+        mixer.handle_message(MixerMessage::SetTrack(None));
+
+        let mut culled: Vec<_> = channels
+            .events
+            .try_iter()
+            .filter_map(|msg| match msg {
+                EventMessage::FireCoreEvent(CoreContext::TrackCulled(data)) =>
+                    Some((data.uuid, data.reason)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(culled.pop(), Some((replaced_uuid, CullReason::Stop)));
+        culled.sort_by_key(|(uuid, _)| expected.iter().position(|(u, _)| u == uuid));
+        assert_eq!(culled, expected);
+    }
+
+    #[test]
+    fn failed_recreation_is_culled_as_error() {
+        #[derive(Clone)]
+        struct FailingRestarter;
+
+        #[async_trait::async_trait]
+        impl Restart for FailingRestarter {
+            async fn call_restart(
+                &mut self,
+                _time: Option<Duration>,
+            ) -> input::error::Result<Input> {
+                Err(input::error::Error::Streams)
+            }
+
+            async fn lazy_init(
+                &mut self,
+            ) -> input::error::Result<(Option<input::Metadata>, Codec, Container)> {
+                Ok((None, Codec::FloatPcm, Container::Raw))
+            }
+        }
+
+        let (rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+
+        let source = rt
+            .block_on(Restartable::new(FailingRestarter, true))
+            .unwrap();
+        let mut track = tracks::create_player(source.into()).0;
+        track.source.prep_with_handle(rt.handle().clone(), None);
+        let uuid = track.uuid;
+        mixer.tracks.push(track);
+
+        // The first frame starts recreating the source, and the next sees it fail.
+        mixer.cycle().unwrap();
+        rt.block_on(tokio::task::yield_now());
+        mixer.cycle().unwrap();
+        mixer.audio_commands_events().unwrap();
+        assert!(mixer.tracks.is_empty());
+
+        let culled: Vec<_> = channels
+            .events
+            .try_iter()
+            .filter_map(|msg| match msg {
+                EventMessage::FireCoreEvent(CoreContext::TrackCulled(data)) =>
+                    Some((data.uuid, data.reason)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(culled, vec![(uuid, CullReason::Error)]);
+    }
+
+    #[test]
+    fn passthrough_read_errors_mark_track_errored() {
+        struct Failing;
+
+        impl std::io::Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::ConnectionReset.into())
+            }
+        }
+
+        let input = Input::new(
+            true,
+            Reader::Extension(Box::new(ReadOnlySource::new(Failing))),
+            Codec::Opus(OpusDecoderState::new().unwrap()),
+            Container::Dca { first_frame: 0 },
+            None,
+        );
+        let mut tracks = vec![tracks::create_player(input).0];
+        mix_once(&mut tracks);
+        assert!(tracks[0].errored);
+
+        // Reaching the end of the input is not an error.
+        let input = input::opus_packets(vec![vec![0xf8; 10]], true, None).unwrap();
+        let mut tracks = vec![tracks::create_player(input).0];
+        mix_once(&mut tracks);
+        mix_once(&mut tracks);
+        assert_eq!(tracks[0].packet_size_stats().count, 1);
+        assert!(!tracks[0].errored);
+    }
+
+    #[test]
+    fn bitrate_changes_apply_to_next_frame() {
+        let (_rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+        mixer
+            .tracks
+            .push(raw_track(make_sine(20 * STEREO_FRAME_SIZE, true)));
+
+        let packet_lens = |mixer: &mut Mixer| {
+            for _ in 0..4 {
+                mixer.cycle().unwrap();
+            }
+            channels
+                .udp_tx
+                .try_iter()
+                .map(|msg| match msg {
+                    UdpTxMessage::Packet(p) => p.len(),
+                    _ => panic!("Unexpected UDP message."),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let high = packet_lens(&mut mixer);
+        mixer.handle_message(MixerMessage::SetBitrate(Bitrate::BitsPerSecond(16_000)));
+        assert_eq!(mixer.bitrate, Bitrate::BitsPerSecond(16_000));
+        let low = packet_lens(&mut mixer);
+
+        assert_eq!(high.len(), 4);
+        assert_eq!(low.len(), 4);
+        assert!(low.iter().max() < high.iter().min());
+        assert_eq!(mixer.tracks.len(), 1);
+    }
+
+    #[test]
+    fn rtp_marker_set_on_first_packet_after_silence() {
+        let (_rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+        mixer.handle_message(MixerMessage::SetConfig(
+            Config::default()
+                .rtp_marker_after_silence(true)
+                .rtp_timestamp_step(480),
+        ));
+
+        let talkspurt = || raw_track(make_sine(2 * MONO_FRAME_SIZE, true));
+
+        // Two frames of audio, five of trailing silence, then one unsent frame.
+        mixer.tracks.push(talkspurt());
+        for _ in 0..8 {
+            mixer.cycle().unwrap();
+        }
+        mixer.tracks.clear();
+        mixer.tracks.push(talkspurt());
+        mixer.cycle().unwrap();
+
+        let headers: Vec<(u8, u32)> = channels
+            .udp_tx
+            .try_iter()
+            .map(|msg| match msg {
+                UdpTxMessage::Packet(p) => {
+                    let rtp = RtpPacket::new(&p[..]).unwrap();
+                    (rtp.get_marker(), rtp.get_timestamp().into())
+                },
+                _ => panic!("Unexpected UDP message."),
+            })
+            .collect();
+
+        let markers: Vec<u8> = headers.iter().map(|(marker, _)| *marker).collect();
+        assert_eq!(markers, vec![1, 0, 0, 0, 0, 0, 0, 1]);
+
+        for pair in headers.windows(2) {
+            assert_eq!(pair[1].1.wrapping_sub(pair[0].1), 480);
+        }
+    }
+
+    #[test]
+    fn long_frames_span_several_cycles() {
+        let (_rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+        mixer.handle_message(MixerMessage::SetConfig(
+            Config::default()
+                .frame_length(FrameLength::Ms40)
+                .silence_frames_on_stop(0),
+        ));
+
+        // Three steps of audio: the last is padded out when the track ends.
+        mixer
+            .tracks
+            .push(raw_track(make_sine(3 * MONO_FRAME_SIZE, true)));
+        for _ in 0..6 {
+            mixer.cycle().unwrap();
+        }
+
+        let timestamps: Vec<u32> = channels
+            .udp_tx
+            .try_iter()
+            .map(|msg| match msg {
+                UdpTxMessage::Packet(p) => RtpPacket::new(&p[..]).unwrap().get_timestamp().into(),
+                _ => panic!("Unexpected UDP message."),
+            })
+            .collect();
+
+        assert_eq!(timestamps.len(), 2);
+        assert_eq!(
+            timestamps[1].wrapping_sub(timestamps[0]),
+            2 * MONO_FRAME_SIZE as u32
+        );
+        assert!(mixer.pending_pcm.is_empty());
+    }
+
+    #[test]
+    fn short_frames_split_each_cycle() {
+        let (_rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+        mixer.handle_message(MixerMessage::SetConfig(
+            Config::default()
+                .frame_length(FrameLength::Ms10)
+                .silence_frames_on_stop(0),
+        ));
+        mixer
+            .tracks
+            .push(raw_track(make_sine(2 * MONO_FRAME_SIZE, true)));
+        mixer.cycle().unwrap();
+
+        let first = channels.try_recv_udp_tx().unwrap();
+        let second = channels.try_recv_udp_tx().unwrap();
+        assert!(channels.try_recv_udp_tx().is_err());
+
+        let first_ts: u32 = match first {
+            UdpTxMessage::Packet(p) => RtpPacket::new(&p[..]).unwrap().get_timestamp().into(),
+            _ => panic!("Expected the first half to be sent immediately."),
+        };
+        match second {
+            UdpTxMessage::Scheduled(p, at) => {
+                let ts: u32 = RtpPacket::new(&p[..]).unwrap().get_timestamp().into();
+                assert_eq!(ts.wrapping_sub(first_ts), MONO_FRAME_SIZE as u32 / 2);
+                assert_eq!(at, mixer.deadline + FrameLength::Ms10.duration());
+            },
+            _ => panic!("Expected the second half to be scheduled."),
+        }
+    }
+
+    #[test]
+    fn ssrc_override_applies_to_emitted_packets() {
+        let (_rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+        let ssrc = |msg| match msg {
+            UdpTxMessage::Packet(p) => RtpPacket::new(&p[..]).unwrap().get_ssrc(),
+            _ => panic!("Unexpected UDP message."),
+        };
+
+        mixer.override_ssrc(Some(0xdead_beef));
+        mixer
+            .tracks
+            .push(raw_track(make_sine(4 * STEREO_FRAME_SIZE, true)));
+        mixer.cycle().unwrap();
+        assert_eq!(ssrc(channels.try_recv_udp_tx().unwrap()), 0xdead_beef);
+
+        // Reconnecting keeps the override in place.
+        let conn = mixer.conn_active.take().unwrap();
+        mixer.handle_message(MixerMessage::SetConn(conn, 1234));
+        mixer.cycle().unwrap();
+        assert_eq!(ssrc(channels.try_recv_udp_tx().unwrap()), 0xdead_beef);
+    }
+
+    #[test]
+    fn prerendered_packets_are_scheduled_ahead() {
+        let (_rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+        mixer.handle_message(MixerMessage::SetConfig(
+            Config::default().prerender_frames(3),
+        ));
+        mixer
+            .tracks
+            .push(raw_track(make_sine(2 * MONO_FRAME_SIZE, true)));
+
+        mixer.cycle().unwrap();
+
+        match channels.try_recv_udp_tx() {
+            Ok(UdpTxMessage::Scheduled(_, at)) =>
+                assert_eq!(at, mixer.deadline + 3 * TIMESTEP_LENGTH),
+            _ => panic!("Expected a scheduled packet."),
+        }
+    }
+
+    #[test]
+    fn tracks_beyond_limit_are_refused() {
+        let (_rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+        mixer.handle_message(MixerMessage::SetConfig(
+            Config::default().max_concurrent_tracks(Some(2)),
+        ));
+        // As counted by the driver when sending each track below.
+        mixer.track_count.store(4, Ordering::Release);
+
+        let try_add = |mixer: &mut Mixer, track: Track| {
+            let (tx, rx) = flume::bounded(1);
+            mixer.handle_message(MixerMessage::AddTrackWithResult(track, tx));
+            rx.try_recv().unwrap()
+        };
+
+        assert_eq!(try_add(&mut mixer, raw_track(vec![])), Ok(()));
+        mixer.handle_message(MixerMessage::AddTrack(raw_track(vec![])));
+        let refused = raw_track(vec![]);
+        let refused_uuid = refused.uuid;
+        assert_eq!(try_add(&mut mixer, refused), Err(TrackError::TooManyTracks));
+        let refused = raw_track(vec![]);
+        let silent_uuid = refused.uuid;
+        mixer.handle_message(MixerMessage::AddTrack(refused));
+
+        // Refused tracks are culled as stopped, so that their end events still fire.
+        mixer.audio_commands_events().unwrap();
+        let culled: Vec<_> = channels
+            .events
+            .try_iter()
+            .filter_map(|msg| match msg {
+                EventMessage::FireCoreEvent(CoreContext::TrackCulled(data)) =>
+                    Some((data.uuid, data.reason)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(culled.len(), 2);
+        assert!(culled.contains(&(refused_uuid, CullReason::Stop)));
+        assert!(culled.contains(&(silent_uuid, CullReason::Stop)));
+        assert_eq!(mixer.tracks.len(), 2);
+        assert_eq!(mixer.track_count.load(Ordering::Acquire), 2);
+    }
+
+    #[test]
+    fn tracks_stop_at_max_track_duration() {
+        let (_rt, mut mixer, _channels) = test_harness::runtime_with_mixer();
+        mixer.handle_message(MixerMessage::SetConfig(
+            Config::default().max_track_duration(Some(3 * TIMESTEP_LENGTH)),
+        ));
+        mixer
+            .tracks
+            .push(raw_track(make_sine(50 * MONO_FRAME_SIZE, true)));
+
+        for _ in 0..2 {
+            mixer.cycle().unwrap();
+            mixer.audio_commands_events().unwrap();
+        }
+        assert_eq!(mixer.tracks.len(), 1);
+
+        // The third frame is still played in full.
+        mixer.cycle().unwrap();
+        assert_eq!(mixer.tracks[0].play_time, 3 * TIMESTEP_LENGTH);
+        mixer.audio_commands_events().unwrap();
+        assert!(mixer.tracks.is_empty());
+    }
+
+    #[test]
+    fn speaking_flag_outlasts_trailing_silence() {
+        let (_rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+        mixer.handle_message(MixerMessage::SetConfig(
+            Config::default().silence_frames_on_stop(2),
+        ));
+        mixer
+            .tracks
+            .push(raw_track(make_sine(2 * MONO_FRAME_SIZE, true)));
+
+        let flags: Vec<bool> = (0..6)
+            .map(|_| {
+                mixer.cycle().unwrap();
+                mixer.speaking.load(Ordering::Acquire)
+            })
+            .collect();
+        assert_eq!(flags, vec![true, true, true, true, false, false]);
+
+        let changes: Vec<bool> = channels
+            .events
+            .try_iter()
+            .filter_map(|msg| match msg {
+                EventMessage::FireCoreEvent(CoreContext::DriverSpeaking(s)) => Some(s),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(changes, vec![true, false]);
+    }
+
+    #[test]
+    fn configured_silence_frames_follow_audio() {
+        let (_rt, mut mixer, channels) = test_harness::runtime_with_mixer();
+        mixer.handle_message(MixerMessage::SetConfig(
+            Config::default().silence_frames_on_stop(2),
+        ));
+        mixer
+            .tracks
+            .push(raw_track(make_sine(2 * MONO_FRAME_SIZE, true)));
+
+        for _ in 0..6 {
+            mixer.cycle().unwrap();
+        }
+
+        let payload_lens: Vec<usize> = channels
+            .udp_tx
+            .try_iter()
+            .map(|msg| match msg {
+                UdpTxMessage::Packet(p) => p.len(),
+                _ => panic!("Unexpected UDP message."),
+            })
+            .collect();
+
+        // Two frames of audio, then two (smaller) silent frames.
+        assert_eq!(payload_lens.len(), 4);
+        assert_eq!(payload_lens[2], payload_lens[3]);
+        assert!(payload_lens[1] > payload_lens[2]);
+    }
+
+    #[test]
+    fn passthrough_usage_summarises_track_lifetime() {
+        let mut tracks = vec![raw_track(make_sine(10 * MONO_FRAME_SIZE, true))];
+        assert_eq!(tracks[0].state().passthrough_usage, None);
+
+        mix_once(&mut tracks);
+        assert_eq!(
+            tracks[0].state().passthrough_usage,
+            Some(PassthroughUsage::Never)
+        );
+
+        use PassthroughUsage::*;
+        assert_eq!(PassthroughUsage::record(None, true), Always);
+        assert_eq!(PassthroughUsage::record(Some(Always), true), Always);
+        assert_eq!(PassthroughUsage::record(Some(Always), false), Mixed);
+        assert_eq!(PassthroughUsage::record(Some(Never), true), Mixed);
+        assert_eq!(PassthroughUsage::record(Some(Mixed), false), Mixed);
+    }
+
+    #[test]
+    fn waveform_is_computed_from_cached_source() {
+        let rt = test_harness::test_runtime();
+        let ic = test_harness::disconnected_interconnect();
+
+        let mut data = make_sine(4 * MONO_FRAME_SIZE, true);
+        data.resize(2 * data.len(), 0);
+        let input = Input::new(true, data.into(), Codec::FloatPcm, Container::Raw, None);
+        let memory = Memory::new(input).unwrap();
+        let (mut track, handle) = tracks::create_player(memory.try_into().unwrap());
+
+        let peaks = rt.block_on(async {
+            let waveform = handle.compute_waveform(2);
+            futures::pin_mut!(waveform);
+            assert!(futures::poll!(waveform.as_mut()).is_pending());
+
+            track.process_commands(0, &ic);
+            waveform.await.unwrap()
+        });
+
+        assert_eq!(peaks.len(), 2);
+        assert!(peaks[0] > 0.9);
+        assert_eq!(peaks[1], 0.0);
+
+        // Served from the handle's cache, without the track's involvement.
+        drop(track);
+        assert_eq!(rt.block_on(handle.compute_waveform(2)).unwrap(), peaks);
+    }
+}
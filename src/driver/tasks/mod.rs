@@ -9,35 +9,78 @@ pub(crate) mod udp_rx;
 pub(crate) mod udp_tx;
 pub(crate) mod ws;
 
-use std::time::Duration;
+use std::{
+    convert::TryFrom,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use super::connection::{error::Error as ConnectionError, Connection};
+use super::{
+    connection::{error::Error as ConnectionError, Connection},
+    SendTiming,
+};
 use crate::{
     events::{
         context_data::{DisconnectKind, DisconnectReason},
-        internal_data::{InternalConnect, InternalDisconnect},
+        internal_data::{InternalConnect, InternalDisconnect, InternalReconnectAttempt},
         CoreContext,
     },
+    input::{cached::Memory, Input},
+    tracks,
     Config,
     ConnectionInfo,
 };
 use flume::{Receiver, RecvError, Sender};
 use message::*;
+use mixer::FrameBudget;
+use parking_lot::Mutex;
 #[cfg(not(feature = "tokio-02-marker"))]
 use tokio::{runtime::Handle, spawn, time::sleep as tsleep};
 #[cfg(feature = "tokio-02-marker")]
 use tokio_compat::{runtime::Handle, spawn, time::delay_for as tsleep};
 use tracing::{debug, instrument, trace};
 
-pub(crate) fn start(config: Config, rx: Receiver<CoreMessage>, tx: Sender<CoreMessage>) {
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn start(
+    config: Config,
+    rx: Receiver<CoreMessage>,
+    tx: Sender<CoreMessage>,
+    connected: Arc<AtomicBool>,
+    external_addr: Arc<Mutex<Option<SocketAddr>>>,
+    frame_budget: Arc<FrameBudget>,
+    send_timing: Arc<SendTiming>,
+    speaking: Arc<AtomicBool>,
+    track_count: Arc<AtomicUsize>,
+) {
     spawn(async move {
         trace!("Driver started.");
-        runner(config, rx, tx).await;
+        runner(
+            config,
+            rx,
+            tx,
+            connected,
+            external_addr,
+            frame_budget,
+            send_timing,
+            speaking,
+            track_count,
+        )
+        .await;
         trace!("Driver finished.");
     });
 }
 
-fn start_internals(core: Sender<CoreMessage>, config: Config) -> Interconnect {
+fn start_internals(
+    core: Sender<CoreMessage>,
+    config: Config,
+    frame_budget: Arc<FrameBudget>,
+    speaking: Arc<AtomicBool>,
+    track_count: Arc<AtomicUsize>,
+) -> Interconnect {
     let (evt_tx, evt_rx) = flume::unbounded();
     let (mix_tx, mix_rx) = flume::unbounded();
 
@@ -58,20 +101,55 @@ fn start_internals(core: Sender<CoreMessage>, config: Config) -> Interconnect {
     let handle = Handle::current();
     std::thread::spawn(move || {
         trace!("Mixer started.");
-        mixer::runner(ic, mix_rx, handle, config);
+        mixer::runner(
+            ic,
+            mix_rx,
+            handle,
+            config,
+            frame_budget,
+            speaking,
+            track_count,
+        );
         trace!("Mixer finished.");
     });
 
     interconnect
 }
 
-#[instrument(skip(rx, tx))]
-async fn runner(mut config: Config, rx: Receiver<CoreMessage>, tx: Sender<CoreMessage>) {
+#[instrument(skip(
+    rx,
+    tx,
+    connected,
+    external_addr,
+    frame_budget,
+    send_timing,
+    speaking,
+    track_count
+))]
+#[allow(clippy::too_many_arguments)]
+async fn runner(
+    mut config: Config,
+    rx: Receiver<CoreMessage>,
+    tx: Sender<CoreMessage>,
+    connected: Arc<AtomicBool>,
+    external_addr: Arc<Mutex<Option<SocketAddr>>>,
+    frame_budget: Arc<FrameBudget>,
+    send_timing: Arc<SendTiming>,
+    speaking: Arc<AtomicBool>,
+    track_count: Arc<AtomicUsize>,
+) {
     let mut next_config: Option<Config> = None;
     let mut connection: Option<Connection> = None;
-    let mut interconnect = start_internals(tx, config.clone());
+    let mut interconnect = start_internals(
+        tx,
+        config.clone(),
+        frame_budget,
+        speaking,
+        track_count.clone(),
+    );
     let mut retrying = None;
     let mut attempt_idx = 0;
+    let mut join_sound = None;
 
     loop {
         match rx.recv_async().await {
@@ -95,7 +173,16 @@ async fn runner(mut config: Config, rx: Receiver<CoreMessage>, tx: Sender<CoreMe
                     // This allows the gateway component to keep sending join requests independent
                     // of driver failures.
                     connection = ConnectionRetryData::connect(tx, info, &mut attempt_idx)
-                        .attempt(&mut retrying, &interconnect, &config)
+                        .attempt(
+                            &mut retrying,
+                            &interconnect,
+                            &config,
+                            &join_sound,
+                            &connected,
+                            &track_count,
+                            &external_addr,
+                            &send_timing,
+                        )
                         .await;
                 } else {
                     // No reconnection was attempted as there's a valid, identical connection;
@@ -108,7 +195,16 @@ async fn runner(mut config: Config, rx: Receiver<CoreMessage>, tx: Sender<CoreMe
                 if retry_idx == attempt_idx {
                     if let Some(progress) = retrying.take() {
                         connection = progress
-                            .attempt(&mut retrying, &interconnect, &config)
+                            .attempt(
+                                &mut retrying,
+                                &interconnect,
+                                &config,
+                                &join_sound,
+                                &connected,
+                                &track_count,
+                                &external_addr,
+                                &send_timing,
+                            )
                             .await;
                     }
                 }
@@ -155,9 +251,17 @@ async fn runner(mut config: Config, rx: Receiver<CoreMessage>, tx: Sender<CoreMe
             Ok(CoreMessage::AddTrack(s)) => {
                 let _ = interconnect.mixer.send(MixerMessage::AddTrack(s));
             },
+            Ok(CoreMessage::AddTrackWithResult(s, tx)) => {
+                let _ = interconnect
+                    .mixer
+                    .send(MixerMessage::AddTrackWithResult(s, tx));
+            },
             Ok(CoreMessage::SetBitrate(b)) => {
                 let _ = interconnect.mixer.send(MixerMessage::SetBitrate(b));
             },
+            Ok(CoreMessage::SetJoinSound(s)) => {
+                join_sound = s;
+            },
             Ok(CoreMessage::SetConfig(mut new_config)) => {
                 next_config = Some(new_config.clone());
 
@@ -183,7 +287,7 @@ async fn runner(mut config: Config, rx: Receiver<CoreMessage>, tx: Sender<CoreMe
                     let full_connect = match conn.reconnect(&config).await {
                         Ok(()) => {
                             connection = Some(conn);
-                            false
+                            None
                         },
                         Err(ConnectionError::InterconnectFailure(_)) => {
                             interconnect.restart_volatile_internals();
@@ -191,18 +295,28 @@ async fn runner(mut config: Config, rx: Receiver<CoreMessage>, tx: Sender<CoreMe
                             match conn.reconnect(&config).await {
                                 Ok(()) => {
                                     connection = Some(conn);
-                                    false
+                                    None
                                 },
-                                _ => true,
+                                Err(e) => Some(DisconnectReason::from(&e)),
                             }
                         },
-                        _ => true,
+                        Err(e) => Some(DisconnectReason::from(&e)),
                     };
 
-                    if full_connect {
-                        connection = ConnectionRetryData::reconnect(info, &mut attempt_idx)
-                            .attempt(&mut retrying, &interconnect, &config)
-                            .await;
+                    if let Some(reason) = full_connect {
+                        connection =
+                            ConnectionRetryData::reconnect(info, &mut attempt_idx, Some(reason))
+                                .attempt(
+                                    &mut retrying,
+                                    &interconnect,
+                                    &config,
+                                    &join_sound,
+                                    &connected,
+                                    &track_count,
+                                    &external_addr,
+                                    &send_timing,
+                                )
+                                .await;
                     } else if let Some(ref connection) = &connection {
                         let _ = interconnect.events.send(EventMessage::FireCoreEvent(
                             CoreContext::DriverReconnect(InternalConnect {
@@ -217,8 +331,17 @@ async fn runner(mut config: Config, rx: Receiver<CoreMessage>, tx: Sender<CoreMe
                 if let Some(conn) = connection.take() {
                     let info = conn.info.clone();
 
-                    connection = ConnectionRetryData::reconnect(info, &mut attempt_idx)
-                        .attempt(&mut retrying, &interconnect, &config)
+                    connection = ConnectionRetryData::reconnect(info, &mut attempt_idx, None)
+                        .attempt(
+                            &mut retrying,
+                            &interconnect,
+                            &config,
+                            &join_sound,
+                            &connected,
+                            &track_count,
+                            &external_addr,
+                            &send_timing,
+                        )
                         .await;
                 },
             Ok(CoreMessage::RebuildInterconnect) => {
@@ -228,8 +351,14 @@ async fn runner(mut config: Config, rx: Receiver<CoreMessage>, tx: Sender<CoreMe
                 break;
             },
         }
+
+        connected.store(connection.is_some(), Ordering::Release);
+        *external_addr.lock() = connection.as_ref().map(|conn| conn.external_addr);
     }
 
+    connected.store(false, Ordering::Release);
+    *external_addr.lock() = None;
+
     trace!("Main thread exited");
     interconnect.poison_all();
 }
@@ -238,6 +367,8 @@ struct ConnectionRetryData {
     flavour: ConnectionFlavour,
     attempts: usize,
     last_wait: Option<Duration>,
+    /// Cause of the most recent failure, reported to reconnect attempt events.
+    last_reason: Option<DisconnectReason>,
     info: ConnectionInfo,
     idx: usize,
 }
@@ -248,33 +379,72 @@ impl ConnectionRetryData {
         info: ConnectionInfo,
         idx_src: &mut usize,
     ) -> Self {
-        Self::base(ConnectionFlavour::Connect(tx), info, idx_src)
+        Self::base(ConnectionFlavour::Connect(tx), info, idx_src, None)
     }
 
-    fn reconnect(info: ConnectionInfo, idx_src: &mut usize) -> Self {
-        Self::base(ConnectionFlavour::Reconnect, info, idx_src)
+    fn reconnect(
+        info: ConnectionInfo,
+        idx_src: &mut usize,
+        reason: Option<DisconnectReason>,
+    ) -> Self {
+        Self::base(ConnectionFlavour::Reconnect, info, idx_src, reason)
     }
 
-    fn base(flavour: ConnectionFlavour, info: ConnectionInfo, idx_src: &mut usize) -> Self {
+    fn base(
+        flavour: ConnectionFlavour,
+        info: ConnectionInfo,
+        idx_src: &mut usize,
+        last_reason: Option<DisconnectReason>,
+    ) -> Self {
         *idx_src = idx_src.wrapping_add(1);
 
         Self {
             flavour,
             attempts: 0,
             last_wait: None,
+            last_reason,
             info,
             idx: *idx_src,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn attempt(
         mut self,
         attempt_slot: &mut Option<Self>,
         interconnect: &Interconnect,
         config: &Config,
+        join_sound: &Option<Memory>,
+        connected: &AtomicBool,
+        track_count: &AtomicUsize,
+        external_addr: &Mutex<Option<SocketAddr>>,
+        send_timing: &Arc<SendTiming>,
     ) -> Option<Connection> {
-        match Connection::new(self.info.clone(), interconnect, config, self.idx).await {
+        if let ConnectionFlavour::Reconnect = self.flavour {
+            let _ = interconnect.events.send(EventMessage::FireCoreEvent(
+                CoreContext::DriverReconnectAttempt(InternalReconnectAttempt {
+                    attempt: self.attempts + 1,
+                    reason: self.last_reason,
+                    info: self.info.clone(),
+                }),
+            ));
+        }
+
+        let connection = Connection::new(
+            self.info.clone(),
+            interconnect,
+            config,
+            self.idx,
+            send_timing,
+        );
+
+        match connection.await {
             Ok(connection) => {
+                // Set before notifying the caller, so that `Driver::is_connected`
+                // is accurate as soon as a connect call resolves.
+                connected.store(true, Ordering::Release);
+                *external_addr.lock() = Some(connection.external_addr);
+
                 match self.flavour {
                     ConnectionFlavour::Connect(tx) => {
                         // Other side may not be listening: this is fine.
@@ -286,6 +456,10 @@ impl ConnectionRetryData {
                                 ssrc: connection.ssrc,
                             }),
                         ));
+
+                        if let Some(sound) = join_sound {
+                            play_join_sound(sound, interconnect, track_count);
+                        }
                     },
                     ConnectionFlavour::Reconnect => {
                         let _ = interconnect.events.send(EventMessage::FireCoreEvent(
@@ -312,6 +486,7 @@ impl ConnectionRetryData {
 
                     self.attempts += 1;
                     self.last_wait = Some(t);
+                    self.last_reason = Some(DisconnectReason::from(&why));
 
                     debug!(
                         "Retrying connection for {:?} in {}s ({}/{:?})",
@@ -364,6 +539,17 @@ impl ConnectionRetryData {
     }
 }
 
+fn play_join_sound(sound: &Memory, interconnect: &Interconnect, track_count: &AtomicUsize) {
+    match Input::try_from(sound.new_handle()) {
+        Ok(input) => {
+            let (track, _) = tracks::create_player(input);
+            track_count.fetch_add(1, Ordering::AcqRel);
+            let _ = interconnect.mixer.send(MixerMessage::AddTrack(track));
+        },
+        Err(e) => debug!("Failed to prepare join sound: {:?}", e),
+    }
+}
+
 enum ConnectionFlavour {
     Connect(Sender<Result<(), ConnectionError>>),
     Reconnect,
@@ -1,8 +1,8 @@
 use super::message::*;
-use crate::constants::*;
+use crate::{constants::*, driver::SendTiming};
 use discortp::discord::MutableKeepalivePacket;
 use flume::Receiver;
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc, time::Instant as StdInstant};
 #[cfg(not(feature = "tokio-02-marker"))]
 use tokio::{
     net::UdpSocket,
@@ -18,6 +18,7 @@ use tracing::{error, instrument, trace};
 struct UdpTx {
     ssrc: u32,
     rx: Receiver<UdpTxMessage>,
+    send_timing: Option<Arc<SendTiming>>,
 
     #[cfg(not(feature = "tokio-02-marker"))]
     udp_tx: Arc<UdpSocket>,
@@ -33,23 +34,57 @@ impl UdpTx {
         ka.set_ssrc(self.ssrc);
 
         let mut ka_time = Instant::now() + UDP_KEEPALIVE_GAP;
+        // Scheduled packets waiting for their send time, in order of arrival.
+        let mut pending: VecDeque<(Vec<u8>, Instant)> = VecDeque::new();
 
         loop {
             use UdpTxMessage::*;
-            match timeout_at(ka_time, self.rx.recv_async()).await {
-                Err(_) => {
-                    trace!("Sending UDP Keepalive.");
-                    if let Err(e) = self.udp_tx.send(&keepalive_bytes[..]).await {
-                        error!("Fatal UDP keepalive send error: {:?}.", e);
-                        break;
-                    }
-                    ka_time += UDP_KEEPALIVE_GAP;
+
+            // Due sends are flushed before every wait, so that a busy channel
+            // cannot hold back scheduled packets or keepalives.
+            let now = Instant::now();
+
+            while let Some((p, _)) = pending.front().filter(|(_, at)| *at <= now) {
+                if let Err(e) = self.udp_tx.send(&p[..]).await {
+                    error!("Fatal UDP packet send error: {:?}.", e);
+                    return;
+                }
+
+                self.record_send();
+                pending.pop_front();
+            }
+
+            if ka_time <= now {
+                trace!("Sending UDP Keepalive.");
+                if let Err(e) = self.udp_tx.send(&keepalive_bytes[..]).await {
+                    error!("Fatal UDP keepalive send error: {:?}.", e);
+                    break;
+                }
+                ka_time += UDP_KEEPALIVE_GAP;
+            }
+
+            let deadline = match pending.front() {
+                Some((_, at)) => ka_time.min(*at),
+                None => ka_time,
+            };
+
+            match timeout_at(deadline, self.rx.recv_async()).await {
+                Err(_) => {},
+                Ok(Ok(Packet(p))) if !pending.is_empty() => {
+                    // Keep to arrival order behind any packets still waiting.
+                    pending.push_back((p, Instant::now()));
                 },
-                Ok(Ok(Packet(p))) =>
+                Ok(Ok(Packet(p))) => {
                     if let Err(e) = self.udp_tx.send(&p[..]).await {
                         error!("Fatal UDP packet send error: {:?}.", e);
                         break;
-                    },
+                    }
+
+                    self.record_send();
+                },
+                Ok(Ok(Scheduled(p, at))) => {
+                    pending.push_back((p, Instant::from_std(at)));
+                },
                 Ok(Err(e)) => {
                     error!("Fatal UDP packet receive error: {:?}.", e);
                     break;
@@ -60,16 +95,28 @@ impl UdpTx {
             }
         }
     }
+
+    fn record_send(&self) {
+        if let Some(timing) = &self.send_timing {
+            timing.record(StdInstant::now());
+        }
+    }
 }
 
 #[cfg(not(feature = "tokio-02-marker"))]
-#[instrument(skip(udp_msg_rx))]
-pub(crate) async fn runner(udp_msg_rx: Receiver<UdpTxMessage>, ssrc: u32, udp_tx: Arc<UdpSocket>) {
+#[instrument(skip(udp_msg_rx, send_timing))]
+pub async fn runner(
+    udp_msg_rx: Receiver<UdpTxMessage>,
+    ssrc: u32,
+    udp_tx: Arc<UdpSocket>,
+    send_timing: Option<Arc<SendTiming>>,
+) {
     trace!("UDP transmit handle started.");
 
     let mut txer = UdpTx {
         ssrc,
         rx: udp_msg_rx,
+        send_timing,
         udp_tx,
     };
 
@@ -79,13 +126,19 @@ pub(crate) async fn runner(udp_msg_rx: Receiver<UdpTxMessage>, ssrc: u32, udp_tx
 }
 
 #[cfg(feature = "tokio-02-marker")]
-#[instrument(skip(udp_msg_rx))]
-pub(crate) async fn runner(udp_msg_rx: Receiver<UdpTxMessage>, ssrc: u32, udp_tx: SendHalf) {
+#[instrument(skip(udp_msg_rx, send_timing))]
+pub async fn runner(
+    udp_msg_rx: Receiver<UdpTxMessage>,
+    ssrc: u32,
+    udp_tx: SendHalf,
+    send_timing: Option<Arc<SendTiming>>,
+) {
     trace!("UDP transmit handle started.");
 
     let mut txer = UdpTx {
         ssrc,
         rx: udp_msg_rx,
+        send_timing,
         udp_tx,
     };
 
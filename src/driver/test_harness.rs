@@ -0,0 +1,138 @@
+//! Tools for running a [`Mixer`] in isolation, without a live voice connection.
+//!
+//! Included if using the `"internals"` feature flag.
+//! You should not and/or cannot use these as part of a normal application.
+//!
+//! [`Mixer`]: super::tasks::mixer::Mixer
+
+use super::{
+    tasks::{message::*, mixer::Mixer},
+    CryptoState,
+};
+use flume::{Receiver, TryRecvError};
+#[cfg(not(feature = "tokio-02-marker"))]
+use tokio::runtime::{Builder, Handle, Runtime};
+#[cfg(feature = "tokio-02-marker")]
+use tokio_compat::runtime::Handle;
+use xsalsa20poly1305::{aead::NewAead, XSalsa20Poly1305 as Cipher, KEY_SIZE};
+
+/// Receiving ends of every channel a dummied [`Mixer`] can send on.
+///
+/// [`Mixer`]: super::tasks::mixer::Mixer
+#[derive(Debug)]
+pub struct MixerTestChannels {
+    /// Messages sent to the driver's core task, e.g., reconnection requests.
+    pub core: Receiver<CoreMessage>,
+    /// Messages sent to the event task, e.g., track state changes.
+    pub events: Receiver<EventMessage>,
+    /// Messages sent to the UDP receive task, e.g., config changes.
+    pub udp_rx: Receiver<UdpRxMessage>,
+    /// Messages sent to the UDP transmit task, i.e., encrypted voice packets.
+    pub udp_tx: Receiver<UdpTxMessage>,
+}
+
+impl MixerTestChannels {
+    /// Attempts to receive a message sent to the core task.
+    pub fn try_recv_core(&self) -> Result<CoreMessage, TryRecvError> {
+        self.core.try_recv()
+    }
+
+    /// Attempts to receive a message sent to the event task.
+    pub fn try_recv_events(&self) -> Result<EventMessage, TryRecvError> {
+        self.events.try_recv()
+    }
+
+    /// Attempts to receive a message sent to the UDP receive task.
+    pub fn try_recv_udp_rx(&self) -> Result<UdpRxMessage, TryRecvError> {
+        self.udp_rx.try_recv()
+    }
+
+    /// Attempts to receive a message sent to the UDP transmit task.
+    pub fn try_recv_udp_tx(&self) -> Result<UdpTxMessage, TryRecvError> {
+        self.udp_tx.try_recv()
+    }
+}
+
+impl From<MixerTestChannels>
+    for (
+        Receiver<CoreMessage>,
+        Receiver<EventMessage>,
+        Receiver<UdpRxMessage>,
+        Receiver<UdpTxMessage>,
+    )
+{
+    fn from(val: MixerTestChannels) -> Self {
+        (val.core, val.events, val.udp_rx, val.udp_tx)
+    }
+}
+
+/// Creates a [`Mixer`] with a fake (unencrypted-key) connection, which will
+/// not sleep between calls to [`Mixer::cycle`].
+///
+/// [`Mixer`]: super::tasks::mixer::Mixer
+/// [`Mixer::cycle`]: super::tasks::mixer::Mixer::cycle
+pub fn dummied_mixer(handle: Handle) -> (Mixer, MixerTestChannels) {
+    let (mix_tx, mix_rx) = flume::unbounded();
+    let (core_tx, core_rx) = flume::unbounded();
+    let (event_tx, event_rx) = flume::unbounded();
+
+    let (udp_sender_tx, udp_sender_rx) = flume::unbounded();
+    let (udp_receiver_tx, udp_receiver_rx) = flume::unbounded();
+
+    let ic = Interconnect {
+        core: core_tx,
+        events: event_tx,
+        mixer: mix_tx,
+    };
+
+    let mut out = Mixer::new(mix_rx, handle, ic, Default::default());
+
+    let fake_conn = MixerConnection {
+        cipher: Cipher::new_from_slice(&[0u8; KEY_SIZE]).unwrap(),
+        crypto_state: CryptoState::Normal,
+        udp_rx: udp_receiver_tx,
+        udp_tx: udp_sender_tx,
+    };
+
+    out.conn_active = Some(fake_conn);
+
+    out.skip_sleep = true;
+
+    (
+        out,
+        MixerTestChannels {
+            core: core_rx,
+            events: event_rx,
+            udp_rx: udp_receiver_rx,
+            udp_tx: udp_sender_rx,
+        },
+    )
+}
+
+/// Creates an [`Interconnect`] whose every receiver has hung up, so that any
+/// messages sent on it are dropped.
+///
+/// [`Interconnect`]: super::tasks::message::Interconnect
+pub fn disconnected_interconnect() -> Interconnect {
+    Interconnect {
+        core: flume::unbounded().0,
+        events: flume::unbounded().0,
+        mixer: flume::unbounded().0,
+    }
+}
+
+/// Creates a single-threaded runtime, with timers enabled, for driving tests.
+#[cfg(not(feature = "tokio-02-marker"))]
+pub fn test_runtime() -> Runtime {
+    Builder::new_current_thread().enable_time().build().unwrap()
+}
+
+/// Creates a [`dummied_mixer`] on a new [`test_runtime`], which must be kept alive
+/// alongside the mixer.
+#[cfg(not(feature = "tokio-02-marker"))]
+pub fn runtime_with_mixer() -> (Runtime, Mixer, MixerTestChannels) {
+    let rt = test_runtime();
+    let (mixer, channels) = dummied_mixer(rt.handle().clone());
+
+    (rt, mixer, channels)
+}
@@ -0,0 +1,37 @@
+use uuid::Uuid;
+
+/// Why the mixer removed a track from its set of live tracks.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum CullReason {
+    /// The track's input ran out of audio.
+    ///
+    /// Inputs which end abruptly are also reported this way, including child
+    /// processes (e.g., `ffmpeg`) which exit early, as they cannot be told apart
+    /// from a normal end of stream.
+    End,
+    /// The track's input failed while being read, or a [`Restartable`] input
+    /// could not be recreated.
+    ///
+    /// [`Restartable`]: crate::input::Restartable
+    Error,
+    /// The track was stopped, e.g., via [`TrackHandle::stop`], [`Driver::stop`],
+    /// or by reaching the driver's [maximum track duration]. Tracks refused by a
+    /// driver already holding its [maximum number of tracks] are also stopped.
+    ///
+    /// [`TrackHandle::stop`]: crate::tracks::TrackHandle::stop
+    /// [`Driver::stop`]: crate::driver::Driver::stop
+    /// [maximum track duration]: crate::Config::max_track_duration
+    /// [maximum number of tracks]: crate::Config::max_concurrent_tracks
+    Stop,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+/// Details of a track which the mixer has discarded.
+pub struct TrackCullData {
+    /// Unique identifier of the removed track.
+    pub uuid: Uuid,
+    /// Why the track was removed.
+    pub reason: CullReason,
+}
@@ -2,11 +2,13 @@
 //!
 //! [`EventContext`]: super::EventContext
 mod connect;
+mod cull;
 mod disconnect;
+mod reconnect;
 mod rtcp;
 mod speaking;
 mod voice;
 
 use discortp::{rtcp::Rtcp, rtp::Rtp};
 
-pub use self::{connect::*, disconnect::*, rtcp::*, speaking::*, voice::*};
+pub use self::{connect::*, cull::*, disconnect::*, reconnect::*, rtcp::*, speaking::*, voice::*};
@@ -0,0 +1,23 @@
+use super::DisconnectReason;
+use crate::id::*;
+
+/// Details of an upcoming attempt to reestablish a lost voice connection.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct ReconnectAttemptData<'a> {
+    /// The number of this attempt, starting from `1` after each dropped connection.
+    pub attempt: usize,
+    /// Why the previous attempt failed, or why the connection was lost if this is the
+    /// first attempt.
+    ///
+    /// This is `None` when the cause of a dropped connection is not known.
+    pub reason: Option<DisconnectReason>,
+    /// ID of the voice channel being rejoined, if it is known.
+    pub channel_id: Option<ChannelId>,
+    /// ID of the target voice channel's parent guild.
+    pub guild_id: GuildId,
+    /// Unique string describing this session for validation/authentication purposes.
+    pub session_id: &'a str,
+    /// The domain name of the Discord voice server being reconnected to.
+    pub server: &'a str,
+}
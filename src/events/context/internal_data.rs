@@ -15,6 +15,13 @@ pub struct InternalDisconnect {
     pub info: ConnectionInfo,
 }
 
+#[derive(Debug)]
+pub struct InternalReconnectAttempt {
+    pub attempt: usize,
+    pub reason: Option<DisconnectReason>,
+    pub info: ConnectionInfo,
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct InternalSpeakingUpdate {
     pub ssrc: u32,
@@ -60,6 +67,19 @@ impl<'a> From<&'a InternalDisconnect> for DisconnectData<'a> {
     }
 }
 
+impl<'a> From<&'a InternalReconnectAttempt> for ReconnectAttemptData<'a> {
+    fn from(val: &'a InternalReconnectAttempt) -> Self {
+        Self {
+            attempt: val.attempt,
+            reason: val.reason,
+            channel_id: val.info.channel_id,
+            guild_id: val.info.guild_id,
+            session_id: &val.info.session_id,
+            server: &val.info.endpoint,
+        }
+    }
+}
+
 impl<'a> From<&'a InternalSpeakingUpdate> for SpeakingUpdateData {
     fn from(val: &'a InternalSpeakingUpdate) -> Self {
         Self {
@@ -84,6 +84,12 @@ pub enum EventContext<'a> {
     DriverReconnectFailed,
     /// Fires when this driver fails to connect to, or drops from, a voice channel.
     DriverDisconnect(DisconnectData<'a>),
+    /// Fires before each attempt this driver makes to reconnect after losing its connection.
+    DriverReconnectAttempt(ReconnectAttemptData<'a>),
+    /// Fires when this driver starts (`true`) or stops (`false`) sending audio.
+    DriverSpeaking(bool),
+    /// Fires when the mixer discards a track, and why.
+    TrackCulled(TrackCullData),
     #[deprecated(
         since = "0.2.0",
         note = "Please use the DriverConnect/Reconnect events instead."
@@ -111,6 +117,9 @@ pub enum CoreContext {
     DriverConnect(InternalConnect),
     DriverReconnect(InternalConnect),
     DriverDisconnect(InternalDisconnect),
+    DriverReconnectAttempt(InternalReconnectAttempt),
+    DriverSpeaking(bool),
+    TrackCulled(TrackCullData),
     DriverConnectFailed,
     DriverReconnectFailed,
     SsrcKnown(u32),
@@ -131,6 +140,10 @@ impl<'a> CoreContext {
             DriverConnect(evt) => EventContext::DriverConnect(ConnectData::from(evt)),
             DriverReconnect(evt) => EventContext::DriverReconnect(ConnectData::from(evt)),
             DriverDisconnect(evt) => EventContext::DriverDisconnect(DisconnectData::from(evt)),
+            DriverReconnectAttempt(evt) =>
+                EventContext::DriverReconnectAttempt(ReconnectAttemptData::from(evt)),
+            DriverSpeaking(s) => EventContext::DriverSpeaking(*s),
+            TrackCulled(evt) => EventContext::TrackCulled(*evt),
             #[allow(deprecated)]
             DriverConnectFailed => EventContext::DriverConnectFailed,
             #[allow(deprecated)]
@@ -158,6 +171,9 @@ impl EventContext<'_> {
             DriverConnect(_) => Some(CoreEvent::DriverConnect),
             DriverReconnect(_) => Some(CoreEvent::DriverReconnect),
             DriverDisconnect(_) => Some(CoreEvent::DriverDisconnect),
+            DriverReconnectAttempt(_) => Some(CoreEvent::DriverReconnectAttempt),
+            DriverSpeaking(_) => Some(CoreEvent::DriverSpeaking),
+            TrackCulled(_) => Some(CoreEvent::TrackCulled),
             #[allow(deprecated)]
             DriverConnectFailed => Some(CoreEvent::DriverConnectFailed),
             #[allow(deprecated)]
@@ -61,6 +61,36 @@ pub enum CoreEvent {
     DriverReconnectFailed,
     /// Fires when this driver fails to connect to, or drops from, a voice channel.
     DriverDisconnect,
+    /// Fires when this driver starts or stops sending audio.
+    ///
+    /// See [`Driver::is_speaking`] for how this is debounced.
+    ///
+    /// [`Driver::is_speaking`]: crate::driver::Driver::is_speaking
+    DriverSpeaking,
+    /// Fires whenever the mixer discards a track, with the track's UUID and why it
+    /// was removed.
+    ///
+    /// This allows records of which tracks are playing to follow the mixer's own
+    /// set of tracks. Each track is reported once, including those removed en masse
+    /// by [`Driver::play_only`] or [`Driver::stop`].
+    ///
+    /// [`Driver::play_only`]: crate::driver::Driver::play_only
+    /// [`Driver::stop`]: crate::driver::Driver::stop
+    TrackCulled,
+    /// Fires before each attempt this driver makes to reconnect after losing its
+    /// connection, carrying the attempt number and the cause of the last failure.
+    ///
+    /// Each reconnection ends with either a [`DriverReconnect`] (on success) or a
+    /// [`DriverDisconnect`] (once the retry strategy has given up). Short-lived
+    /// websocket resumptions, which keep the existing session, do not fire this event.
+    ///
+    /// Handlers cannot veto or delay an attempt: the driver does not wait for them
+    /// to complete, and retry behaviour is governed solely by [`Config::driver_retry`].
+    ///
+    /// [`DriverReconnect`]: Self::DriverReconnect
+    /// [`DriverDisconnect`]: Self::DriverDisconnect
+    /// [`Config::driver_retry`]: crate::Config::driver_retry
+    DriverReconnectAttempt,
     /// Fires whenever the driver is assigned a new [RTP SSRC] by the voice server.
     ///
     /// This typically fires alongside a [DriverConnect], or a full [DriverReconnect].
@@ -22,4 +22,21 @@ pub enum TrackEvent {
     End,
     /// The attached track has looped.
     Loop,
+    /// The attached track's input can now be played.
+    ///
+    /// This fires once per track, once its input has been created: lazily
+    /// initialised [`Restartable`] sources fire this after their first
+    /// (re)creation completes, while all other sources fire this as soon as they
+    /// reach the mixer.
+    ///
+    /// [`Restartable`]: crate::input::restartable::Restartable
+    Ready,
+    /// The attached track has produced its first frame of audio.
+    ///
+    /// This fires once per track, after the first mixer cycle in which its input
+    /// yields any audio: unlike [`Play`], this is not sent while a lazily initialised
+    /// source is still starting.
+    ///
+    /// [`Play`]: TrackEvent::Play
+    Started,
 }
@@ -19,6 +19,8 @@ pub struct OpusDecoderState {
     pub(crate) current_frame: Vec<f32>,
     pub(crate) frame_pos: usize,
     pub(crate) should_reset: bool,
+    pub(crate) frames_primed: u64,
+    pub(crate) decode_errors: u32,
 }
 
 impl OpusDecoderState {
@@ -38,6 +40,8 @@ impl OpusDecoderState {
             current_frame: Vec::with_capacity(STEREO_FRAME_SIZE),
             frame_pos: 0,
             should_reset: false,
+            frames_primed: 0,
+            decode_errors: 0,
         }
     }
 }
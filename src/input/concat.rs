@@ -0,0 +1,257 @@
+//! Playback of several inputs, back-to-back, as a single source.
+
+use super::{
+    error::{Error, Result},
+    Codec,
+    Container,
+    Input,
+    Metadata,
+    Reader,
+};
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Seek, SeekFrom},
+    time::Duration,
+};
+use symphonia_core::io::MediaSource;
+
+/// Creates an [`Input`] which plays each of `inputs` in turn, as one track.
+///
+/// The resulting track fires a single end event once its last member finishes.
+/// Its metadata is taken from the first member, with a duration given by the
+/// sum of all members' durations when each is known.
+///
+/// All members must have the same channel count: otherwise, this returns
+/// [`Error::ChannelMismatch`].
+///
+/// # Limitations
+///
+/// The concatenated input is only seekable if every member is. Seeks within members
+/// which have already been played through are exact; seeking further ahead requires
+/// reading (and discarding) each unplayed member up to the target position, as their
+/// lengths cannot be known in advance.
+///
+/// Members are not individually prepared by the driver. In particular, any lazily
+/// initialised [`Restartable`] members should be made playable before being
+/// concatenated.
+///
+/// [`Input`]: Input
+/// [`Error::ChannelMismatch`]: Error::ChannelMismatch
+/// [`Restartable`]: super::Restartable
+pub fn concat(inputs: Vec<Input>) -> Result<Input> {
+    let stereo = inputs.first().map(Input::is_stereo).unwrap_or(true);
+
+    if inputs.iter().any(|input| input.is_stereo() != stereo) {
+        return Err(Error::ChannelMismatch);
+    }
+
+    let duration = inputs
+        .iter()
+        .map(|input| input.metadata.duration)
+        .sum::<Option<Duration>>();
+
+    let metadata = inputs
+        .first()
+        .map(|input| Metadata {
+            duration,
+            ..(*input.metadata).clone()
+        })
+        .unwrap_or_default();
+
+    let reader = Reader::Extension(Box::new(Concat::new(inputs)));
+
+    Ok(Input::new(
+        stereo,
+        reader,
+        Codec::FloatPcm,
+        Container::Raw,
+        Some(metadata),
+    ))
+}
+
+/// A floating-point PCM bytestream formed from several [`Input`]s.
+///
+/// This is usually created via [`concat`].
+///
+/// [`Input`]: Input
+/// [`concat`]: concat()
+#[derive(Debug)]
+pub struct Concat {
+    inputs: Vec<Input>,
+    /// Index of the member currently being read.
+    current: usize,
+    /// Byte offsets where each member begins, for all members reached so far.
+    starts: Vec<u64>,
+    pos: u64,
+}
+
+impl Concat {
+    /// Combines `inputs` into one bytestream, to be played in order.
+    pub fn new(inputs: Vec<Input>) -> Self {
+        Self {
+            inputs,
+            current: 0,
+            starts: vec![0],
+            pos: 0,
+        }
+    }
+
+    /// Moves on to the next member, after the current one has run out.
+    fn advance(&mut self) {
+        if self.starts.len() == self.current + 1 {
+            self.starts.push(self.pos);
+        }
+
+        self.current += 1;
+    }
+
+    /// Reads and discards audio until `target` is reached, or all members run out.
+    fn skip_to(&mut self, target: u64) -> IoResult<()> {
+        let mut scratch = [0u8; 4096];
+
+        while self.pos < target {
+            let len = scratch.len().min((target - self.pos) as usize);
+
+            if self.read(&mut scratch[..len])? == 0 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for Concat {
+    fn read(&mut self, buffer: &mut [u8]) -> IoResult<usize> {
+        while let Some(input) = self.inputs.get_mut(self.current) {
+            let len = input.read(buffer)?;
+
+            if len > 0 || buffer.is_empty() {
+                self.pos += len as u64;
+                return Ok(len);
+            }
+
+            self.advance();
+        }
+
+        Ok(0)
+    }
+}
+
+impl Seek for Concat {
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        let target = match pos {
+            SeekFrom::Start(pos) => pos,
+            SeekFrom::Current(rel) => (self.pos as i64 + rel).max(0) as u64,
+            SeekFrom::End(_) =>
+                return Err(IoError::new(
+                    IoErrorKind::InvalidInput,
+                    "Concatenated inputs have no known end.",
+                )),
+        };
+
+        if !self.is_seekable() {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "Seeking not supported: not all concatenated inputs are seekable.",
+            ));
+        }
+
+        // Find the member holding `target`, among those reached so far.
+        // Only the last of these can have an unknown length.
+        let member = self
+            .starts
+            .iter()
+            .rposition(|start| *start <= target)
+            .unwrap_or(0);
+        let start = self.starts[member];
+
+        // Any later members which have been played must be heard from their start again.
+        let last_touched = self.current.min(self.inputs.len().saturating_sub(1));
+        for input in self
+            .inputs
+            .iter_mut()
+            .take(last_touched + 1)
+            .skip(member + 1)
+        {
+            input.seek(SeekFrom::Start(0))?;
+        }
+
+        if let Some(input) = self.inputs.get_mut(member) {
+            if member + 1 < self.starts.len() {
+                input.seek(SeekFrom::Start(target - start))?;
+                self.current = member;
+                self.pos = target;
+            } else {
+                input.seek(SeekFrom::Start(0))?;
+                self.current = member;
+                self.pos = start;
+                self.skip_to(target)?;
+            }
+        }
+
+        Ok(self.pos)
+    }
+}
+
+impl MediaSource for Concat {
+    fn is_seekable(&self) -> bool {
+        self.inputs.iter().all(Input::is_seekable)
+    }
+
+    fn len(&self) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn pcm_input(data: Vec<u8>) -> Input {
+        Input::float_pcm(true, data.into())
+    }
+
+    #[test]
+    fn concat_plays_members_in_turn() {
+        let first = make_sine(100, true);
+        let second = make_sine(60, true);
+        let expected = [&first[..], &second[..]].concat();
+
+        let mut input = concat(vec![pcm_input(first), pcm_input(second)]).unwrap();
+        let mut out = vec![];
+        input.read_to_end(&mut out).unwrap();
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn concat_seeks_across_boundaries() {
+        let first = make_sine(100, true);
+        let second = make_sine(60, true);
+        let expected = [&first[..], &second[..]].concat();
+
+        let mut input = concat(vec![pcm_input(first), pcm_input(second)]).unwrap();
+        let mut out = vec![];
+
+        // Forwards into an unplayed member, then back into an earlier one.
+        for target in [1000, 40].iter() {
+            input.seek(SeekFrom::Start(*target as u64)).unwrap();
+            out.clear();
+            input.read_to_end(&mut out).unwrap();
+
+            assert_eq!(out, &expected[*target..]);
+        }
+    }
+
+    #[test]
+    fn concat_rejects_mixed_channels() {
+        let mono = Input::float_pcm(false, make_sine(10, false).into());
+        let stereo = pcm_input(make_sine(10, true));
+
+        assert!(matches!(
+            concat(vec![mono, stereo]),
+            Err(Error::ChannelMismatch)
+        ));
+    }
+}
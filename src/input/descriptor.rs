@@ -0,0 +1,71 @@
+use super::restartable::{FfmpegRestarter, Restart, YtdlRestarter};
+use std::{
+    ffi::OsString,
+    fmt::{Debug, Formatter, Result as FormatResult},
+    sync::Arc,
+};
+
+type RestartFactory = dyn Fn() -> Box<dyn Restart + Send> + Send + Sync;
+
+/// Where an [`Input`]'s audio came from, so that a fresh copy can be created
+/// after the original has been played.
+///
+/// This is set automatically on inputs made by [`ytdl`], [`ytdl_search`] and [`ffmpeg`],
+/// and by their [`Restartable`] equivalents. Other sources can describe how to
+/// recreate themselves via [`Input::set_descriptor`].
+///
+/// [`Input`]: super::Input
+/// [`ytdl`]: super::ytdl
+/// [`ytdl_search`]: super::ytdl_search
+/// [`ffmpeg`]: super::ffmpeg
+/// [`Restartable`]: super::Restartable
+/// [`Input::set_descriptor`]: super::Input::set_descriptor
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum SourceDescriptor {
+    /// A URL (or search query) played via youtube-dl.
+    Ytdl(String),
+    /// A path to a file (or other location) opened by ffmpeg.
+    Ffmpeg(OsString),
+    /// A user-defined way to create a [`Restart`] for the source.
+    ///
+    /// See [`SourceDescriptor::custom`].
+    ///
+    /// [`Restart`]: Restart
+    /// [`SourceDescriptor::custom`]: SourceDescriptor::custom
+    Custom(Arc<RestartFactory>),
+}
+
+impl SourceDescriptor {
+    /// Describes a source which is recreated by a new [`Restart`] from `factory`.
+    ///
+    /// [`Restart`]: Restart
+    pub fn custom<F, R>(factory: F) -> Self
+    where
+        F: Fn() -> R + Send + Sync + 'static,
+        R: Restart + Send + 'static,
+    {
+        SourceDescriptor::Custom(Arc::new(move || Box::new(factory())))
+    }
+
+    /// Creates a [`Restart`] which recreates the described source.
+    ///
+    /// [`Restart`]: Restart
+    pub(crate) fn restarter(&self) -> Box<dyn Restart + Send> {
+        match self {
+            SourceDescriptor::Ytdl(uri) => Box::new(YtdlRestarter { uri: uri.clone() }),
+            SourceDescriptor::Ffmpeg(path) => Box::new(FfmpegRestarter { path: path.clone() }),
+            SourceDescriptor::Custom(factory) => factory(),
+        }
+    }
+}
+
+impl Debug for SourceDescriptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        match self {
+            SourceDescriptor::Ytdl(uri) => f.debug_tuple("Ytdl").field(uri).finish(),
+            SourceDescriptor::Ffmpeg(path) => f.debug_tuple("Ffmpeg").field(path).finish(),
+            SourceDescriptor::Custom(_) => f.debug_tuple("Custom").field(&"<fn>").finish(),
+        }
+    }
+}
@@ -12,6 +12,8 @@ use streamcatcher::CatcherError;
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum Error {
+    /// Inputs to be concatenated did not all have the same channel count.
+    ChannelMismatch,
     /// An error occurred while opening a new DCA source.
     Dca(DcaError),
     /// An error occurred while reading, or opening a file.
@@ -72,6 +74,7 @@ impl From<OpusError> for Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Error::ChannelMismatch => write!(f, "concatenated inputs had differing channel counts"),
             Error::Dca(_) => write!(f, "opening file DCA failed"),
             Error::Io(e) => e.fmt(f),
             Error::Json {
@@ -93,6 +96,7 @@ impl fmt::Display for Error {
 impl StdError for Error {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match self {
+            Error::ChannelMismatch => None,
             Error::Dca(e) => Some(e),
             Error::Io(e) => e.source(),
             Error::Json {
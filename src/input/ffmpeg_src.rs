@@ -5,6 +5,7 @@ use super::{
     Container,
     Input,
     Metadata,
+    SourceDescriptor,
 };
 use serde_json::Value;
 use std::{
@@ -25,7 +26,10 @@ use tracing::debug;
 ///
 /// [`Restartable::ffmpeg`]: crate::input::restartable::Restartable::ffmpeg
 pub async fn ffmpeg<P: AsRef<OsStr>>(path: P) -> Result<Input> {
-    _ffmpeg(path.as_ref()).await
+    let mut input = _ffmpeg(path.as_ref()).await?;
+    input.set_descriptor(Some(SourceDescriptor::Ffmpeg(path.as_ref().to_os_string())));
+
+    Ok(input)
 }
 
 pub(crate) async fn _ffmpeg(path: &OsStr) -> Result<Input> {
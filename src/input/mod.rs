@@ -31,11 +31,14 @@
 pub mod cached;
 mod child;
 pub mod codec;
+mod concat;
 mod container;
 mod dca;
+mod descriptor;
 pub mod error;
 mod ffmpeg_src;
 mod metadata;
+mod opus_packets;
 pub mod reader;
 pub mod restartable;
 pub mod utils;
@@ -43,13 +46,16 @@ mod ytdl_src;
 
 pub use self::{
     child::*,
-    codec::{Codec, CodecType},
+    codec::{Codec, CodecType, OpusDecoderState},
+    concat::{concat, Concat},
     container::{Container, Frame},
     dca::dca,
+    descriptor::SourceDescriptor,
     ffmpeg_src::*,
     metadata::Metadata,
+    opus_packets::{opus_packets, MAX_OPUS_PACKET_LEN},
     reader::Reader,
-    restartable::Restartable,
+    restartable::{InitLimit, Restartable},
     ytdl_src::*,
 };
 
@@ -79,6 +85,10 @@ use std::{
 };
 use tracing::{debug, error};
 
+/// Number of Opus frames decoded ahead of a seek target, after which a reset
+/// decoder's output is usable (80ms, as recommended by RFC 7845).
+const OPUS_PREROLL_FRAMES: usize = 4;
+
 /// Data and metadata needed to correctly parse a [`Reader`]'s audio bytestream.
 ///
 /// See the [module root] for more information.
@@ -98,6 +108,7 @@ pub struct Input {
     /// Framing strategy needed to identify frames of compressed audio.
     pub container: Container,
     pos: usize,
+    descriptor: Option<SourceDescriptor>,
 }
 
 impl Input {
@@ -110,6 +121,7 @@ impl Input {
             kind: Codec::FloatPcm,
             container: Container::Raw,
             pos: 0,
+            descriptor: None,
         }
     }
 
@@ -128,6 +140,7 @@ impl Input {
             kind,
             container,
             pos: 0,
+            descriptor: None,
         }
     }
 
@@ -151,6 +164,23 @@ impl Input {
         (&self.kind).into()
     }
 
+    /// Returns where this input's audio came from, if known.
+    ///
+    /// See [`SourceDescriptor`] for more details.
+    ///
+    /// [`SourceDescriptor`]: SourceDescriptor
+    pub fn descriptor(&self) -> Option<&SourceDescriptor> {
+        self.descriptor.as_ref()
+    }
+
+    /// Sets where this input's audio came from, allowing it to be recreated,
+    /// e.g., when a [`TrackQueue`] loops.
+    ///
+    /// [`TrackQueue`]: crate::tracks::TrackQueue
+    pub fn set_descriptor(&mut self, descriptor: Option<SourceDescriptor>) {
+        self.descriptor = descriptor;
+    }
+
     /// Mixes the output of this stream into a 20ms stereo audio buffer.
     #[inline]
     pub fn mix(&mut self, float_buffer: &mut [f32; STEREO_FRAME_SIZE], volume: f32) -> usize {
@@ -158,6 +188,17 @@ impl Input {
             .unwrap_or(0)
     }
 
+    /// Mixes the output of this stream into a 20ms stereo audio buffer, returning
+    /// `None` if the underlying source failed.
+    #[inline]
+    pub(crate) fn mix_checked(
+        &mut self,
+        float_buffer: &mut [f32; STEREO_FRAME_SIZE],
+        volume: f32,
+    ) -> Option<usize> {
+        self.add_float_pcm_frame(float_buffer, self.stereo, volume)
+    }
+
     /// Seeks the stream to the given time, if possible.
     ///
     /// Returns the actual time reached.
@@ -198,7 +239,7 @@ impl Input {
                     decoder_state.current_frame.truncate(0);
 
                     // Step two: take frames if we can.
-                    while buffer.len() - aud_skipped >= STEREO_FRAME_BYTE_SIZE {
+                    while buffer.len() >= aud_skipped + STEREO_FRAME_BYTE_SIZE {
                         decoder_state.should_reset = true;
 
                         let frame = self
@@ -234,13 +275,24 @@ impl Input {
                         let seen =
                             Read::read(&mut self.reader, &mut opus_data_buffer[..frame.frame_len])?;
 
-                        let samples = decoder
-                            .decode_float(
-                                Some(&opus_data_buffer[..seen]),
-                                &mut decoder_state.current_frame[..],
-                                false,
-                            )
-                            .unwrap_or(0);
+                        let decoded = decoder.decode_float(
+                            Some(&opus_data_buffer[..seen]),
+                            &mut decoder_state.current_frame[..],
+                            false,
+                        );
+                        let samples = match decoded {
+                            Ok(samples) => samples,
+                            Err(_) => {
+                                // An empty frame would read as the end of the track:
+                                // play a corrupt frame as silence instead.
+                                decoder_state.decode_errors =
+                                    decoder_state.decode_errors.saturating_add(1);
+                                for sample in decoder_state.current_frame.iter_mut() {
+                                    *sample = 0.0;
+                                }
+                                MONO_FRAME_SIZE
+                            },
+                        };
 
                         decoder_state.current_frame.truncate(2 * samples);
                         decoder_state.frame_pos = 0;
@@ -251,7 +303,7 @@ impl Input {
 
                     let start = decoder_state.frame_pos;
                     let to_write = float_space.min(decoder_state.current_frame.len() - start);
-                    for val in &decoder_state.current_frame[start..start + float_space] {
+                    for val in &decoder_state.current_frame[start..start + to_write] {
                         buffer.write_f32::<LittleEndian>(*val)?;
                     }
                     decoder_state.frame_pos += to_write;
@@ -281,22 +333,85 @@ impl Input {
         })
     }
 
+    /// Returns, and resets, the number of Opus frames decoded and discarded
+    /// to prime the decoder after seeking.
+    pub(crate) fn take_frames_primed(&mut self) -> u64 {
+        match &mut self.kind {
+            Codec::Opus(state) => mem::take(&mut state.frames_primed),
+            _ => 0,
+        }
+    }
+
+    /// Returns, and resets, the number of Opus frames which failed to decode.
+    pub(crate) fn take_decode_errors(&mut self) -> u32 {
+        match &mut self.kind {
+            Codec::Opus(state) => mem::take(&mut state.decode_errors),
+            _ => 0,
+        }
+    }
+
     fn cheap_consume(&mut self, count: usize) -> IoResult<usize> {
         let mut scratch = [0u8; STEREO_FRAME_BYTE_SIZE * 4];
         let len = scratch.len();
         let mut done = 0;
 
-        loop {
-            let read = self.read_inner(&mut scratch[..len.min(count - done)], true)?;
+        // Opus frames skipped over are never fed to the decoder, which must then
+        // be reset: its first few frames of output after this are garbage. Decode the
+        // last few frames before the target and throw away their audio, so that
+        // playback resumes from a decoder which has converged.
+        let preroll = match &self.kind {
+            Codec::Opus(_) => OPUS_PREROLL_FRAMES * STEREO_FRAME_BYTE_SIZE,
+            _ => 0,
+        };
+
+        while done + preroll < count {
+            let read = self.read_inner(&mut scratch[..len.min(count - done - preroll)], true)?;
             if read == 0 {
                 break;
             }
             done += read;
         }
 
+        let priming = matches!(&self.kind, Codec::Opus(state) if state.should_reset);
+
+        while done < count {
+            let new_frame = matches!(
+                &self.kind,
+                Codec::Opus(state) if state.frame_pos == state.current_frame.len()
+            );
+
+            let read = self.read_inner(&mut scratch[..len.min(count - done)], false)?;
+            if read == 0 {
+                break;
+            }
+            done += read;
+
+            if let Codec::Opus(state) = &mut self.kind {
+                if priming && new_frame {
+                    state.frames_primed += 1;
+                }
+            }
+        }
+
         Ok(done)
     }
 
+    /// Returns whether any audio has been read from the source or decoded,
+    /// but not yet played.
+    pub(crate) fn has_buffered_audio(&self) -> bool {
+        let decoded = match &self.kind {
+            Codec::Opus(state) => state.frame_pos < state.current_frame.len(),
+            _ => false,
+        };
+
+        let read = match &self.reader {
+            Reader::Pipe(pipe) => !pipe.buffer().is_empty(),
+            _ => false,
+        };
+
+        decoded || read
+    }
+
     pub(crate) fn supports_passthrough(&self) -> bool {
         match &self.kind {
             Codec::Opus(state) => state.allow_passthrough,
@@ -333,8 +448,34 @@ impl Input {
         }
     }
 
-    pub(crate) fn prep_with_handle(&mut self, handle: Handle) {
-        self.reader.prep_with_handle(handle);
+    /// Creates a second, independent reader over the same audio, if this input
+    /// is backed by an in-memory cache.
+    ///
+    /// The new input starts from the beginning of the audio, with its own decoder.
+    pub(crate) fn new_view(&self) -> Option<Input> {
+        let reader = match &self.reader {
+            Reader::Memory(raw) => Reader::Memory(raw.new_handle()),
+            Reader::Compressed(raw) => Reader::Compressed(raw.new_handle()),
+            _ => return None,
+        };
+
+        // Opus decoder state is shared between clones, so must be replaced.
+        let kind = match &self.kind {
+            Codec::Opus(_) => Codec::Opus(OpusDecoderState::new().ok()?),
+            other => other.clone(),
+        };
+
+        Some(Input::new(
+            self.stereo,
+            reader,
+            kind,
+            self.container,
+            Some((*self.metadata).clone()),
+        ))
+    }
+
+    pub(crate) fn prep_with_handle(&mut self, handle: Handle, init_limit: Option<InitLimit>) {
+        self.reader.prep_with_handle(handle, init_limit);
     }
 }
 
@@ -378,6 +519,14 @@ impl Seek for Input {
                 &mut self.reader,
                 SeekFrom::Start(self.container.input_start() as u64),
             )?;
+            self.pos = 0;
+
+            // Any decoder state now belongs to the wrong part of the stream.
+            if let Codec::Opus(state) = &mut self.kind {
+                state.current_frame.truncate(0);
+                state.frame_pos = 0;
+                state.should_reset = true;
+            }
 
             self.cheap_consume(target)
         })
@@ -516,7 +665,7 @@ impl<R: Read + Sized> ReadAudioExt for R {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use super::{codec::OpusDecoderState, *};
     use crate::test_utils::*;
 
     #[test]
@@ -574,6 +723,78 @@ mod tests {
         }
     }
 
+    fn opus_dca_sine(frames: usize) -> Vec<u8> {
+        use audiopus::{coder::Encoder, Application, Channels};
+
+        let encoder = Encoder::new(SAMPLE_RATE, Channels::Stereo, Application::Audio).unwrap();
+        let pcm = make_sine(frames * MONO_FRAME_SIZE, true);
+        let mut samples = &pcm[..];
+        let mut float_frame = vec![0f32; STEREO_FRAME_SIZE];
+        let mut opus_frame = [0u8; 4000];
+        let mut out = vec![];
+
+        for _ in 0..frames {
+            samples
+                .read_f32_into::<LittleEndian>(&mut float_frame[..])
+                .unwrap();
+            let len = encoder
+                .encode_float(&float_frame[..], &mut opus_frame[..])
+                .unwrap();
+
+            out.write_i16::<LittleEndian>(len as i16).unwrap();
+            out.extend_from_slice(&opus_frame[..len]);
+        }
+
+        out
+    }
+
+    fn max_error(a: &[u8], b: &[u8]) -> f32 {
+        a.chunks(mem::size_of::<f32>())
+            .zip(b.chunks(mem::size_of::<f32>()))
+            .map(|(mut a, mut b)| {
+                let a = a.read_f32::<LittleEndian>().unwrap();
+                let b = b.read_f32::<LittleEndian>().unwrap();
+                (a - b).abs()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn opus_seek_discards_priming_frames() {
+        let data = opus_dca_sine(50);
+        let open = || {
+            Input::new(
+                true,
+                data.clone().into(),
+                Codec::Opus(OpusDecoderState::new().unwrap()),
+                Container::Dca { first_frame: 0 },
+                None,
+            )
+        };
+
+        // DCA sources report the end of a stream as an error.
+        let mut reference = open();
+        let mut expected = vec![0u8; 50 * STEREO_FRAME_BYTE_SIZE];
+        reference.read_exact(&mut expected[..]).unwrap();
+
+        let mut input = open();
+        let mut frame = vec![0u8; STEREO_FRAME_BYTE_SIZE];
+
+        for &target_frame in &[30, 10] {
+            let target = target_frame * STEREO_FRAME_BYTE_SIZE;
+            let reached = input.seek(SeekFrom::Start(target as u64)).unwrap();
+            assert_eq!(reached as usize, target);
+            assert_eq!(input.take_frames_primed(), OPUS_PREROLL_FRAMES as u64);
+
+            input.read_exact(&mut frame[..]).unwrap();
+            let err = max_error(
+                &frame[..],
+                &expected[target..target + STEREO_FRAME_BYTE_SIZE],
+            );
+            assert!(err < 0.005);
+        }
+    }
+
     #[test]
     fn pcm_input_becomes_float_stereo() {
         let data = make_pcm_sine(50 * MONO_FRAME_SIZE, true);
@@ -0,0 +1,120 @@
+//! Playback of pre-encoded Opus packets, without decoding where possible.
+
+use super::{error::Result, Codec, Container, Input, Metadata, OpusDecoderState, Reader};
+use crate::constants::{MAX_CRYPTO_OVERHEAD, VOICE_PACKET_MAX};
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult};
+use symphonia_core::io::ReadOnlySource;
+
+/// Size of the fixed RTP header preceding each voice packet's payload.
+const RTP_HEADER_LEN: usize = 12;
+
+/// Largest Opus packet which can be sent as-is under every [`CryptoMode`].
+///
+/// [`CryptoMode`]: crate::driver::CryptoMode
+pub const MAX_OPUS_PACKET_LEN: usize = VOICE_PACKET_MAX - RTP_HEADER_LEN - MAX_CRYPTO_OVERHEAD;
+
+/// Creates an [`Input`] from a sequence of already-encoded Opus packets, e.g., as
+/// relayed from another voice connection or read from a cache.
+///
+/// When this is the only track playing at full volume, the driver sends each packet
+/// to Discord unchanged, rather than decoding and re-encoding it. Otherwise, packets
+/// are decoded and mixed like any other input.
+///
+/// Every packet must hold exactly 20ms of 48kHz audio, as sent by Discord clients
+/// and produced by Songbird's encoder: packets of any other frame size will play at
+/// the wrong speed, and desynchronise the track's position. Packets larger than
+/// [`MAX_OPUS_PACKET_LEN`] cannot fit in a voice packet alongside its encryption
+/// overhead, and end the track with an [`InvalidData`] error once reached.
+///
+/// Packets are pulled from `packets` only as they are needed, so this may be a
+/// live feed. The track ends once the iterator does. The resulting input cannot be
+/// seeked.
+///
+/// [`Input`]: Input
+/// [`MAX_OPUS_PACKET_LEN`]: MAX_OPUS_PACKET_LEN
+/// [`InvalidData`]: std::io::ErrorKind::InvalidData
+pub fn opus_packets<I>(packets: I, stereo: bool, metadata: Option<Metadata>) -> Result<Input>
+where
+    I: IntoIterator<Item = Vec<u8>>,
+    I::IntoIter: Send + 'static,
+{
+    let reader = PacketReader {
+        packets: packets.into_iter(),
+        frame: Vec::new(),
+        frame_pos: 0,
+    };
+
+    Ok(Input::new(
+        stereo,
+        Reader::Extension(Box::new(ReadOnlySource::new(reader))),
+        Codec::Opus(OpusDecoderState::new()?),
+        Container::Dca { first_frame: 0 },
+        metadata,
+    ))
+}
+
+/// Writes each packet out in DCA framing, as the driver expects of Opus inputs.
+struct PacketReader<I> {
+    packets: I,
+    frame: Vec<u8>,
+    frame_pos: usize,
+}
+
+impl<I: Iterator<Item = Vec<u8>>> Read for PacketReader<I> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if self.frame_pos >= self.frame.len() {
+            let packet = match self.packets.next() {
+                Some(packet) => packet,
+                None => return Ok(0),
+            };
+
+            if packet.len() > MAX_OPUS_PACKET_LEN {
+                return Err(IoError::new(
+                    IoErrorKind::InvalidData,
+                    format!(
+                        "Opus packet of {}B exceeds the {}B limit.",
+                        packet.len(),
+                        MAX_OPUS_PACKET_LEN
+                    ),
+                ));
+            }
+
+            self.frame.clear();
+            self.frame.write_i16::<LittleEndian>(packet.len() as i16)?;
+            self.frame.extend_from_slice(&packet);
+            self.frame_pos = 0;
+        }
+
+        let len = buf.len().min(self.frame.len() - self.frame_pos);
+        buf[..len].copy_from_slice(&self.frame[self.frame_pos..self.frame_pos + len]);
+        self.frame_pos += len;
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_pass_through_unchanged() {
+        let packets = vec![
+            vec![1u8; 3],
+            vec![2u8; 40],
+            vec![3u8; MAX_OPUS_PACKET_LEN + 1],
+        ];
+        let mut input = opus_packets(packets, true, None).unwrap();
+        assert!(input.supports_passthrough());
+
+        let mut buf = [0u8; VOICE_PACKET_MAX];
+        assert_eq!(input.read_opus_frame(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], &[1u8; 3]);
+        assert_eq!(input.read_opus_frame(&mut buf).unwrap(), 40);
+        assert_eq!(&buf[..40], &[2u8; 40][..]);
+
+        let err = input.read_opus_frame(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), IoErrorKind::InvalidData);
+    }
+}
@@ -76,14 +76,25 @@ impl Reader {
     }
 
     #[allow(clippy::single_match)]
-    pub(crate) fn prep_with_handle(&mut self, handle: Handle) {
+    pub(crate) fn prep_with_handle(&mut self, handle: Handle, init_limit: Option<InitLimit>) {
         use Reader::*;
         match self {
-            Restartable(r) => r.prep_with_handle(handle),
+            Restartable(r) => r.prep_with_handle(handle, init_limit),
             _ => {},
         }
     }
 
+    /// Returns whether this source can be read from without first being created,
+    /// i.e., whether any lazily initialised [`Restartable`] has been started.
+    ///
+    /// [`Restartable`]: Reader::Restartable
+    pub(crate) fn is_ready(&self) -> bool {
+        match self {
+            Reader::Restartable(r) => r.is_ready(),
+            _ => true,
+        }
+    }
+
     #[allow(clippy::single_match)]
     pub(crate) fn make_playable(&mut self) {
         use Reader::*;
@@ -11,7 +11,7 @@
 
 use super::*;
 use async_trait::async_trait;
-use flume::{Receiver, TryRecvError};
+use flume::{Receiver, Sender, TryRecvError};
 use std::{
     ffi::OsStr,
     fmt::{Debug, Error as FormatError, Formatter},
@@ -53,6 +53,58 @@ impl Debug for LazyProgress {
     }
 }
 
+/// A limit on how many [`Restartable`] sources may be creating their inputs at once.
+///
+/// Clones of a limit share the same permits. This is usually set via
+/// [`Config::max_concurrent_input_init`].
+///
+/// [`Restartable`]: Restartable
+/// [`Config::max_concurrent_input_init`]: crate::Config::max_concurrent_input_init
+#[derive(Clone, Debug)]
+pub struct InitLimit {
+    max: usize,
+    // Each message held in the channel is one permit in use.
+    acquire: Sender<()>,
+    release: Receiver<()>,
+}
+
+impl InitLimit {
+    /// Creates a limit allowing up to `max` sources to be created concurrently.
+    ///
+    /// A `max` of `0` is treated as `1`.
+    pub fn new(max: usize) -> Self {
+        let max = max.max(1);
+        let (acquire, release) = flume::bounded(max);
+
+        Self {
+            max,
+            acquire,
+            release,
+        }
+    }
+
+    /// Returns the maximum number of sources which may be created at once.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Waits until a permit is free, which is held until the returned guard is dropped.
+    async fn acquire(&self) -> InitPermit<'_> {
+        // Neither half of the channel can be dropped while `self` is alive.
+        let _ = self.acquire.send_async(()).await;
+
+        InitPermit(self)
+    }
+}
+
+struct InitPermit<'a>(&'a InitLimit);
+
+impl Drop for InitPermit<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.release.try_recv();
+    }
+}
+
 /// A wrapper around a method to create a new [`Input`] which
 /// seeks backward by recreating the source.
 ///
@@ -73,8 +125,10 @@ impl Debug for LazyProgress {
 #[derive(Debug)]
 pub struct Restartable {
     async_handle: Option<Handle>,
+    init_limit: Option<InitLimit>,
     position: usize,
     source: LazyProgress,
+    descriptor: Option<SourceDescriptor>,
 }
 
 impl Restartable {
@@ -92,6 +146,7 @@ impl Restartable {
                 .await
                 .map(move |(meta, kind, codec)| Self {
                     async_handle: None,
+                    init_limit: None,
                     position: 0,
                     source: LazyProgress::Dead(
                         meta.unwrap_or_default().into(),
@@ -99,12 +154,15 @@ impl Restartable {
                         kind,
                         codec,
                     ),
+                    descriptor: None,
                 })
         } else {
             recreator.call_restart(None).await.map(move |source| Self {
                 async_handle: None,
+                init_limit: None,
                 position: 0,
                 source: LazyProgress::Live(source.into(), Some(Box::new(recreator))),
+                descriptor: None,
             })
         }
     }
@@ -114,7 +172,11 @@ impl Restartable {
         path: P,
         lazy: bool,
     ) -> Result<Self> {
-        Self::new(FfmpegRestarter { path }, lazy).await
+        let descriptor = SourceDescriptor::Ffmpeg(path.as_ref().to_os_string());
+
+        Self::new(FfmpegRestarter { path }, lazy)
+            .await
+            .map(|src| src.with_descriptor(descriptor))
     }
 
     /// Create a new restartable ytdl source.
@@ -125,7 +187,15 @@ impl Restartable {
         uri: P,
         lazy: bool,
     ) -> Result<Self> {
-        Self::new(YtdlRestarter { uri }, lazy).await
+        let src = Self::new(YtdlRestarter { uri: uri.clone() }, lazy).await?;
+
+        // Searches are recreated from whichever result they found.
+        let uri = src
+            .metadata()
+            .and_then(|meta| meta.source_url.clone())
+            .unwrap_or_else(|| uri.as_ref().to_string());
+
+        Ok(src.with_descriptor(SourceDescriptor::Ytdl(uri)))
     }
 
     /// Create a new restartable ytdl source, using the first result of a youtube search.
@@ -136,8 +206,50 @@ impl Restartable {
         Self::ytdl(format!("ytsearch1:{}", name.as_ref()), lazy).await
     }
 
-    pub(crate) fn prep_with_handle(&mut self, handle: Handle) {
+    /// Create a new lazy source from its `descriptor`, using previously fetched metadata,
+    /// without creating the source until it is needed.
+    pub(crate) fn from_descriptor(
+        descriptor: SourceDescriptor,
+        metadata: Metadata,
+        kind: Codec,
+        container: Container,
+    ) -> Self {
+        Self {
+            async_handle: None,
+            init_limit: None,
+            position: 0,
+            source: LazyProgress::Dead(
+                metadata.into(),
+                Some(descriptor.restarter()),
+                kind,
+                container,
+            ),
+            descriptor: Some(descriptor),
+        }
+    }
+
+    fn with_descriptor(mut self, descriptor: SourceDescriptor) -> Self {
+        self.descriptor = Some(descriptor);
+        self
+    }
+
+    fn metadata(&self) -> Option<&Metadata> {
+        match &self.source {
+            LazyProgress::Dead(meta, _, _, _) => Some(meta),
+            LazyProgress::Live(input, _) => Some(&input.metadata),
+            LazyProgress::Working(_, _, _, _) => None,
+        }
+    }
+
+    pub(crate) fn prep_with_handle(&mut self, handle: Handle, init_limit: Option<InitLimit>) {
         self.async_handle = Some(handle);
+        self.init_limit = init_limit;
+    }
+
+    /// Returns whether this source has been created, and can be read from
+    /// without waiting on its recreator.
+    pub(crate) fn is_ready(&self) -> bool {
+        matches!(self.source, LazyProgress::Live(_, _))
     }
 
     pub(crate) fn make_playable(&mut self) {
@@ -167,11 +279,11 @@ pub trait Restart {
     async fn lazy_init(&mut self) -> Result<(Option<Metadata>, Codec, Container)>;
 }
 
-struct FfmpegRestarter<P>
+pub(crate) struct FfmpegRestarter<P>
 where
     P: AsRef<OsStr> + Send + Sync,
 {
-    path: P,
+    pub(crate) path: P,
 }
 
 #[async_trait]
@@ -216,11 +328,11 @@ where
     }
 }
 
-struct YtdlRestarter<P>
+pub(crate) struct YtdlRestarter<P>
 where
     P: AsRef<str> + Send + Sync,
 {
-    uri: P,
+    pub(crate) uri: P,
 }
 
 #[async_trait]
@@ -262,7 +374,11 @@ impl From<Restartable> for Input {
             LazyProgress::Working(kind, container, stereo, _) =>
                 (None, *stereo, kind.clone(), *container),
         };
-        Input::new(stereo, Reader::Restartable(src), kind, container, meta)
+        let descriptor = src.descriptor.take();
+
+        let mut input = Input::new(stereo, Reader::Restartable(src), kind, container, meta);
+        input.set_descriptor(descriptor);
+        input
     }
 }
 
@@ -277,6 +393,7 @@ impl Read for Restartable {
             Dead(meta, rec, kind, container) => {
                 let stereo = meta.channels == Some(2);
                 let handle = self.async_handle.clone();
+                let init_limit = self.init_limit.clone();
                 let new_chan = if let Some(rec) = rec.take() {
                     Some(regenerate_channel(
                         rec,
@@ -285,6 +402,7 @@ impl Read for Restartable {
                         kind.clone(),
                         *container,
                         handle,
+                        init_limit,
                     )?)
                 } else {
                     return Err(IoError::new(
@@ -309,9 +427,11 @@ impl Read for Restartable {
 
                         (bytes_read, true, Some(Live(new_source, Some(recreator))))
                     },
+                    // Failures are not reported as `UnexpectedEof`, so that the
+                    // mixer does not mistake them for the end of the track.
                     Ok(Err(source_error)) => {
                         let e = Err(IoError::new(
-                            IoErrorKind::UnexpectedEof,
+                            IoErrorKind::BrokenPipe,
                             format!("Failed to create new reader: {:?}.", source_error),
                         ));
                         (e, false, None)
@@ -325,7 +445,7 @@ impl Read for Restartable {
                     },
                     Err(_) => {
                         let e = Err(IoError::new(
-                            IoErrorKind::UnexpectedEof,
+                            IoErrorKind::BrokenPipe,
                             "Failed to create new reader: dropped.",
                         ));
                         (e, false, None)
@@ -358,6 +478,7 @@ impl Seek for Restartable {
             Start(offset) => {
                 let offset = offset as usize;
                 let handle = self.async_handle.clone();
+                let init_limit = self.init_limit.clone();
 
                 use LazyProgress::*;
                 match &mut self.source {
@@ -371,6 +492,7 @@ impl Seek for Restartable {
                                 kind.clone(),
                                 *container,
                                 handle,
+                                init_limit,
                             )?
                         } else {
                             return Err(IoError::new(
@@ -393,6 +515,7 @@ impl Seek for Restartable {
                                     input.kind.clone(),
                                     input.container,
                                     handle,
+                                    init_limit,
                                 )?
                             } else {
                                 return Err(IoError::new(
@@ -433,11 +556,17 @@ fn regenerate_channel(
     kind: Codec,
     container: Container,
     handle: Option<Handle>,
+    init_limit: Option<InitLimit>,
 ) -> IoResult<LazyProgress> {
     if let Some(handle) = handle.as_ref() {
         let (tx, rx) = flume::bounded(1);
 
         handle.spawn(async move {
+            let _permit = match &init_limit {
+                Some(limit) => Some(limit.acquire().await),
+                None => None,
+            };
+
             let ret_val = rec
                 .call_restart(Some(utils::byte_count_to_timestamp(offset, stereo)))
                 .await;
@@ -453,3 +582,22 @@ fn regenerate_channel(
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, FutureExt};
+
+    #[test]
+    fn init_limit_holds_permits_until_dropped() {
+        let limit = InitLimit::new(2);
+        let shared = limit.clone();
+
+        let first = block_on(limit.acquire());
+        let _second = block_on(shared.acquire());
+        assert!(limit.acquire().now_or_never().is_none());
+
+        drop(first);
+        assert!(shared.acquire().now_or_never().is_some());
+    }
+}
@@ -5,7 +5,9 @@ use super::{
     Container,
     Input,
     Metadata,
+    SourceDescriptor,
 };
+use futures::stream::{self, StreamExt};
 use serde_json::Value;
 use std::{
     io::{BufRead, BufReader, Read},
@@ -36,7 +38,17 @@ const YOUTUBE_DL_COMMAND: &str = if cfg!(feature = "youtube-dlc") {
 ///
 /// [`Restartable::ytdl`]: crate::input::restartable::Restartable::ytdl
 pub async fn ytdl(uri: impl AsRef<str>) -> Result<Input> {
-    _ytdl(uri.as_ref(), &[]).await
+    let mut input = _ytdl(uri.as_ref(), &[]).await?;
+
+    // Searches are recreated from whichever result they found.
+    let uri = input
+        .metadata
+        .source_url
+        .clone()
+        .unwrap_or_else(|| uri.as_ref().to_string());
+    input.set_descriptor(Some(SourceDescriptor::Ytdl(uri)));
+
+    Ok(input)
 }
 
 pub(crate) async fn _ytdl(uri: &str, pre_args: &[&str]) -> Result<Input> {
@@ -177,3 +189,23 @@ pub(crate) async fn _ytdl_metadata(uri: &str) -> Result<Metadata> {
 pub async fn ytdl_search(name: impl AsRef<str>) -> Result<Input> {
     ytdl(&format!("ytsearch1:{}", name.as_ref())).await
 }
+
+/// Fetches metadata for several `youtube-dl` sources, without creating any [`Input`]s.
+///
+/// This runs the same query used by lazy [`Restartable::ytdl`] sources, e.g., to show
+/// the titles and durations of a playlist before it is queued. At most `concurrency`
+/// queries run at once (and at least one). Results are returned in the same order
+/// as `uris`.
+///
+/// [`Input`]: Input
+/// [`Restartable::ytdl`]: crate::input::restartable::Restartable::ytdl
+pub async fn prefetch_metadata<S: AsRef<str>>(
+    uris: &[S],
+    concurrency: usize,
+) -> Vec<Result<Metadata>> {
+    stream::iter(uris)
+        .map(|uri| _ytdl_metadata(uri.as_ref()))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
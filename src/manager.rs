@@ -180,6 +180,45 @@ impl Songbird {
         })
     }
 
+    /// Returns the number of [`Call`]s held by this manager, whether or not
+    /// they are connected.
+    ///
+    /// [`Call`]: Call
+    pub fn call_count(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Returns the number of [`Call`]s which are currently connected.
+    ///
+    /// When using the driver, this counts calls whose voice connection is live,
+    /// and which are able to send audio: calls which are still connecting, or are
+    /// reconnecting after a dropped connection, are excluded. Without the driver,
+    /// this counts calls which have received full connection info from the gateway.
+    ///
+    /// This briefly locks every call in turn.
+    ///
+    /// [`Call`]: Call
+    pub async fn active_count(&self) -> usize {
+        // Clone out each call, so that no part of the map is locked across an await.
+        let calls: Vec<_> = self.calls.iter().map(|call| call.value().clone()).collect();
+        let mut active = 0;
+
+        for call in calls {
+            let call = call.lock().await;
+
+            #[cfg(feature = "driver-core")]
+            let connected = call.is_connected();
+            #[cfg(not(feature = "driver-core"))]
+            let connected = call.current_connection().is_some();
+
+            if connected {
+                active += 1;
+            }
+        }
+
+        active
+    }
+
     /// Sets a shared configuration for all drivers created from this
     /// manager.
     ///
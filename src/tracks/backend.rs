@@ -0,0 +1,104 @@
+//! Storage for a mirror of a [`TrackQueue`]'s contents.
+//!
+//! [`TrackQueue`]: super::TrackQueue
+
+use crate::input::Metadata;
+use std::{collections::VecDeque, fmt};
+use uuid::Uuid;
+
+/// A record of one track held in a [`TrackQueue`].
+///
+/// Entries hold only a track's identity and metadata, and cannot be used
+/// to control or play the track: the live [`TrackHandle`] always stays
+/// within the local queue.
+///
+/// [`TrackQueue`]: super::TrackQueue
+/// [`TrackHandle`]: super::TrackHandle
+#[derive(Clone, Debug)]
+pub struct QueueEntry {
+    /// The track's unique identifier.
+    pub uuid: Uuid,
+    /// Whether the track was [pinned] when it was added.
+    ///
+    /// [pinned]: super::Queued::set_pinned
+    pub pinned: bool,
+    /// The track's metadata, as given by its [`TrackHandle`].
+    ///
+    /// [`TrackHandle`]: super::TrackHandle
+    pub metadata: Metadata,
+}
+
+/// An external store kept in step with the order of a [`TrackQueue`].
+///
+/// This allows, e.g., queues to be mirrored into a shared database so that
+/// other processes can display or rebuild them. Backends are installed with
+/// [`TrackQueue::set_backend`], and are told about every change to the queue's
+/// contents while its lock is held: implementations **should not** block,
+/// and may wish to forward each operation to a background task.
+///
+/// Changes to a track's [pinned] status made through [`TrackQueue::modify_queue`]
+/// do not alter the queue's order, and are not reported.
+///
+/// [`TrackQueue`]: super::TrackQueue
+/// [`TrackQueue::set_backend`]: super::TrackQueue::set_backend
+/// [pinned]: super::Queued::set_pinned
+/// [`TrackQueue::modify_queue`]: super::TrackQueue::modify_queue
+pub trait QueueBackend: Send {
+    /// Inserts a new entry at position `index`.
+    ///
+    /// `index` is never greater than the number of stored entries.
+    fn push(&mut self, index: usize, entry: QueueEntry);
+
+    /// Removes the entry at the head of the queue, after it has finished playing.
+    fn pop(&mut self) -> Option<QueueEntry>;
+
+    /// Rearranges all stored entries to match `order`.
+    ///
+    /// Entries which are absent from `order` have been removed from the queue,
+    /// and should be discarded.
+    fn reorder(&mut self, order: &[Uuid]);
+
+    /// Returns all stored entries, in queue order.
+    fn snapshot(&self) -> Vec<QueueEntry>;
+}
+
+impl fmt::Debug for dyn QueueBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "QueueBackend")
+    }
+}
+
+/// A [`QueueBackend`] which holds its entries in memory.
+///
+/// This is mainly useful as a reference implementation, or for
+/// inspecting the operations performed on a queue.
+///
+/// [`QueueBackend`]: QueueBackend
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+    entries: VecDeque<QueueEntry>,
+}
+
+impl QueueBackend for MemoryBackend {
+    fn push(&mut self, index: usize, entry: QueueEntry) {
+        self.entries.insert(index, entry);
+    }
+
+    fn pop(&mut self) -> Option<QueueEntry> {
+        self.entries.pop_front()
+    }
+
+    fn reorder(&mut self, order: &[Uuid]) {
+        let mut old = std::mem::take(&mut self.entries);
+
+        for uuid in order {
+            if let Some(pos) = old.iter().position(|entry| entry.uuid == *uuid) {
+                self.entries.extend(old.remove(pos));
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Vec<QueueEntry> {
+        self.entries.iter().cloned().collect()
+    }
+}
@@ -1,5 +1,5 @@
 use super::*;
-use crate::events::EventData;
+use crate::{events::EventData, input::Input, tracks::effects::Effect};
 use flume::Sender;
 use std::time::Duration;
 
@@ -14,20 +14,42 @@ pub enum TrackCommand {
     Play,
     /// Set the track's play_mode to pause.
     Pause,
+    /// Pause the track, while continuing to read and discard its live input.
+    PauseLive,
     /// Stop the target track. This cannot be undone.
     Stop,
+    /// Stop the target track once its buffered audio has played. This cannot be undone.
+    StopAfterBuffer,
     /// Set the track's volume.
     Volume(f32),
     /// Seek to the given duration.
     ///
     /// On unsupported input types, this can be fatal.
     Seek(Duration),
+    /// Seek to the given duration, reporting the position actually reached.
+    ///
+    /// On unsupported input types, this can be fatal.
+    SeekWithResult(Duration, Sender<TrackResult<Duration>>),
+    /// Append an audio processing stage to the track.
+    AddEffect(Box<dyn Effect>),
+    /// Set whether the track lowers the volume of all others, and by how much.
+    Ducking(bool, f32),
     /// Register an event on this track.
     AddEvent(EventData),
     /// Run some closure on this track, with direct access to the core object.
     Do(Box<dyn FnOnce(&mut Track) + Send + Sync + 'static>),
     /// Request a copy of this track's state.
     Request(Sender<TrackState>),
+    /// Request a copy of this track's playback totals.
+    RequestStats(Sender<PlayStats>),
+    /// Request the number of Opus frames which failed to decode.
+    RequestDecodeErrors(Sender<u32>),
+    /// Reset this track's count of failed Opus frames.
+    ResetDecodeErrors,
+    /// Request the sizes of Opus packets this track has sent via passthrough.
+    RequestPacketSizes(Sender<PacketSizeStats>),
+    /// Request a second, independent reader over this track's cached audio, if possible.
+    NewView(Sender<Option<Input>>),
     /// Change the loop count/strategy of this track.
     Loop(LoopState),
     /// Prompts a track's input to become live and usable, if it is not already.
@@ -43,12 +65,22 @@ impl std::fmt::Debug for TrackCommand {
             match self {
                 Play => "Play".to_string(),
                 Pause => "Pause".to_string(),
+                PauseLive => "PauseLive".to_string(),
                 Stop => "Stop".to_string(),
+                StopAfterBuffer => "StopAfterBuffer".to_string(),
                 Volume(vol) => format!("Volume({})", vol),
                 Seek(d) => format!("Seek({:?})", d),
+                SeekWithResult(d, tx) => format!("SeekWithResult({:?}, {:?})", d, tx),
+                AddEffect(_) => "AddEffect([effect])".to_string(),
+                Ducking(ducking, att) => format!("Ducking({}, {})", ducking, att),
                 AddEvent(evt) => format!("AddEvent({:?})", evt),
                 Do(_f) => "Do([function])".to_string(),
                 Request(tx) => format!("Request({:?})", tx),
+                RequestStats(tx) => format!("RequestStats({:?})", tx),
+                RequestDecodeErrors(tx) => format!("RequestDecodeErrors({:?})", tx),
+                ResetDecodeErrors => "ResetDecodeErrors".to_string(),
+                RequestPacketSizes(tx) => format!("RequestPacketSizes({:?})", tx),
+                NewView(tx) => format!("NewView({:?})", tx),
                 Loop(loops) => format!("Loop({:?})", loops),
                 MakePlayable => "MakePlayable".to_string(),
             }
@@ -0,0 +1,225 @@
+//! Audio processing stages which may be attached to a [`Track`].
+//!
+//! [`Track`]: super::Track
+
+use crate::constants::SAMPLE_RATE_RAW;
+use std::{f32::consts::PI, fmt};
+
+/// A digital signal processing stage, applied to a track's decoded audio.
+///
+/// Effects run in the order they were added, once per 20ms frame, before the track's
+/// volume is applied and its audio is mixed with all others. Tracks with any
+/// effects cannot use Opus passthrough.
+///
+/// Implementations **must not** block or perform costly work, as effects run
+/// within the driver's mixing thread.
+pub trait Effect: Send {
+    /// Modifies a frame of 48kHz audio in place.
+    ///
+    /// `samples` holds interleaved samples for `channels` channels.
+    fn process(&mut self, samples: &mut [f32], channels: usize);
+}
+
+impl fmt::Debug for dyn Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Effect")
+    }
+}
+
+/// Multiplies all samples by a fixed factor.
+///
+/// The main use of this is raising a track's volume beyond what would be
+/// clipped by its own [volume] control, before other effects; otherwise,
+/// prefer [volume].
+///
+/// [volume]: super::Track::set_volume
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gain(pub f32);
+
+impl Effect for Gain {
+    fn process(&mut self, samples: &mut [f32], _channels: usize) {
+        for sample in samples {
+            *sample *= self.0;
+        }
+    }
+}
+
+/// A second-order IIR filter, applied to each channel separately.
+///
+/// Coefficients follow the formulae of Robert Bristow-Johnson's
+/// [Audio EQ Cookbook].
+///
+/// [Audio EQ Cookbook]: https://www.w3.org/TR/audio-eq-cookbook/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    /// Transposed direct form II state, per channel.
+    state: Vec<[f32; 2]>,
+}
+
+impl Biquad {
+    /// Creates a filter with the given normalised coefficients, where `a0 = 1`.
+    pub fn new(b0: f32, b1: f32, b2: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b: [b0, b1, b2],
+            a: [a1, a2],
+            state: Vec::new(),
+        }
+    }
+
+    /// Creates a low-pass filter, removing frequencies above `cutoff` (in Hz).
+    ///
+    /// `q` controls the resonance at the cutoff: `0.707` gives a flat passband.
+    pub fn low_pass(cutoff: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::intermediates(cutoff, q);
+
+        Self::normalised(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Creates a low-shelf filter, which raises (or lowers) all frequencies
+    /// below `freq` (in Hz) by `gain_db` decibels.
+    ///
+    /// A positive gain on a low frequency (e.g., 100Hz) gives a "bass boost".
+    pub fn low_shelf(freq: f32, gain_db: f32) -> Self {
+        let amp = 10f32.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::intermediates(freq, std::f32::consts::FRAC_1_SQRT_2);
+        let root = 2.0 * amp.sqrt() * alpha;
+
+        Self::normalised(
+            [
+                amp * ((amp + 1.0) - (amp - 1.0) * cos + root),
+                2.0 * amp * ((amp - 1.0) - (amp + 1.0) * cos),
+                amp * ((amp + 1.0) - (amp - 1.0) * cos - root),
+            ],
+            [
+                (amp + 1.0) + (amp - 1.0) * cos + root,
+                -2.0 * ((amp - 1.0) + (amp + 1.0) * cos),
+                (amp + 1.0) + (amp - 1.0) * cos - root,
+            ],
+        )
+    }
+
+    fn intermediates(freq: f32, q: f32) -> (f32, f32) {
+        let w0 = 2.0 * PI * freq / SAMPLE_RATE_RAW as f32;
+
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    fn normalised(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self::new(
+            b[0] / a[0],
+            b[1] / a[0],
+            b[2] / a[0],
+            a[1] / a[0],
+            a[2] / a[0],
+        )
+    }
+}
+
+impl Effect for Biquad {
+    fn process(&mut self, samples: &mut [f32], channels: usize) {
+        let channels = channels.max(1);
+        self.state.resize(channels, [0.0; 2]);
+
+        for frame in samples.chunks_mut(channels) {
+            for (sample, state) in frame.iter_mut().zip(self.state.iter_mut()) {
+                let x = *sample;
+                let y = self.b[0] * x + state[0];
+
+                state[0] = self.b[1] * x - self.a[0] * y + state[1];
+                state[1] = self.b[2] * x - self.a[1] * y;
+
+                *sample = y;
+            }
+        }
+    }
+}
+
+/// Passes each frame of a track's decoded audio to a user-provided function,
+/// leaving the audio itself unchanged.
+///
+/// `F` receives interleaved samples for the given number of channels, e.g.,
+/// to record a track to a file or forward it for transcription. Sinks see the
+/// output of any effects added before them, prior to the track's volume, and are
+/// not called while the track is paused.
+///
+/// As with any other effect, this prevents the track from using Opus passthrough,
+/// so tracks which could otherwise have been sent as-is must be decoded and
+/// re-encoded. Like all effects, `F` runs within the driver's mixing thread, and
+/// **must not** block: costly work should be sent elsewhere, e.g., over a channel.
+pub struct SampleSink<F>(pub F);
+
+impl<F> fmt::Debug for SampleSink<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SampleSink")
+    }
+}
+
+impl<F> Effect for SampleSink<F>
+where
+    F: FnMut(&[f32], usize) + Send,
+{
+    fn process(&mut self, samples: &mut [f32], channels: usize) {
+        (self.0)(samples, channels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Peak amplitude of a stereo sine at `freq`, after `effect`'s output has settled.
+    fn settled_peak(effect: &mut dyn Effect, freq: f32) -> f32 {
+        let mut samples: Vec<f32> = (0..SAMPLE_RATE_RAW / 10)
+            .flat_map(|i| {
+                let s = (2.0 * PI * freq * i as f32 / SAMPLE_RATE_RAW as f32).sin() * 0.25;
+                vec![s, s]
+            })
+            .collect();
+
+        effect.process(&mut samples, 2);
+
+        let tail = samples.len() / 2;
+        samples[tail..].iter().fold(0.0, |acc, s| acc.max(s.abs()))
+    }
+
+    #[test]
+    fn gain_scales_samples() {
+        let mut samples = [0.5, -0.25];
+        Gain(2.0).process(&mut samples, 2);
+
+        assert_eq!(samples, [1.0, -0.5]);
+    }
+
+    #[test]
+    fn sample_sink_observes_unchanged_samples() {
+        let mut seen = vec![];
+        let mut samples = [0.5, -0.25];
+        SampleSink(|s: &[f32], channels| seen.push((s.to_vec(), channels)))
+            .process(&mut samples, 2);
+
+        assert_eq!(samples, [0.5, -0.25]);
+        assert_eq!(seen, vec![(vec![0.5, -0.25], 2)]);
+    }
+
+    #[test]
+    fn low_pass_removes_high_frequencies() {
+        let mut filter = Biquad::low_pass(500.0, 0.707);
+
+        assert!((settled_peak(&mut filter.clone(), 50.0) - 0.25).abs() < 0.01);
+        assert!(settled_peak(&mut filter, 10_000.0) < 0.01);
+    }
+
+    #[test]
+    fn low_shelf_boosts_bass() {
+        let mut filter = Biquad::low_shelf(200.0, 6.0);
+        let boost = 10f32.powf(6.0 / 20.0);
+
+        assert!((settled_peak(&mut filter.clone(), 20.0) - 0.25 * boost).abs() < 0.02);
+        assert!((settled_peak(&mut filter, 10_000.0) - 0.25).abs() < 0.01);
+    }
+}
@@ -1,10 +1,10 @@
-use std::{error::Error, fmt};
+use std::{error::Error, fmt, sync::Arc, time::Duration};
 
 /// Errors associated with control and manipulation of tracks.
 ///
 /// Unless otherwise stated, these don't invalidate an existing track,
 /// but do advise on valid operations and commands.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 #[non_exhaustive]
 pub enum TrackError {
     /// The operation failed because the track has ended, has been removed
@@ -17,6 +17,52 @@ pub enum TrackError {
     ///
     /// [`Input`]: crate::input::Input
     SeekUnsupported,
+    /// The track is pinned within a [`TrackQueue`], and cannot be skipped or removed.
+    ///
+    /// [`TrackQueue`]: super::TrackQueue
+    Pinned,
+    /// The track could not be queued, as the target driver has no active voice connection.
+    NotConnected,
+    /// The track could not be queued, as its queue already holds a track with the same UUID.
+    DuplicateUuid,
+    /// The requested clip of a track is empty, or extends past the track's end.
+    InvalidClip,
+    /// The track is not among those a [`TrackQueue`] remembers having played.
+    ///
+    /// [`TrackQueue`]: super::TrackQueue
+    NotInHistory,
+    /// The track's source cannot be recreated, e.g., as its input had no
+    /// [`SourceDescriptor`].
+    ///
+    /// [`SourceDescriptor`]: crate::input::SourceDescriptor
+    NotReplayable,
+    /// The track could not be played, as the driver already holds its
+    /// [maximum number of tracks].
+    ///
+    /// Every [`TrackQueue`] method which adds new tracks checks for this before
+    /// changing the queue, counting queued tracks which have yet to play, as does
+    /// [`Driver::try_enqueue`].
+    ///
+    /// [maximum number of tracks]: crate::Config::max_concurrent_tracks
+    /// [`TrackQueue`]: super::TrackQueue
+    /// [`Driver::try_enqueue`]: crate::driver::Driver::try_enqueue
+    TooManyTracks,
+    /// The track could not be queued, as its requester has reached the queue's
+    /// [enqueue rate limit].
+    ///
+    /// [enqueue rate limit]: super::TrackQueue::set_enqueue_rate_limit
+    Throttled {
+        /// Time until the requester may next add a track.
+        retry_after: Duration,
+    },
+    /// The track was refused by its queue's [source filter].
+    ///
+    /// [source filter]: super::TrackQueue::set_source_filter
+    Rejected {
+        /// Why the filter refused the track, as given by the filter, e.g., naming the
+        /// offending host or URL.
+        reason: Arc<str>,
+    },
 }
 
 impl fmt::Display for TrackError {
@@ -27,6 +73,20 @@ impl fmt::Display for TrackError {
             TrackError::InvalidTrackEvent =>
                 write!(f, "given event listener can't be fired on a track"),
             TrackError::SeekUnsupported => write!(f, "track did not support seeking"),
+            TrackError::Pinned => write!(f, "track is pinned in its queue"),
+            TrackError::NotConnected => write!(f, "driver is not connected"),
+            TrackError::DuplicateUuid => write!(f, "track's UUID is already queued"),
+            TrackError::InvalidClip => write!(f, "clip range is empty or exceeds the track"),
+            TrackError::NotInHistory => write!(f, "track is not in the queue's history"),
+            TrackError::NotReplayable => write!(f, "track's source could not be recreated"),
+            TrackError::TooManyTracks => write!(f, "driver is at its track limit"),
+            TrackError::Throttled { retry_after } => write!(
+                f,
+                "requester is rate limited for {:.1}s",
+                retry_after.as_secs_f32()
+            ),
+            TrackError::Rejected { reason } =>
+                write!(f, "track was rejected by the queue's filter: {}", reason),
         }
     }
 }
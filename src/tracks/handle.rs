@@ -1,14 +1,16 @@
 use super::*;
 use crate::{
+    constants::STEREO_FRAME_SIZE,
     events::{Event, EventData, EventHandler},
-    input::Metadata,
+    input::{Input, Metadata},
 };
 use flume::Sender;
+use parking_lot::Mutex;
 use std::{fmt, sync::Arc, time::Duration};
 #[cfg(not(feature = "tokio-02-marker"))]
-use tokio::sync::RwLock;
+use tokio::{sync::RwLock, task};
 #[cfg(feature = "tokio-02-marker")]
-use tokio_compat::sync::RwLock;
+use tokio_compat::{sync::RwLock, task};
 use typemap_rev::TypeMap;
 use uuid::Uuid;
 
@@ -34,6 +36,7 @@ struct InnerHandle {
     uuid: Uuid,
     metadata: Box<Metadata>,
     typemap: RwLock<TypeMap>,
+    waveform: Mutex<Option<(usize, Vec<f32>)>>,
 }
 
 impl fmt::Debug for InnerHandle {
@@ -44,6 +47,7 @@ impl fmt::Debug for InnerHandle {
             .field("uuid", &self.uuid)
             .field("metadata", &self.metadata)
             .field("typemap", &"<LOCK>")
+            .field("waveform", &self.waveform)
             .finish()
     }
 }
@@ -65,6 +69,7 @@ impl TrackHandle {
             uuid,
             metadata,
             typemap: RwLock::new(TypeMap::new()),
+            waveform: Default::default(),
         });
 
         Self { inner }
@@ -80,6 +85,15 @@ impl TrackHandle {
         self.send(TrackCommand::Pause)
     }
 
+    /// Pauses a live audio track, so that it resumes from its latest audio.
+    ///
+    /// See [`Track::pause_live`] for details.
+    ///
+    /// [`Track::pause_live`]: Track::pause_live
+    pub fn pause_live(&self) -> TrackResult<()> {
+        self.send(TrackCommand::PauseLive)
+    }
+
     /// Stops an audio track.
     ///
     /// This is *final*, and will cause the audio context to fire
@@ -90,11 +104,75 @@ impl TrackHandle {
         self.send(TrackCommand::Stop)
     }
 
+    /// Stops an audio track once it has played all audio already buffered by its
+    /// input, rather than cutting it off immediately.
+    ///
+    /// See [`Track::stop_after_buffer`] for more details.
+    ///
+    /// [`Track::stop_after_buffer`]: Track::stop_after_buffer
+    pub fn stop_after_buffer(&self) -> TrackResult<()> {
+        self.send(TrackCommand::StopAfterBuffer)
+    }
+
     /// Sets the volume of an audio track.
     pub fn set_volume(&self, volume: f32) -> TrackResult<()> {
         self.send(TrackCommand::Volume(volume))
     }
 
+    /// Sets the volume of an audio track as a percentage, where `100` is unity gain.
+    ///
+    /// This uses [`VolumeCurve::Linear`], and values above `200` are treated as `200`.
+    /// For finer control at low volumes, [`set_volume_percent_with`] accepts other
+    /// curves such as [`VolumeCurve::Perceptual`].
+    ///
+    /// [`VolumeCurve::Linear`]: VolumeCurve::Linear
+    /// [`VolumeCurve::Perceptual`]: VolumeCurve::Perceptual
+    /// [`set_volume_percent_with`]: TrackHandle::set_volume_percent_with
+    pub fn set_volume_percent(&self, percent: u8) -> TrackResult<()> {
+        self.set_volume_percent_with(VolumeCurve::Linear, percent)
+    }
+
+    /// Sets the volume of an audio track as a percentage on the given `curve`, where
+    /// `100` is unity gain.
+    ///
+    /// Values above `200` are treated as `200`.
+    pub fn set_volume_percent_with(&self, curve: VolumeCurve, percent: u8) -> TrackResult<()> {
+        self.set_volume(curve.gain(percent))
+    }
+
+    /// Adds an audio processing stage, to run after all of this track's existing effects.
+    ///
+    /// See [`Track::add_effect`] for more details.
+    ///
+    /// [`Track::add_effect`]: Track::add_effect
+    pub fn add_effect(&self, effect: Box<dyn Effect>) -> TrackResult<()> {
+        self.send(TrackCommand::AddEffect(effect))
+    }
+
+    /// Passes a copy of each frame of this track's decoded audio to `sink`, after all of
+    /// its existing effects.
+    ///
+    /// `sink` receives interleaved samples and the number of channels. This forces the
+    /// track to be decoded, even if it could otherwise use Opus passthrough. See
+    /// [`SampleSink`] for more details.
+    ///
+    /// [`SampleSink`]: effects::SampleSink
+    pub fn add_sample_sink<F>(&self, sink: F) -> TrackResult<()>
+    where
+        F: FnMut(&[f32], usize) + Send + 'static,
+    {
+        self.add_effect(Box::new(effects::SampleSink(sink)))
+    }
+
+    /// Sets whether this track lowers the volume of all other tracks while it plays.
+    ///
+    /// See [`Track::set_ducking`] for more details.
+    ///
+    /// [`Track::set_ducking`]: Track::set_ducking
+    pub fn set_ducking(&self, ducking: bool, attenuation: f32) -> TrackResult<()> {
+        self.send(TrackCommand::Ducking(ducking, attenuation))
+    }
+
     /// Ready a track for playing if it is lazily initialised.
     ///
     /// Currently, only [`Restartable`] sources support lazy setup.
@@ -131,6 +209,61 @@ impl TrackHandle {
         }
     }
 
+    /// Seeks forwards (or backwards, if negative) by `delta_ms` milliseconds from the
+    /// track's current position.
+    ///
+    /// The new position is computed from the track's position at the time the seek is
+    /// carried out, rather than when this is called, and is clamped to the start of the
+    /// track and (if known) its duration.
+    ///
+    /// If the underlying [`Input`] does not support seeking,
+    /// then all calls will fail with [`TrackError::SeekUnsupported`].
+    ///
+    /// [`Input`]: crate::input::Input
+    /// [`TrackError::SeekUnsupported`]: TrackError::SeekUnsupported
+    pub fn seek_relative(&self, delta_ms: i64) -> TrackResult<()> {
+        if !self.is_seekable() {
+            return Err(TrackError::SeekUnsupported);
+        }
+
+        self.action(move |track| {
+            let delta = Duration::from_millis(delta_ms.unsigned_abs());
+            let mut target = if delta_ms < 0 {
+                track.position.checked_sub(delta).unwrap_or_default()
+            } else {
+                track.position + delta
+            };
+
+            if let Some(duration) = track.source.metadata.duration {
+                target = target.min(duration);
+            }
+
+            let _ = track.seek_time(target);
+        })
+    }
+
+    /// Seeks along the track to the specified position, waiting until the seek
+    /// has been carried out.
+    ///
+    /// On success, this returns the position the track actually reached, which
+    /// may differ from `position` for inputs which can only seek coarsely.
+    ///
+    /// If the underlying [`Input`] does not support seeking,
+    /// then all calls will fail with [`TrackError::SeekUnsupported`].
+    ///
+    /// [`Input`]: crate::input::Input
+    /// [`TrackError::SeekUnsupported`]: TrackError::SeekUnsupported
+    pub async fn seek_async(&self, position: Duration) -> TrackResult<Duration> {
+        if !self.is_seekable() {
+            return Err(TrackError::SeekUnsupported);
+        }
+
+        let (tx, rx) = flume::bounded(1);
+        self.send(TrackCommand::SeekWithResult(position, tx))?;
+
+        rx.recv_async().await.map_err(|_| TrackError::Finished)?
+    }
+
     /// Attach an event handler to an audio track. These will receive [`EventContext::Track`].
     ///
     /// Events which can only be fired by the global context return [`TrackError::InvalidTrackEvent`]
@@ -169,6 +302,91 @@ impl TrackHandle {
         rx.recv_async().await.map_err(|_| TrackError::Finished)
     }
 
+    /// Request running totals of the audio this track has produced.
+    ///
+    /// See [`PlayStats`] for more details.
+    ///
+    /// [`PlayStats`]: PlayStats
+    pub async fn play_stats(&self) -> TrackResult<PlayStats> {
+        let (tx, rx) = flume::bounded(1);
+        self.send(TrackCommand::RequestStats(tx))?;
+
+        rx.recv_async().await.map_err(|_| TrackError::Finished)
+    }
+
+    /// Request the number of Opus frames which could not be decoded, and were
+    /// played as silence.
+    ///
+    /// See [`Track::decode_error_count`] for more details.
+    ///
+    /// [`Track::decode_error_count`]: Track::decode_error_count
+    pub async fn decode_error_count(&self) -> TrackResult<u32> {
+        let (tx, rx) = flume::bounded(1);
+        self.send(TrackCommand::RequestDecodeErrors(tx))?;
+
+        rx.recv_async().await.map_err(|_| TrackError::Finished)
+    }
+
+    /// Resets the count given by [`decode_error_count`] to zero, e.g., once a
+    /// burst of errors has been reported.
+    ///
+    /// [`decode_error_count`]: TrackHandle::decode_error_count
+    pub fn reset_decode_errors(&self) -> TrackResult<()> {
+        self.send(TrackCommand::ResetDecodeErrors)
+    }
+
+    /// Request the sizes of the Opus packets this track has sent via passthrough.
+    ///
+    /// See [`PacketSizeStats`] for more details.
+    ///
+    /// [`PacketSizeStats`]: PacketSizeStats
+    pub async fn packet_size_stats(&self) -> TrackResult<PacketSizeStats> {
+        let (tx, rx) = flume::bounded(1);
+        self.send(TrackCommand::RequestPacketSizes(tx))?;
+
+        rx.recv_async().await.map_err(|_| TrackError::Finished)
+    }
+
+    /// Computes the peak amplitude of this track's audio within each of
+    /// `buckets` equally-sized spans, e.g., to draw a waveform.
+    ///
+    /// The whole track is decoded on a blocking thread, separately from
+    /// playback, so this can be expensive for long tracks. Results are cached
+    /// on the handle, and later calls with the same `buckets` return immediately.
+    ///
+    /// This is only possible for tracks whose [`Input`] is backed by a
+    /// [`Memory`] or [`Compressed`] cache, and fails with [`TrackError::SeekUnsupported`]
+    /// otherwise.
+    ///
+    /// [`Input`]: crate::input::Input
+    /// [`Memory`]: crate::input::cached::Memory
+    /// [`Compressed`]: crate::input::cached::Compressed
+    /// [`TrackError::SeekUnsupported`]: TrackError::SeekUnsupported
+    pub async fn compute_waveform(&self, buckets: usize) -> TrackResult<Vec<f32>> {
+        if let Some((cached_buckets, peaks)) = &*self.inner.waveform.lock() {
+            if *cached_buckets == buckets {
+                return Ok(peaks.clone());
+            }
+        }
+
+        let (tx, rx) = flume::bounded(1);
+        self.send(TrackCommand::NewView(tx))?;
+
+        let view = rx
+            .recv_async()
+            .await
+            .map_err(|_| TrackError::Finished)?
+            .ok_or(TrackError::SeekUnsupported)?;
+
+        let peaks = task::spawn_blocking(move || compute_peaks(view, buckets))
+            .await
+            .map_err(|_| TrackError::Finished)?;
+
+        *self.inner.waveform.lock() = Some((buckets, peaks.clone()));
+
+        Ok(peaks)
+    }
+
     /// Set an audio track to loop indefinitely.
     ///
     /// If the underlying [`Input`] does not support seeking,
@@ -241,6 +459,14 @@ impl TrackHandle {
         &self.inner.typemap
     }
 
+    /// Returns whether the underlying [`Track`] has been discarded, e.g., because it
+    /// ended or hit an error.
+    ///
+    /// [`Track`]: Track
+    pub(crate) fn is_finished(&self) -> bool {
+        self.inner.command_channel.is_disconnected()
+    }
+
     #[inline]
     /// Send a raw command to the [`Track`] object.
     ///
@@ -254,3 +480,34 @@ impl TrackHandle {
             .map_err(|_e| TrackError::Finished)
     }
 }
+
+fn compute_peaks(mut input: Input, buckets: usize) -> Vec<f32> {
+    let mut frame_peaks = vec![];
+    let mut buffer = [0f32; STEREO_FRAME_SIZE];
+
+    loop {
+        buffer.iter_mut().for_each(|x| *x = 0.0);
+
+        if input.mix(&mut buffer, 1.0) == 0 {
+            break;
+        }
+
+        frame_peaks.push(buffer.iter().fold(0f32, |acc, x| acc.max(x.abs())));
+    }
+
+    let n = frame_peaks.len();
+    if n == 0 {
+        return vec![0.0; buckets];
+    }
+
+    (0..buckets)
+        .map(|b| {
+            let start = (b * n / buckets).min(n - 1);
+            let end = ((b + 1) * n / buckets).max(start + 1).min(n);
+
+            frame_peaks[start..end]
+                .iter()
+                .fold(0f32, |acc, x| acc.max(*x))
+        })
+        .collect()
+}
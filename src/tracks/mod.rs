@@ -14,19 +14,35 @@
 //! [`TrackHandle`]: struct.TrackHandle.html
 //! [`create_player`]: fn.create_player.html
 
+mod backend;
 mod command;
+pub mod effects;
 mod error;
 mod handle;
 mod looping;
 mod mode;
+mod passthrough;
 mod queue;
 mod state;
-
-pub use self::{command::*, error::*, handle::*, looping::*, mode::*, queue::*, state::*};
+mod volume;
+
+pub use self::{
+    backend::*,
+    command::*,
+    error::*,
+    handle::*,
+    looping::*,
+    mode::*,
+    passthrough::*,
+    queue::*,
+    state::*,
+    volume::*,
+};
 
 use crate::{constants::*, driver::tasks::message::*, events::EventStore, input::Input};
+use effects::Effect;
 use flume::{Receiver, TryRecvError};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 /// Control object for audio playback.
@@ -80,6 +96,60 @@ pub struct Track {
     /// [`volume`]: Track::volume
     pub(crate) volume: f32,
 
+    /// Gain applied to all other tracks while this track is playing, if it
+    /// is a ducking source.
+    ///
+    /// Can be controlled with [`set_ducking`] if chaining is desired.
+    ///
+    /// [`set_ducking`]: Track::set_ducking
+    pub(crate) ducking: Option<f32>,
+
+    /// How the mixer handled this track on its last cycle, if it has played.
+    pub(crate) passthrough: Option<PassthroughDecision>,
+
+    /// Whether the mixer has passed through this track's frames, if it has played.
+    pub(crate) passthrough_usage: Option<PassthroughUsage>,
+
+    /// Whether this track should stop once its buffered audio has been played.
+    ///
+    /// Can be controlled with [`stop_after_buffer`] if chaining is desired.
+    ///
+    /// [`stop_after_buffer`]: Track::stop_after_buffer
+    pub(crate) stopping: bool,
+
+    /// Whether this track is paused, but still reading (and discarding) its live input.
+    ///
+    /// Can be controlled with [`pause_live`] if chaining is desired.
+    ///
+    /// [`pause_live`]: Track::pause_live
+    pub(crate) draining: bool,
+
+    /// Whether the mixer has reported that this track's input is ready to play.
+    pub(crate) ready: bool,
+
+    /// Whether this track's input has failed while being mixed.
+    pub(crate) errored: bool,
+
+    /// Totals of all audio produced by this track so far.
+    pub(crate) stats: PlayStats,
+
+    /// Number of Opus frames which failed to decode since this was last reset.
+    pub(crate) decode_errors: u32,
+
+    /// Sizes of all Opus packets sent as-is from this track.
+    pub(crate) packet_sizes: PacketSizeStats,
+
+    /// When the mixer produced this track's first and most recent frames of audio.
+    pub(crate) first_frame_at: Option<Instant>,
+    pub(crate) last_frame_at: Option<Instant>,
+
+    /// Audio processing stages applied to this track's decoded audio, in order.
+    ///
+    /// Can be extended with [`add_effect`] if chaining is desired.
+    ///
+    /// [`add_effect`]: Track::add_effect
+    pub(crate) effects: Vec<Box<dyn Effect>>,
+
     /// Underlying data access object.
     ///
     /// *Calling code is not expected to use this.*
@@ -115,6 +185,13 @@ pub struct Track {
 
     /// Unique identifier for this track.
     pub(crate) uuid: Uuid,
+
+    /// ID of whoever requested this track, if set.
+    ///
+    /// See [`set_requester`] for more details.
+    ///
+    /// [`set_requester`]: Track::set_requester
+    pub(crate) requester: Option<u64>,
 }
 
 impl Track {
@@ -130,6 +207,19 @@ impl Track {
         Self {
             playing: Default::default(),
             volume: 1.0,
+            ducking: None,
+            passthrough: None,
+            passthrough_usage: None,
+            stopping: false,
+            draining: false,
+            ready: false,
+            errored: false,
+            effects: Vec::new(),
+            stats: Default::default(),
+            decode_errors: 0,
+            packet_sizes: Default::default(),
+            first_frame_at: None,
+            last_frame_at: None,
             source,
             position: Default::default(),
             play_time: Default::default(),
@@ -138,6 +228,7 @@ impl Track {
             handle,
             loops: LoopState::Finite(0),
             uuid,
+            requester: None,
         }
     }
 
@@ -151,6 +242,21 @@ impl Track {
         self.set_playing(PlayMode::Pause)
     }
 
+    /// Pauses a live track, while continuing to read from its input so that it
+    /// resumes from the latest available audio, rather than stale buffered audio.
+    ///
+    /// While paused in this way, the mixer reads and discards one frame of audio per
+    /// 20ms, to keep pace with the source: the track's position does not advance.
+    /// Inputs which can seek (e.g., files, or cached sources) have no live edge to catch
+    /// up to, and are paused as normal. Playing the track (by any means) resumes it
+    /// from wherever its input has reached.
+    pub fn pause_live(&mut self) -> &mut Self {
+        self.pause();
+        self.draining = self.playing == PlayMode::Pause && !self.source.is_seekable();
+
+        self
+    }
+
     /// Manually stops a track.
     ///
     /// This will cause the audio track to be removed, with any relevant events triggered.
@@ -159,6 +265,26 @@ impl Track {
         self.set_playing(PlayMode::Stop)
     }
 
+    /// Stops a track once it has played all audio which has already been read from,
+    /// or decoded by, its [`Input`].
+    ///
+    /// Unlike [`stop`], this lets a track finish the remainder of (e.g.) a partially played
+    /// Opus frame or a pipe's read buffer, rather than discarding it. The track stops
+    /// immediately if nothing is buffered, or it is paused. This is not a fade-out:
+    /// the audio which is played is unaltered.
+    ///
+    /// [`Input`]: Input
+    /// [`stop`]: Track::stop
+    pub fn stop_after_buffer(&mut self) -> &mut Self {
+        self.stopping = true;
+
+        if self.playing != PlayMode::Play || !self.source.has_buffered_audio() {
+            self.stop();
+        }
+
+        self
+    }
+
     pub(crate) fn end(&mut self) -> &mut Self {
         self.set_playing(PlayMode::End)
     }
@@ -166,6 +292,7 @@ impl Track {
     #[inline]
     fn set_playing(&mut self, new_state: PlayMode) -> &mut Self {
         self.playing = self.playing.change_to(new_state);
+        self.draining = false;
 
         self
     }
@@ -184,11 +311,53 @@ impl Track {
         self
     }
 
+    /// Adds an audio processing stage, to run after all of this track's existing effects.
+    ///
+    /// A track with any effects will always be decoded, and cannot use Opus passthrough.
+    /// See [`Effect`] for more details.
+    ///
+    /// [`Effect`]: Effect
+    pub fn add_effect(&mut self, effect: Box<dyn Effect>) -> &mut Self {
+        self.effects.push(effect);
+
+        self
+    }
+
     /// Returns the current playback position.
     pub fn volume(&self) -> f32 {
         self.volume
     }
 
+    /// Sets whether this track lowers the volume of all other tracks while it plays.
+    ///
+    /// While a ducking track is playing, every non-ducking track in the same driver
+    /// has its volume multiplied by `attenuation`: e.g., `0.25` plays music at a quarter
+    /// of its set volume beneath a spoken announcement. Other tracks return to their set
+    /// volume as soon as no ducking tracks are playing. If several ducking tracks play
+    /// at once, the strongest attenuation applies.
+    ///
+    /// `attenuation` is clamped to lie between `0.0` and `1.0`, and is ignored if
+    /// `ducking` is `false`.
+    pub fn set_ducking(&mut self, ducking: bool, attenuation: f32) -> &mut Self {
+        self.ducking = if ducking {
+            Some(attenuation.clamp(0.0, 1.0))
+        } else {
+            None
+        };
+
+        self
+    }
+
+    /// Returns the gain this track applies to all others while playing, if it is a
+    /// ducking source.
+    ///
+    /// See [`set_ducking`] for more details.
+    ///
+    /// [`set_ducking`]: Track::set_ducking
+    pub fn ducking(&self) -> Option<f32> {
+        self.ducking
+    }
+
     /// Returns the current playback position.
     pub fn position(&self) -> Duration {
         self.position
@@ -262,6 +431,13 @@ impl Track {
                                 TrackStateChange::Mode(self.playing),
                             ));
                         },
+                        PauseLive => {
+                            self.pause_live();
+                            let _ = ic.events.send(EventMessage::ChangeState(
+                                index,
+                                TrackStateChange::Mode(self.playing),
+                            ));
+                        },
                         Stop => {
                             self.stop();
                             let _ = ic.events.send(EventMessage::ChangeState(
@@ -269,6 +445,13 @@ impl Track {
                                 TrackStateChange::Mode(self.playing),
                             ));
                         },
+                        StopAfterBuffer => {
+                            self.stop_after_buffer();
+                            let _ = ic.events.send(EventMessage::ChangeState(
+                                index,
+                                TrackStateChange::Mode(self.playing),
+                            ));
+                        },
                         Volume(vol) => {
                             self.set_volume(vol);
                             let _ = ic.events.send(EventMessage::ChangeState(
@@ -276,13 +459,18 @@ impl Track {
                                 TrackStateChange::Volume(self.volume),
                             ));
                         },
-                        Seek(time) =>
-                            if let Ok(new_time) = self.seek_time(time) {
-                                let _ = ic.events.send(EventMessage::ChangeState(
-                                    index,
-                                    TrackStateChange::Position(new_time),
-                                ));
-                            },
+                        Seek(time) => {
+                            let _ = self.seek_and_notify(time, index, ic);
+                        },
+                        SeekWithResult(time, tx) => {
+                            let _ = tx.send(self.seek_and_notify(time, index, ic));
+                        },
+                        AddEffect(effect) => {
+                            self.add_effect(effect);
+                        },
+                        Ducking(ducking, attenuation) => {
+                            self.set_ducking(ducking, attenuation);
+                        },
                         AddEvent(evt) => {
                             let _ = ic.events.send(EventMessage::AddTrackEvent(index, evt));
                         },
@@ -296,6 +484,21 @@ impl Track {
                         Request(tx) => {
                             let _ = tx.send(self.state());
                         },
+                        RequestStats(tx) => {
+                            let _ = tx.send(self.stats);
+                        },
+                        RequestDecodeErrors(tx) => {
+                            let _ = tx.send(self.decode_errors);
+                        },
+                        ResetDecodeErrors => {
+                            self.decode_errors = 0;
+                        },
+                        RequestPacketSizes(tx) => {
+                            let _ = tx.send(self.packet_sizes);
+                        },
+                        NewView(tx) => {
+                            let _ = tx.send(self.source.new_view());
+                        },
                         Loop(loops) =>
                             if self.set_loops(loops).is_ok() {
                                 let _ = ic.events.send(EventMessage::ChangeState(
@@ -340,9 +543,69 @@ impl Track {
             position: self.position,
             play_time: self.play_time,
             loops: self.loops,
+            passthrough: self.passthrough,
+            passthrough_usage: self.passthrough_usage,
+            first_frame_at: self.first_frame_at,
+            last_frame_at: self.last_frame_at,
         }
     }
 
+    /// Returns running totals of the audio this track has produced.
+    ///
+    /// See [`PlayStats`] for more details.
+    ///
+    /// [`PlayStats`]: PlayStats
+    pub fn play_stats(&self) -> PlayStats {
+        self.stats
+    }
+
+    /// Returns the number of Opus frames which could not be decoded, and were
+    /// played as silence, since this track began or [`reset_decode_errors`] was
+    /// last called.
+    ///
+    /// Frequent errors suggest that a source is corrupt, or its connection unreliable.
+    /// Frames sent via passthrough are not decoded, so are never counted.
+    ///
+    /// [`reset_decode_errors`]: Track::reset_decode_errors
+    pub fn decode_error_count(&self) -> u32 {
+        self.decode_errors
+    }
+
+    /// Resets the count given by [`decode_error_count`] to zero.
+    ///
+    /// [`decode_error_count`]: Track::decode_error_count
+    pub fn reset_decode_errors(&mut self) -> &mut Self {
+        self.decode_errors = 0;
+
+        self
+    }
+
+    /// Returns the sizes of the Opus packets this track has sent via passthrough.
+    ///
+    /// See [`PacketSizeStats`] for more details.
+    ///
+    /// [`PacketSizeStats`]: PacketSizeStats
+    pub fn packet_size_stats(&self) -> PacketSizeStats {
+        self.packet_sizes
+    }
+
+    /// Seeks as requested by a [`TrackHandle`], informing the event thread of the new position.
+    fn seek_and_notify(
+        &mut self,
+        time: Duration,
+        index: usize,
+        ic: &Interconnect,
+    ) -> TrackResult<Duration> {
+        let new_time = self.seek_time(time)?;
+
+        let _ = ic.events.send(EventMessage::ChangeState(
+            index,
+            TrackStateChange::Position(new_time),
+        ));
+
+        Ok(new_time)
+    }
+
     /// Seek to a specific point in the track.
     ///
     /// If the underlying [`Input`] does not support seeking,
@@ -351,7 +614,10 @@ impl Track {
     /// [`Input`]: crate::input::Input
     /// [`TrackError::SeekUnsupported`]: TrackError::SeekUnsupported
     pub fn seek_time(&mut self, pos: Duration) -> TrackResult<Duration> {
-        if let Some(t) = self.source.seek_time(pos) {
+        let out = self.source.seek_time(pos);
+        self.stats.frames_primed += self.source.take_frames_primed();
+
+        if let Some(t) = out {
             self.position = t;
             Ok(t)
         } else {
@@ -363,6 +629,29 @@ impl Track {
     pub fn uuid(&self) -> Uuid {
         self.uuid
     }
+
+    /// Sets the ID of whoever requested this track (e.g., a Discord user ID).
+    ///
+    /// When added to a [`TrackQueue`], this becomes the entry's [requester], and counts
+    /// towards the queue's [enqueue rate limit].
+    ///
+    /// [`TrackQueue`]: TrackQueue
+    /// [requester]: Queued::set_requester
+    /// [enqueue rate limit]: TrackQueue::set_enqueue_rate_limit
+    pub fn set_requester(&mut self, requester: Option<u64>) -> &mut Self {
+        self.requester = requester;
+
+        self
+    }
+
+    /// Returns the ID of whoever requested this track, if set.
+    ///
+    /// See [`set_requester`] for more details.
+    ///
+    /// [`set_requester`]: Track::set_requester
+    pub fn requester(&self) -> Option<u64> {
+        self.requester
+    }
 }
 
 /// Creates a [`Track`] object to pass into the audio context, and a [`TrackHandle`]
@@ -378,10 +667,38 @@ pub fn create_player(source: Input) -> (Track, TrackHandle) {
     create_player_with_uuid(source, Uuid::new_v4())
 }
 
+/// Creates a paused [`Track`] and its [`TrackHandle`] as in [`create_player`],
+/// which will begin from `position` once played.
+///
+/// The seek is queued on the handle before the track exists in any driver, so
+/// it is always carried out before the track produces any audio. If `source` is
+/// not seekable, then the track instead plays from its current position.
+///
+/// [`Track`]: Track
+/// [`TrackHandle`]: TrackHandle
+/// [`create_player`]: create_player
+pub fn create_player_paused_at(source: Input, position: Duration) -> (Track, TrackHandle) {
+    let (mut track, handle) = create_player(source);
+
+    track.pause();
+    // Only fails for unseekable sources: the track holds the command receiver.
+    let _ = handle.seek_time(position);
+
+    (track, handle)
+}
+
 /// Creates a [`Track`] and [`TrackHandle`] as in [`create_player`], allowing
 /// a custom UUID to be set.
 ///
+/// This allows tracks to be identified using IDs from an external system, e.g.
+/// via [`TrackQueue::position_of`]. UUIDs **must** be unique among the tracks of
+/// any one [`TrackQueue`], which matches ended tracks to its entries by their UUID:
+/// queues refuse duplicate tracks with [`TrackError::DuplicateUuid`].
+///
 /// [`create_player`]: create_player
+/// [`TrackQueue::position_of`]: TrackQueue::position_of
+/// [`TrackQueue`]: TrackQueue
+/// [`TrackError::DuplicateUuid`]: TrackError::DuplicateUuid
 /// [`Track`]: Track
 /// [`TrackHandle`]: TrackHandle
 pub fn create_player_with_uuid(source: Input, uuid: Uuid) -> (Track, TrackHandle) {
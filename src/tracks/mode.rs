@@ -1,7 +1,10 @@
 use crate::events::TrackEvent;
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
 /// Playback status of a track.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 #[non_exhaustive]
 pub enum PlayMode {
     /// The track is currently playing.
@@ -0,0 +1,102 @@
+/// Why the mixer did (or did not) forward a track's Opus frames without re-encoding.
+///
+/// This is recorded for each playing track on every mixer cycle, and is exposed via
+/// [`TrackState::passthrough`] for debugging unexpected CPU use.
+///
+/// [`TrackState::passthrough`]: super::TrackState::passthrough
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PassthroughDecision {
+    /// The track's Opus frames were sent as-is.
+    Passthrough,
+    /// The track was decoded because its volume is not `1.0`.
+    DecodedBecauseVolume,
+    /// The track was decoded because its source is not Opus audio
+    /// in Discord's output format, and must be mixed as PCM.
+    DecodedBecauseResample,
+    /// The track was decoded because it has [effects] attached.
+    ///
+    /// [effects]: super::Track::add_effect
+    DecodedBecauseEffect,
+    /// The track was decoded because more than one track is present in the
+    /// driver, so its audio must be mixed with others.
+    DecodedBecauseMix,
+    /// The track was decoded because the driver's [frame length] is shorter than
+    /// each Opus frame, so its audio must be split.
+    ///
+    /// [frame length]: crate::Config::frame_length
+    DecodedBecauseFrameLength,
+}
+
+impl PassthroughDecision {
+    /// Returns whether the track's Opus frames were sent as-is.
+    pub fn is_passthrough(self) -> bool {
+        self == PassthroughDecision::Passthrough
+    }
+}
+
+/// Whether a track's audio has been sent via Opus passthrough, over all of
+/// the frames it has played.
+///
+/// This is exposed via [`TrackState::passthrough_usage`], including in the states
+/// given to event handlers, e.g. to estimate the CPU cost of a playlist: decoded
+/// tracks must also be re-encoded.
+///
+/// [`TrackState::passthrough_usage`]: super::TrackState::passthrough_usage
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PassthroughUsage {
+    /// Every frame was sent as-is.
+    Always,
+    /// Every frame was decoded.
+    Never,
+    /// Some frames were sent as-is, and others were decoded.
+    Mixed,
+}
+
+impl PassthroughUsage {
+    /// Updates `usage` to account for one more frame.
+    pub(crate) fn record(usage: Option<Self>, passthrough: bool) -> Self {
+        use PassthroughUsage::*;
+        match (usage, passthrough) {
+            (None, true) | (Some(Always), true) => Always,
+            (None, false) | (Some(Never), false) => Never,
+            _ => Mixed,
+        }
+    }
+}
+
+/// Sizes of the Opus packets forwarded from a track via passthrough, retrieved
+/// remotely via [`TrackHandle::packet_size_stats`].
+///
+/// Decoded frames are re-encoded at the driver's bitrate, so are not counted:
+/// a track which has never used passthrough has empty stats. For a VBR source,
+/// these show how much its bandwidth varies.
+///
+/// [`TrackHandle::packet_size_stats`]: super::TrackHandle::packet_size_stats
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct PacketSizeStats {
+    /// Number of packets forwarded.
+    pub count: u64,
+    /// Smallest packet forwarded, in bytes.
+    pub min: usize,
+    /// Largest packet forwarded, in bytes.
+    pub max: usize,
+    /// Mean packet size, in bytes.
+    pub mean: f64,
+}
+
+impl PacketSizeStats {
+    /// Accounts for one more forwarded packet of `len` bytes.
+    pub(crate) fn record(&mut self, len: usize) {
+        self.min = if self.count == 0 {
+            len
+        } else {
+            self.min.min(len)
+        };
+        self.max = self.max.max(len);
+        self.count += 1;
+        self.mean += (len as f64 - self.mean) / self.count as f64;
+    }
+}
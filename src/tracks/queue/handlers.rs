@@ -0,0 +1,442 @@
+//! Event handlers through which a [`TrackQueue`] follows its tracks' progress.
+//!
+//! [`TrackQueue`]: super::TrackQueue
+
+use super::{OnComplete, QueueUpdate, RetainedSource, TrackQueue, TrackQueueCore};
+use crate::{
+    constants::TIMESTEP_LENGTH,
+    driver::TrackSink,
+    events::{Event, EventContext, EventHandler},
+    input::Metadata,
+    tracks,
+};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+#[cfg(not(feature = "tokio-02-marker"))]
+use tokio::runtime::Handle;
+#[cfg(feature = "tokio-02-marker")]
+use tokio_compat::runtime::Handle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub(super) struct QueueHandler {
+    pub(super) remote_lock: Arc<Mutex<TrackQueueCore>>,
+}
+
+/// Work left for a [`QueueHandler`] once it has emptied the queue, and released its lock.
+///
+/// [`QueueHandler`]: QueueHandler
+pub(super) enum Completion {
+    Callback(Arc<dyn EventHandler>),
+    Loop(TrackSink, Vec<(Metadata, RetainedSource, Option<u64>)>),
+}
+
+#[async_trait]
+impl EventHandler for QueueHandler {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        // The queue lock must be released before any user callback can run,
+        // as it may well want to add new tracks.
+        match self.advance(ctx) {
+            Some(Completion::Callback(callback)) => {
+                let _ = callback.act(ctx).await;
+            },
+            Some(Completion::Loop(driver, finished)) => self.requeue(&driver, finished),
+            None => {},
+        }
+
+        None
+    }
+}
+
+impl QueueHandler {
+    /// Moves on from the ended head of the queue, returning any work to do if
+    /// this has emptied the queue.
+    pub(super) fn advance(&self, ctx: &EventContext<'_>) -> Option<Completion> {
+        let mut inner = self.remote_lock.lock();
+
+        // Due to possibility that users might remove, reorder,
+        // or dequeue+stop tracks, we need to verify that the FIRST
+        // track is the one who has ended.
+        let (ended_at, played) = match ctx {
+            EventContext::Track(ts) => {
+                // This slice should have exactly one entry.
+                // If the ended track has same id as the queue head, then
+                // we can progress the queue.
+                let (state, handle) = ts.first()?;
+                if inner.tracks.front()?.uuid() != handle.uuid() {
+                    return None;
+                }
+
+                // Hold off until all edits are finished.
+                if inner.editors > 0 {
+                    inner.deferred_end = Some((**state, (*handle).clone()));
+                    return None;
+                }
+
+                // Tracks which never made a sound are timed from when they ended.
+                let ended_at = state.last_frame_at.unwrap_or_else(Instant::now);
+                (ended_at, state.first_frame_at.is_some())
+            },
+            _ => return None,
+        };
+
+        let old = inner.tracks.pop_front();
+        inner.preloading_for = None;
+
+        if let Some(backend) = inner.backend.as_mut() {
+            backend.pop();
+        }
+
+        if let Some(old) = &old {
+            inner.take_fair_turn(old.requester);
+            inner.remember(old);
+
+            if matches!(inner.on_complete, OnComplete::LoopQueue) && played {
+                match &old.source {
+                    Some(source) => {
+                        let meta = old.refreshed.clone();
+                        let meta = meta.unwrap_or_else(|| old.metadata().clone());
+                        inner.finished.push((meta, source.clone(), old.requester));
+                    },
+                    None => warn!(
+                        label = ?inner.label,
+                        "Queued track {} cannot be looped: its source cannot be recreated.",
+                        old.uuid()
+                    ),
+                }
+            }
+        }
+
+        info!(label = ?inner.label, "Queued track ended: {:?}.", ctx);
+        info!(label = ?inner.label, "{} tracks remain.", inner.tracks.len());
+
+        inner.start_playable_head(&self.remote_lock);
+
+        if let Some(head) = inner.tracks.front() {
+            inner.last_transition_gapless = Some(head.ready);
+        }
+
+        let new_head = inner.tracks.front().map(|q| q.uuid());
+        inner.last_end = match (&old, new_head) {
+            (Some(old), Some(_)) => Some((old.uuid(), ended_at)),
+            _ => None,
+        };
+        inner.notify(QueueUpdate::Advanced { new_head });
+        inner.notify_head_change(old.map(|q| q.uuid()));
+
+        if !inner.tracks.is_empty() {
+            return None;
+        }
+
+        match &inner.on_complete {
+            OnComplete::Callback(callback) => Some(Completion::Callback(callback.clone())),
+            OnComplete::LoopQueue if !inner.finished.is_empty() => match inner.driver.clone() {
+                Some(driver) => {
+                    let finished = std::mem::take(&mut inner.finished);
+                    Some(Completion::Loop(driver, finished))
+                },
+                None => {
+                    warn!(label = ?inner.label, "No driver to loop the queue through.");
+                    None
+                },
+            },
+            _ => None,
+        }
+    }
+
+    /// Recreates and re-enqueues `finished` tracks for [`OnComplete::LoopQueue`].
+    ///
+    /// [`OnComplete::LoopQueue`]: OnComplete::LoopQueue
+    pub(super) fn requeue(
+        &self,
+        driver: &TrackSink,
+        finished: Vec<(Metadata, RetainedSource, Option<u64>)>,
+    ) {
+        let queue = TrackQueue {
+            inner: self.remote_lock.clone(),
+        };
+
+        for (meta, source, requester) in finished {
+            let source = match source.recreate(meta) {
+                Some(source) => source,
+                None => {
+                    warn!("Queued track could not be looped: its decoder could not be created.");
+                    continue;
+                },
+            };
+
+            let (mut track, handle) = tracks::create_player(source);
+            track.set_requester(requester);
+            queue.add_raw(&mut track);
+
+            if let Err(e) = driver.play(track) {
+                warn!("Queued track {} could not be looped: {}.", handle.uuid(), e);
+                queue.modify_queue(|vq| vq.retain(|q| q.uuid() != handle.uuid()));
+            }
+        }
+    }
+}
+
+/// Skips the head of the queue if its track is dropped without ending, e.g., by
+/// being discarded before it reaches a driver, when [failed heads are skipped].
+///
+/// A track's event handlers are dropped alongside it, so this needs no polling.
+///
+/// [failed heads are skipped]: TrackQueue::set_skip_failed_head
+pub(super) struct DiscardNotifier {
+    pub(super) remote_lock: Arc<Mutex<TrackQueueCore>>,
+    pub(super) uuid: Uuid,
+    pub(super) ended: AtomicBool,
+}
+
+#[async_trait]
+impl EventHandler for DiscardNotifier {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        // Ended tracks are moved on from by the `QueueHandler`.
+        self.ended.store(true, Ordering::Release);
+
+        None
+    }
+}
+
+impl Drop for DiscardNotifier {
+    fn drop(&mut self) {
+        if self.ended.load(Ordering::Acquire) {
+            return;
+        }
+
+        // The track's command channel closes only after its events are dropped, and
+        // whoever dropped the track may hold the queue's lock: check once both are done.
+        let handle = match Handle::try_current() {
+            Ok(handle) => handle,
+            Err(_) => return,
+        };
+
+        let remote_lock = self.remote_lock.clone();
+        let uuid = self.uuid;
+        handle.spawn(async move {
+            let mut inner = remote_lock.lock();
+            let is_head = inner.tracks.front().map(|q| q.uuid()) == Some(uuid);
+
+            if inner.skip_failed_head && is_head {
+                inner.skip_failed_head(&remote_lock);
+            }
+        });
+    }
+}
+
+/// Records when a queued track is played or paused, including by other code
+/// holding its [`TrackHandle`].
+///
+/// [`TrackHandle`]: crate::tracks::TrackHandle
+pub(super) struct PlayModeNotifier {
+    pub(super) remote_lock: Arc<Mutex<TrackQueueCore>>,
+}
+
+#[async_trait]
+impl EventHandler for PlayModeNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let (state, handle) = match ctx {
+            EventContext::Track(ts) => ts.first()?,
+            _ => return None,
+        };
+
+        let uuid = handle.uuid();
+        let mut inner = self.remote_lock.lock();
+        if let Some(queued) = inner.tracks.iter_mut().find(|q| q.uuid() == uuid) {
+            queued.mode = state.playing;
+        }
+
+        None
+    }
+}
+
+pub(super) struct ReadyNotifier {
+    pub(super) remote_lock: Arc<Mutex<TrackQueueCore>>,
+}
+
+#[async_trait]
+impl EventHandler for ReadyNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let mut inner = self.remote_lock.lock();
+
+        let uuid = match ctx {
+            EventContext::Track(ts) => ts.first()?.1.uuid(),
+            _ => return None,
+        };
+
+        let index = inner.tracks.iter().position(|q| q.uuid() == uuid)?;
+        if inner.tracks[index].ready {
+            return None;
+        }
+        inner.tracks[index].ready = true;
+
+        // Later heads are reported when they are promoted.
+        if index == 0 {
+            inner.notify(QueueUpdate::HeadReady { uuid });
+        }
+
+        None
+    }
+}
+
+pub(super) struct LoopCounter {
+    pub(super) remote_lock: Arc<Mutex<TrackQueueCore>>,
+}
+
+#[async_trait]
+impl EventHandler for LoopCounter {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let mut inner = self.remote_lock.lock();
+
+        let uuid = match ctx {
+            EventContext::Track(ts) => ts.first()?.1.uuid(),
+            _ => return None,
+        };
+
+        let head = inner.tracks.front()?;
+        if head.uuid() != uuid {
+            return None;
+        }
+
+        let head = head.handle();
+        inner.loop_count += 1;
+
+        if matches!(inner.max_loops, Some(max) if inner.loop_count >= max) {
+            let _ = head.disable_loop();
+        }
+
+        None
+    }
+}
+
+pub(super) struct SongPreloader {
+    pub(super) remote_lock: Arc<Mutex<TrackQueueCore>>,
+}
+
+#[async_trait]
+impl EventHandler for SongPreloader {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let mut inner = self.remote_lock.lock();
+
+        // This event is scheduled when a track is added, but the queue may have been
+        // reordered since: only the *current* head may trigger preloading, as any
+        // other track's preload targets are no longer accurate.
+        let uuid = match ctx {
+            EventContext::Track(ts) => ts.first()?.1.uuid(),
+            _ => return None,
+        };
+
+        if inner.tracks.front()?.uuid() != uuid {
+            return None;
+        }
+
+        inner.preloading_for = Some(uuid);
+        inner.preload_upcoming();
+
+        None
+    }
+}
+
+pub(super) struct TransitionTimer {
+    pub(super) remote_lock: Arc<Mutex<TrackQueueCore>>,
+}
+
+#[async_trait]
+impl EventHandler for TransitionTimer {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let mut inner = self.remote_lock.lock();
+
+        let (uuid, started_at) = match ctx {
+            EventContext::Track(ts) => {
+                let (state, handle) = ts.first()?;
+                (handle.uuid(), state.first_frame_at?)
+            },
+            _ => return None,
+        };
+
+        // Only the new head's first frame marks the end of a transition.
+        if inner.tracks.front()?.uuid() != uuid {
+            return None;
+        }
+
+        let (previous, ended_at) = inner.last_end.take()?;
+
+        // Back-to-back frames are one mixer step apart.
+        let gap = started_at
+            .checked_duration_since(ended_at)
+            .and_then(|d| d.checked_sub(TIMESTEP_LENGTH))
+            .unwrap_or_default();
+        inner.notify(QueueUpdate::Transition {
+            previous,
+            current: uuid,
+            gap,
+        });
+
+        None
+    }
+}
+
+pub(super) struct DurationLimiter {
+    pub(super) remote_lock: Arc<Mutex<TrackQueueCore>>,
+}
+
+#[async_trait]
+impl EventHandler for DurationLimiter {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let inner = self.remote_lock.lock();
+
+        let handle = match ctx {
+            EventContext::Track(ts) => ts.first()?.1,
+            _ => return None,
+        };
+
+        // Tracks removed from the queue are no longer its responsibility.
+        if inner.tracks.iter().any(|q| q.uuid() == handle.uuid()) {
+            // Stopping fires the track's end event, which advances the queue.
+            let _ = handle.stop();
+        }
+
+        None
+    }
+}
+
+/// Stops a queued track once its position reaches the end of its clip.
+pub(super) struct ClipLimiter {
+    pub(super) remote_lock: Arc<Mutex<TrackQueueCore>>,
+    pub(super) end: Duration,
+}
+
+#[async_trait]
+impl EventHandler for ClipLimiter {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let (state, handle) = match ctx {
+            EventContext::Track(ts) => ts.first()?,
+            _ => return None,
+        };
+
+        // The track was seeked backwards in the meantime: check again later.
+        if let Some(left) = self.end.checked_sub(state.position) {
+            if left > Duration::default() {
+                return Some(Event::Delayed(left));
+            }
+        }
+
+        let inner = self.remote_lock.lock();
+
+        // Tracks removed from the queue are no longer its responsibility.
+        if inner.tracks.iter().any(|q| q.uuid() == handle.uuid()) {
+            // Stopping fires the track's end event, which advances the queue.
+            let _ = handle.stop();
+        }
+
+        None
+    }
+}
@@ -0,0 +1,144 @@
+//! Records of the tracks which a [`TrackQueue`] has finished with, or failed to play.
+//!
+//! [`TrackQueue`]: super::TrackQueue
+
+use super::{Queued, TrackQueue, TrackQueueCore};
+use crate::{
+    driver::Driver,
+    input::Input,
+    tracks::{self, QueueEntry, TrackError, TrackHandle, TrackResult},
+};
+use uuid::Uuid;
+
+impl TrackQueue {
+    /// Recreates a track from this queue's [history], and adds it to the back of the
+    /// queue, to be played in the channel managed by `handler`.
+    ///
+    /// Sources are recreated lazily from the [`SourceDescriptor`] of the input which the
+    /// track was added with, keeping its metadata, so that nothing is fetched until
+    /// the track is needed.
+    ///
+    /// Returns [`TrackError::NotInHistory`] if `uuid` is not in the queue's history, and
+    /// [`TrackError::NotReplayable`] if the track's input had no descriptor. The same
+    /// errors as [`try_add`] are returned if the track cannot be queued.
+    ///
+    /// [history]: TrackQueue::history
+    /// [`SourceDescriptor`]: crate::input::SourceDescriptor
+    /// [`TrackError::NotInHistory`]: TrackError::NotInHistory
+    /// [`TrackError::NotReplayable`]: TrackError::NotReplayable
+    /// [`try_add`]: TrackQueue::try_add
+    pub fn replay(&self, uuid: Uuid, handler: &mut Driver) -> TrackResult<TrackHandle> {
+        let source = self.replay_source(uuid)?;
+
+        let (track, handle) = tracks::create_player(source);
+        self.try_add(track, handler)?;
+
+        Ok(handle)
+    }
+
+    /// Recreates the source of the track `uuid` in this queue's history.
+    pub(super) fn replay_source(&self, uuid: Uuid) -> TrackResult<Input> {
+        let inner = self.inner.lock();
+
+        let (entry, source) = inner
+            .history
+            .iter()
+            .rev()
+            .find(|(entry, _)| entry.uuid == uuid)
+            .ok_or(TrackError::NotInHistory)?;
+
+        source
+            .as_ref()
+            .and_then(|source| source.recreate(entry.metadata.clone()))
+            .ok_or(TrackError::NotReplayable)
+    }
+
+    /// Sets how many of the most recently finished tracks this queue remembers.
+    ///
+    /// Tracks enter the history once they leave the head of the queue by ending,
+    /// being stopped, or being [skipped]. Tracks which failed to play, or were removed
+    /// before reaching the head, are not included. Lowering this discards the oldest
+    /// entries.
+    ///
+    /// Defaults to `0`, so that no history is kept.
+    ///
+    /// [skipped]: TrackQueue::skip
+    pub fn set_history_len(&self, len: usize) {
+        let mut inner = self.inner.lock();
+
+        inner.history_len = len;
+        while inner.history.len() > len {
+            inner.history.pop_front();
+        }
+    }
+
+    /// Returns the tracks which this queue has finished playing, oldest first.
+    ///
+    /// See [`set_history_len`] for more details.
+    ///
+    /// [`set_history_len`]: TrackQueue::set_history_len
+    pub fn history(&self) -> Vec<QueueEntry> {
+        let inner = self.inner.lock();
+
+        inner
+            .history
+            .iter()
+            .map(|(entry, _)| entry.clone())
+            .collect()
+    }
+
+    /// Returns the number of tracks which could not be played when they reached
+    /// the head of the queue, or could not be [preloaded].
+    ///
+    /// This only increases until [`clear_failures`] is called: it is unaffected by
+    /// [`drain_failures`], [`stop`], and [`replace_all`].
+    ///
+    /// [preloaded]: TrackQueue::set_preload_depth
+    /// [`clear_failures`]: TrackQueue::clear_failures
+    /// [`drain_failures`]: TrackQueue::drain_failures
+    /// [`stop`]: TrackQueue::stop
+    /// [`replace_all`]: TrackQueue::replace_all
+    pub fn failure_count(&self) -> usize {
+        let inner = self.inner.lock();
+
+        inner.failure_count
+    }
+
+    /// Removes and returns the identifier of each track which could not be played,
+    /// alongside the reason, since this was last called.
+    ///
+    /// These entries remain counted by [`failure_count`].
+    ///
+    /// [`failure_count`]: TrackQueue::failure_count
+    pub fn drain_failures(&self) -> Vec<(Uuid, TrackError)> {
+        let mut inner = self.inner.lock();
+
+        std::mem::take(&mut inner.failures)
+    }
+
+    /// Forgets all recorded failures, resetting [`failure_count`] to `0`.
+    ///
+    /// [`failure_count`]: TrackQueue::failure_count
+    pub fn clear_failures(&self) {
+        let mut inner = self.inner.lock();
+
+        inner.failures.clear();
+        inner.failure_count = 0;
+    }
+}
+
+impl TrackQueueCore {
+    /// Adds a track which has left the head of the queue to its history.
+    pub(super) fn remember(&mut self, queued: &Queued) {
+        if self.history_len == 0 {
+            return;
+        }
+
+        if self.history.len() >= self.history_len {
+            self.history.pop_front();
+        }
+
+        self.history
+            .push_back((queued.entry(), queued.source.clone()));
+    }
+}
@@ -0,0 +1,3217 @@
+#[cfg(feature = "builtin-queue")]
+use crate::input::{error::Result as InputResult, restartable::Restart};
+use crate::{
+    constants::TIMESTEP_LENGTH,
+    driver::{Driver, TrackSink},
+    events::{Event, EventContext, EventData, EventHandler, TrackEvent},
+    input::{
+        _ytdl_metadata,
+        Codec,
+        CodecType,
+        Container,
+        Input,
+        Metadata,
+        Restartable,
+        SourceDescriptor,
+    },
+    tracks::{
+        self,
+        LoopState,
+        PlayMode,
+        QueueBackend,
+        QueueEntry,
+        Track,
+        TrackError,
+        TrackHandle,
+        TrackResult,
+        TrackState,
+        VolumeCurve,
+    },
+};
+use flume::{Receiver, Sender, TrySendError};
+use parking_lot::Mutex;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
+    convert::TryFrom,
+    fmt,
+    future::Future,
+    hash::{Hash, Hasher},
+    io::{BufRead, Result as IoResult},
+    ops::Deref,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, Instant},
+};
+#[cfg(not(feature = "tokio-02-marker"))]
+use tokio::{
+    runtime::Handle,
+    time::{sleep as tsleep, timeout},
+};
+#[cfg(feature = "tokio-02-marker")]
+use tokio_compat::{
+    runtime::Handle,
+    time::{delay_for as tsleep, timeout},
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+mod handlers;
+mod history;
+#[cfg(test)]
+mod tests;
+
+use handlers::*;
+
+/// A simple queue for several audio sources, designed to
+/// play in sequence.
+///
+/// This makes use of [`TrackEvent`]s to determine when the current
+/// song or audio file has finished before playing the next entry.
+///
+/// One of these is automatically included via [`Driver::queue`] when
+/// the `"builtin-queue"` feature is enabled.
+///
+/// `examples/serenity/voice_events_queue` demonstrates how a user might manage,
+/// track and use this to run a song queue in many guilds in parallel.
+/// This code is trivial to extend if extra functionality is needed.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use songbird::{
+///     driver::Driver,
+///     id::GuildId,
+///     ffmpeg,
+///     tracks::{create_player, TrackQueue},
+/// };
+/// use std::collections::HashMap;
+///
+/// # async {
+/// let guild = GuildId(0);
+/// // A Call is also valid here!
+/// let mut driver: Driver = Default::default();
+///
+/// let mut queues: HashMap<GuildId, TrackQueue> = Default::default();
+///
+/// let source = ffmpeg("../audio/my-favourite-song.mp3")
+///     .await
+///     .expect("This might fail: handle this error!");
+///
+/// // We need to ensure that this guild has a TrackQueue created for it.
+/// let queue = queues.entry(guild)
+///     .or_default();
+///
+/// // Queueing a track is this easy!
+/// queue.add_source(source, &mut driver);
+/// # };
+/// ```
+///
+/// [`TrackEvent`]: crate::events::TrackEvent
+/// [`Driver::queue`]: crate::driver::Driver
+#[derive(Clone, Debug, Default)]
+pub struct TrackQueue {
+    // NOTE: the choice of a parking lot mutex is quite deliberate
+    inner: Arc<Mutex<TrackQueueCore>>,
+}
+
+/// Reference to a track which is known to be part of a queue.
+///
+/// Instances *should not* be moved from one queue to another.
+#[derive(Debug)]
+pub struct Queued {
+    handle: TrackHandle,
+    pinned: bool,
+    not_before: Option<Instant>,
+    requester: Option<u64>,
+    /// Metadata fetched by [`TrackQueue::refresh_current_metadata`], if any.
+    ///
+    /// [`TrackQueue::refresh_current_metadata`]: TrackQueue::refresh_current_metadata
+    refreshed: Option<Metadata>,
+    /// Whether this track's [`TrackEvent::Ready`] has fired.
+    ///
+    /// [`TrackEvent::Ready`]: TrackEvent::Ready
+    ready: bool,
+    /// Whether the queue has played or preloaded this track.
+    preparing: bool,
+    /// Whether this track is playing or paused, as last set by the queue or
+    /// reported by the track's events.
+    mode: PlayMode,
+    /// How to create this track's source again, if known.
+    source: Option<RetainedSource>,
+}
+
+impl Deref for Queued {
+    type Target = TrackHandle;
+
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
+
+impl From<TrackHandle> for Queued {
+    fn from(handle: TrackHandle) -> Self {
+        Self::from_handle(handle)
+    }
+}
+
+impl Queued {
+    /// Wraps a handle as a queue entry, without adding it to any queue.
+    ///
+    /// This is intended for building test fixtures, or custom queue states via
+    /// [`TrackQueue::modify_queue`]. Entries made this way lack the event handlers
+    /// which advance a queue when its head ends: the underlying [`Track`] should be
+    /// added through [`TrackQueue::add`] (or similar) to gain them.
+    ///
+    /// [`TrackQueue::modify_queue`]: TrackQueue::modify_queue
+    /// [`Track`]: Track
+    /// [`TrackQueue::add`]: TrackQueue::add
+    pub fn from_handle(handle: TrackHandle) -> Self {
+        Self {
+            handle,
+            pinned: false,
+            not_before: None,
+            requester: None,
+            refreshed: None,
+            ready: false,
+            preparing: false,
+            mode: PlayMode::Pause,
+            source: None,
+        }
+    }
+
+    /// Clones the inner handle
+    pub fn handle(&self) -> TrackHandle {
+        self.handle.clone()
+    }
+
+    /// Copies this entry, so that a pinned track can be restored if it is removed.
+    fn duplicate(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+            pinned: self.pinned,
+            not_before: self.not_before,
+            requester: self.requester,
+            refreshed: self.refreshed.clone(),
+            ready: self.ready,
+            preparing: self.preparing,
+            mode: self.mode,
+            source: self.source.clone(),
+        }
+    }
+
+    /// Describes this entry for a [`QueueBackend`].
+    ///
+    /// [`QueueBackend`]: QueueBackend
+    fn entry(&self) -> QueueEntry {
+        QueueEntry {
+            uuid: self.uuid(),
+            pinned: self.pinned,
+            metadata: self.metadata().clone(),
+        }
+    }
+
+    /// Marks whether this track may be skipped or removed from its queue.
+    ///
+    /// Pinned tracks are protected from [`TrackQueue::skip`] and [`TrackQueue::try_dequeue`],
+    /// which return [`TrackError::Pinned`], and from [`TrackQueue::dequeue`],
+    /// [`TrackQueue::prune`] and [`TrackQueue::modify_queue`]. If the head of the queue
+    /// is pinned, then the queue can only advance once it ends naturally.
+    /// [`TrackQueue::stop`] and [`TrackQueue::modify_queue`] ignore this flag.
+    ///
+    /// [`TrackError::Pinned`]: TrackError::Pinned
+    pub fn set_pinned(&mut self, pinned: bool) {
+        self.pinned = pinned;
+    }
+
+    /// Returns whether this track is protected from removal.
+    ///
+    /// See [`set_pinned`] for more details.
+    ///
+    /// [`set_pinned`]: Queued::set_pinned
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Sets the ID of whoever requested this track (e.g., a Discord user ID).
+    ///
+    /// This is used to share out turns between requesters in [fair mode].
+    ///
+    /// [fair mode]: TrackQueue::set_fair_mode
+    pub fn set_requester(&mut self, requester: Option<u64>) {
+        self.requester = requester;
+    }
+
+    /// Returns the ID of whoever requested this track, if set.
+    ///
+    /// See [`set_requester`] for more details.
+    ///
+    /// [`set_requester`]: Queued::set_requester
+    pub fn requester(&self) -> Option<u64> {
+        self.requester
+    }
+
+    /// Returns how far along this track's input is in becoming ready to play.
+    ///
+    /// A track is [`Preparing`] once the queue has started or preloaded it, and
+    /// [`Ready`] once its [`TrackEvent::Ready`] has been handled. Tracks played or
+    /// readied directly through their [`TrackHandle`] are not seen by the queue until
+    /// then. As this relies on events from the driver, it may lag slightly behind the
+    /// mixer's true state.
+    ///
+    /// [`Preparing`]: ReadyState::Preparing
+    /// [`Ready`]: ReadyState::Ready
+    /// [`TrackEvent::Ready`]: TrackEvent::Ready
+    /// [`TrackHandle`]: TrackHandle
+    pub fn ready_state(&self) -> ReadyState {
+        if self.ready {
+            ReadyState::Ready
+        } else if self.preparing {
+            ReadyState::Preparing
+        } else {
+            ReadyState::Uninitialised
+        }
+    }
+}
+
+/// What a queue keeps of a track's [`Input`], so that the track can be created
+/// again once its original source has been used.
+///
+/// [`Input`]: Input
+#[derive(Clone, Debug)]
+struct RetainedSource {
+    descriptor: SourceDescriptor,
+    kind: CodecType,
+    container: Container,
+}
+
+impl RetainedSource {
+    /// Captures how to recreate `source`, if its origin is known.
+    fn of(source: &Input) -> Option<Self> {
+        source.descriptor().map(|descriptor| Self {
+            descriptor: descriptor.clone(),
+            kind: source.get_type(),
+            container: source.container,
+        })
+    }
+
+    /// Creates a new lazy copy of the source, which will start from the beginning.
+    fn recreate(&self, metadata: Metadata) -> Option<Input> {
+        let kind = Codec::try_from(self.kind).ok()?;
+        let source =
+            Restartable::from_descriptor(self.descriptor.clone(), metadata, kind, self.container);
+
+        Some(source.into())
+    }
+}
+
+/// A change made to the contents of a [`TrackQueue`].
+///
+/// These are received by subscribers created via [`TrackQueue::watch`].
+///
+/// [`TrackQueue`]: TrackQueue
+/// [`TrackQueue::watch`]: TrackQueue::watch
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum QueueUpdate {
+    /// A track was added to the queue at the given index.
+    Added {
+        /// The new track's unique identifier.
+        uuid: Uuid,
+        /// The new track's position in the queue.
+        index: usize,
+    },
+    /// A track was removed from the queue without being played to completion.
+    Removed {
+        /// The removed track's unique identifier.
+        uuid: Uuid,
+    },
+    /// The head of the queue ended, and the queue moved on to the next track.
+    Advanced {
+        /// The new head of the queue, or `None` if the queue is now empty.
+        new_head: Option<Uuid>,
+    },
+    /// The order of tracks in the queue was changed.
+    Reordered,
+    /// The track at the head of the queue changed, for any reason.
+    ///
+    /// This accompanies other updates (e.g., [`Advanced`] or [`Reordered`]), and is
+    /// intended for refreshing a "now playing" display.
+    ///
+    /// [`Advanced`]: QueueUpdate::Advanced
+    /// [`Reordered`]: QueueUpdate::Reordered
+    HeadChanged {
+        /// The previous head of the queue, if any.
+        previous: Option<Uuid>,
+        /// The new head of the queue, or `None` if the queue is now empty.
+        current: Option<Uuid>,
+        /// The token set by [`TrackQueue::set_now_playing`], if any.
+        ///
+        /// [`TrackQueue::set_now_playing`]: TrackQueue::set_now_playing
+        now_playing: Option<u64>,
+    },
+    /// All tracks were removed from the queue.
+    Cleared,
+    /// The new head of the queue began to play, after the previous head ended.
+    ///
+    /// `gap` is the silence between the last frame of audio mixed from `previous`
+    /// and the first mixed from `current`, as timed by the mixer. This is zero if
+    /// the new head was ready in time, so sustained gaps suggest that tracks are not
+    /// being preloaded early enough. If `previous` never produced any audio, this is
+    /// measured from when it ended instead.
+    Transition {
+        /// The ended track's unique identifier.
+        previous: Uuid,
+        /// The new head's unique identifier.
+        current: Uuid,
+        /// Time spent between the two tracks.
+        gap: Duration,
+    },
+    /// The input of the track at the head of the queue is now ready to play.
+    ///
+    /// This is sent at most once for each new head: when it becomes the head, if its
+    /// input was already created (e.g., by preloading), or otherwise once its
+    /// [`TrackEvent::Ready`] fires. Unlike [`HeadChanged`], this marks the end of any
+    /// wait for the head's source to be fetched or started.
+    ///
+    /// [`TrackEvent::Ready`]: TrackEvent::Ready
+    /// [`HeadChanged`]: QueueUpdate::HeadChanged
+    HeadReady {
+        /// The head's unique identifier.
+        uuid: Uuid,
+    },
+}
+
+/// Action taken by a [`TrackQueue`] once its last track ends.
+///
+/// Set via [`TrackQueue::set_on_complete`].
+///
+/// [`TrackQueue`]: TrackQueue
+/// [`TrackQueue::set_on_complete`]: TrackQueue::set_on_complete
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum OnComplete {
+    /// The queue is left empty.
+    ///
+    /// This is the default behaviour.
+    Stop,
+    /// The given handler is run, e.g., to fetch and enqueue recommended tracks.
+    ///
+    /// This is passed the context of the final track's [`TrackEvent::End`]. Any
+    /// [`Event`] returned by the handler is ignored.
+    ///
+    /// [`TrackEvent::End`]: TrackEvent::End
+    /// [`Event`]: Event
+    Callback(Arc<dyn EventHandler>),
+    /// Every track which has finished since the queue was last empty is added again,
+    /// in the order they played.
+    ///
+    /// Each track is recreated lazily from the [`SourceDescriptor`] of the input it was
+    /// added with, keeping its metadata. Tracks whose inputs had no descriptor are left
+    /// out with a warning, as are tracks which ended without producing any audio (e.g.,
+    /// broken links). Recreated tracks are sent to the driver most recently passed to one
+    /// of the queue's add methods, and are subject to its [maximum number of tracks].
+    ///
+    /// [`TrackQueue::stop`], [`TrackQueue::replace_all`] and changing this policy
+    /// forget all tracks which have finished so far.
+    ///
+    /// [`SourceDescriptor`]: crate::input::SourceDescriptor
+    /// [maximum number of tracks]: crate::Config::max_concurrent_tracks
+    /// [`TrackQueue::stop`]: TrackQueue::stop
+    /// [`TrackQueue::replace_all`]: TrackQueue::replace_all
+    LoopQueue,
+}
+
+impl fmt::Debug for OnComplete {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OnComplete::Stop => write!(f, "Stop"),
+            OnComplete::LoopQueue => write!(f, "LoopQueue"),
+            OnComplete::Callback(_) => write!(f, "Callback([handler])"),
+        }
+    }
+}
+
+/// Readiness of a track's input, as last seen by its [`TrackQueue`].
+///
+/// See [`Queued::ready_state`] for how this is tracked.
+///
+/// [`TrackQueue`]: TrackQueue
+/// [`Queued::ready_state`]: Queued::ready_state
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[non_exhaustive]
+pub enum ReadyState {
+    /// The queue has not yet asked for the track's input to be created.
+    Uninitialised,
+    /// The track's input is still being created, e.g., while a lazy source starts.
+    Preparing,
+    /// The track's input is ready to play.
+    Ready,
+}
+
+/// Snapshot of a [`TrackQueue`]'s state, taken under a single lock.
+///
+/// Created via [`TrackQueue::stats`].
+///
+/// [`TrackQueue`]: TrackQueue
+/// [`TrackQueue::stats`]: TrackQueue::stats
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[non_exhaustive]
+pub struct QueueStats {
+    /// Number of tracks in the queue, including the head.
+    pub len: usize,
+    /// Number of tracks waiting behind the head.
+    pub upcoming: usize,
+    /// Combined duration of all tracks with a known duration, as given by
+    /// [`TrackQueue::known_duration`].
+    ///
+    /// [`TrackQueue::known_duration`]: TrackQueue::known_duration
+    pub known_duration: Duration,
+    /// Number of times the current head has looped.
+    pub loop_count: usize,
+    /// Most times each head may loop, as set by [`TrackQueue::set_max_loops`].
+    ///
+    /// [`TrackQueue::set_max_loops`]: TrackQueue::set_max_loops
+    pub max_loops: Option<usize>,
+    /// Gain applied to every track in the queue, if set.
+    pub volume: Option<f32>,
+    /// Number of tracks which could not be played, as given by
+    /// [`TrackQueue::failure_count`].
+    ///
+    /// [`TrackQueue::failure_count`]: TrackQueue::failure_count
+    pub failure_count: usize,
+    /// Readiness of the head of the queue, or `None` if the queue is empty.
+    pub head: Option<ReadyState>,
+    /// Whether the head of the queue is playing or paused, or `None` if the queue
+    /// is empty.
+    ///
+    /// This is the state last set by the queue or reported by the head's events, so
+    /// may briefly lag behind commands sent directly to the head's [`TrackHandle`].
+    ///
+    /// [`TrackHandle`]: TrackHandle
+    pub head_mode: Option<PlayMode>,
+}
+
+/// Check run on the source of each track added to a [`TrackQueue`].
+///
+/// [`TrackQueue`]: TrackQueue
+#[derive(Clone)]
+struct SourceFilter(Arc<SourceCheck>);
+
+type SourceCheck = dyn Fn(&Input) -> TrackResult<()> + Send + Sync;
+
+impl fmt::Debug for SourceFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SourceFilter([function])")
+    }
+}
+
+/// Number of unreceived updates held for each [`TrackQueue::watch`] subscriber.
+///
+/// [`TrackQueue::watch`]: TrackQueue::watch
+const WATCH_BUFFER_LEN: usize = 32;
+
+/// Exclusive hold on a [`TrackQueue`]'s playback while its contents are edited.
+///
+/// Created via [`TrackQueue::begin_edit`]. The queue's methods may be called through
+/// this guard, and the queue will not advance until it (and all other guards on the
+/// same queue) are dropped.
+///
+/// [`TrackQueue`]: TrackQueue
+/// [`TrackQueue::begin_edit`]: TrackQueue::begin_edit
+#[derive(Debug)]
+pub struct QueueEditGuard<'a> {
+    queue: &'a TrackQueue,
+}
+
+impl Deref for QueueEditGuard<'_> {
+    type Target = TrackQueue;
+
+    fn deref(&self) -> &Self::Target {
+        self.queue
+    }
+}
+
+impl Drop for QueueEditGuard<'_> {
+    fn drop(&mut self) {
+        let remote_lock = &self.queue.inner;
+
+        let deferred = {
+            let mut inner = remote_lock.lock();
+            inner.editors -= 1;
+
+            if inner.editors > 0 {
+                return;
+            }
+
+            let head = inner.tracks.front().map(|q| q.uuid());
+            match inner.deferred_end.take() {
+                Some(end) if Some(end.1.uuid()) == head => Some(end),
+                _ => {
+                    if inner.resume_after_edit {
+                        inner.start_playable_head(remote_lock);
+                    }
+                    None
+                },
+            }
+        };
+
+        // The head ended while editing, and is still in place: move on from it.
+        if let Some((state, handle)) = deferred {
+            let handler = QueueHandler {
+                remote_lock: remote_lock.clone(),
+            };
+
+            match handler.advance(&EventContext::Track(&[(&state, &handle)])) {
+                Some(Completion::Callback(callback)) => match Handle::try_current() {
+                    Ok(rt) => {
+                        rt.spawn(async move {
+                            let _ = callback
+                                .act(&EventContext::Track(&[(&state, &handle)]))
+                                .await;
+                        });
+                    },
+                    Err(_) => warn!("No async runtime to run queue completion callback."),
+                },
+                Some(Completion::Loop(driver, finished)) => handler.requeue(&driver, finished),
+                None => {},
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Inner portion of a [`TrackQueue`].
+///
+/// This abstracts away thread-safety from the user,
+/// and offers a convenient location to store further state if required.
+///
+/// [`TrackQueue`]: TrackQueue
+struct TrackQueueCore {
+    tracks: VecDeque<Queued>,
+    preload_depth: usize,
+    /// The head track whose preload window has been entered, if any.
+    preloading_for: Option<Uuid>,
+    watchers: Vec<Sender<QueueUpdate>>,
+    shuffle_on_add: bool,
+    fair_mode: bool,
+    skip_failed_head: bool,
+    /// Whether a track added to an empty queue starts playing immediately.
+    autostart: bool,
+    /// Check which each new track's source must pass, if set.
+    source_filter: Option<SourceFilter>,
+    /// Number of live [`QueueEditGuard`]s.
+    ///
+    /// [`QueueEditGuard`]: QueueEditGuard
+    editors: usize,
+    /// The head's end event, if it ended while the queue was being edited.
+    deferred_end: Option<(TrackState, TrackHandle)>,
+    /// Whether the head was playing when the first live [`QueueEditGuard`] was created.
+    ///
+    /// [`QueueEditGuard`]: QueueEditGuard
+    resume_after_edit: bool,
+    /// The turn on which each requester's track last finished playing.
+    requester_turns: HashMap<Option<u64>, u64>,
+    /// Most tracks each requester may add within the given window, if limited.
+    enqueue_limit: Option<(u32, Duration)>,
+    /// When each requester added their tracks within the current window, oldest first.
+    enqueue_times: HashMap<u64, VecDeque<Instant>>,
+    turn: u64,
+    rng: StdRng,
+    buffer_while_disconnected: bool,
+    /// Tracks which could not be played when they reached the head of the queue.
+    failures: Vec<(Uuid, TrackError)>,
+    failure_count: usize,
+    now_playing: Option<u64>,
+    on_complete: OnComplete,
+    /// Driver most recently passed to an add method, to which looped tracks are sent.
+    driver: Option<TrackSink>,
+    /// Tracks to recreate for [`OnComplete::LoopQueue`], oldest first, alongside
+    /// their requesters.
+    ///
+    /// [`OnComplete::LoopQueue`]: OnComplete::LoopQueue
+    finished: Vec<(Metadata, RetainedSource, Option<u64>)>,
+    /// Number of times the current head has looped.
+    loop_count: usize,
+    max_loops: Option<usize>,
+    label: Option<String>,
+    /// Gain applied to every track in the queue, if set.
+    volume: Option<f32>,
+    /// External mirror of the queue's order, if any.
+    backend: Option<Box<dyn QueueBackend>>,
+    /// The last head to end, and when, until the new head starts to play.
+    last_end: Option<(Uuid, Instant)>,
+    /// Whether the new head was ready to play when the queue last advanced.
+    last_transition_gapless: Option<bool>,
+    /// When the current head reached the front of the queue.
+    head_since: Option<Instant>,
+    /// Tracks which played to completion, oldest first, alongside how to recreate them.
+    history: VecDeque<(QueueEntry, Option<RetainedSource>)>,
+    history_len: usize,
+}
+
+impl Default for TrackQueueCore {
+    fn default() -> Self {
+        Self {
+            tracks: VecDeque::new(),
+            preload_depth: 1,
+            preloading_for: None,
+            watchers: Vec::new(),
+            shuffle_on_add: false,
+            fair_mode: false,
+            skip_failed_head: false,
+            autostart: true,
+            source_filter: None,
+            editors: 0,
+            deferred_end: None,
+            resume_after_edit: false,
+            requester_turns: HashMap::new(),
+            enqueue_limit: None,
+            enqueue_times: HashMap::new(),
+            turn: 0,
+            rng: StdRng::from_entropy(),
+            buffer_while_disconnected: false,
+            failures: Vec::new(),
+            failure_count: 0,
+            now_playing: None,
+            on_complete: OnComplete::Stop,
+            driver: None,
+            finished: Vec::new(),
+            loop_count: 0,
+            max_loops: None,
+            label: None,
+            volume: None,
+            backend: None,
+            last_end: None,
+            last_transition_gapless: None,
+            head_since: None,
+            history: VecDeque::new(),
+            history_len: 0,
+        }
+    }
+}
+
+/// Starts a track which has just become the head of the queue, deferring this
+/// until its scheduled time if it has one.
+///
+/// Returns whether the track was left playing or (until its scheduled time) paused.
+fn start_head(queued: &Queued, remote_lock: &Arc<Mutex<TrackQueueCore>>) -> TrackResult<PlayMode> {
+    match queued.not_before {
+        Some(at) if at > Instant::now() => {
+            // Pausing confirms that the track is still alive.
+            queued.pause()?;
+
+            if schedule_start(remote_lock.clone(), queued.uuid(), at) {
+                Ok(PlayMode::Pause)
+            } else {
+                queued.play().map(|_| PlayMode::Play)
+            }
+        },
+        _ => queued.play().map(|_| PlayMode::Play),
+    }
+}
+
+/// Plays the track `uuid` at `at`, if it is still the head of the queue by then.
+///
+/// Returns `false` if there is no async runtime to wait on.
+fn schedule_start(remote_lock: Arc<Mutex<TrackQueueCore>>, uuid: Uuid, at: Instant) -> bool {
+    let handle = match Handle::try_current() {
+        Ok(handle) => handle,
+        Err(_) => {
+            warn!(
+                "No async runtime to schedule track {}: starting immediately.",
+                uuid
+            );
+            return false;
+        },
+    };
+
+    handle.spawn(async move {
+        tsleep(at.saturating_duration_since(Instant::now())).await;
+
+        let mut inner = remote_lock.lock();
+        let head = inner.tracks.front_mut().filter(|head| head.uuid() == uuid);
+
+        if let Some(head) = head {
+            match head.play() {
+                Ok(()) => head.mode = PlayMode::Play,
+                Err(_) => warn!("Scheduled track {} ended before its start time.", uuid),
+            }
+        }
+    });
+
+    true
+}
+
+/// Registers `events` on a track which has not yet been handed to a driver, timing
+/// any delayed events from `now`.
+fn add_track_events(track: &mut Track, events: Vec<EventData>, now: Duration) {
+    let store = track
+        .events
+        .as_mut()
+        .expect("Queue inspecting EventStore on new Track: did not exist.");
+
+    for event in events {
+        store.add_event(event, now);
+    }
+}
+
+/// Where and how a new track is placed in a [`TrackQueue`].
+///
+/// [`TrackQueue`]: TrackQueue
+#[derive(Clone, Copy, Debug, Default)]
+struct AddOptions {
+    /// Whether to place the track directly after the head.
+    next: bool,
+    /// When the track may start playing, if scheduled.
+    not_before: Option<Instant>,
+    /// Whether to leave the track playing, rather than pausing it until it reaches the head.
+    unpaused: bool,
+}
+
+/// Longest time [`TrackQueue::transfer_to`] waits for the old driver's mixer to
+/// hand back all tracks.
+///
+/// [`TrackQueue::transfer_to`]: TrackQueue::transfer_to
+const TRANSFER_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The live portion of a track taken from one driver, for use in another.
+struct MovedTrack {
+    source: Input,
+    volume: f32,
+    loops: LoopState,
+    position: Duration,
+}
+
+/// Progress of a single track through [`TrackQueue::transfer_to`].
+///
+/// This is shared with the old driver's mixer, so that a track which is handed
+/// back too late is left untouched.
+///
+/// [`TrackQueue::transfer_to`]: TrackQueue::transfer_to
+enum TransferSlot {
+    Waiting,
+    Moved(Box<MovedTrack>),
+    Abandoned,
+}
+
+impl TrackQueue {
+    /// Create a new, empty, track queue.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(TrackQueueCore::default())),
+        }
+    }
+
+    /// Adds an audio source to the queue, to be played in the channel managed by `handler`.
+    ///
+    /// If the source cannot be queued, it is dropped and the reason is logged:
+    /// use [`try_add_source`] to handle this instead.
+    ///
+    /// [`try_add_source`]: TrackQueue::try_add_source
+    pub fn add_source(&self, source: Input, handler: &mut Driver) {
+        let (audio, _) = tracks::create_player(source);
+        self.add(audio, handler);
+    }
+
+    /// Adds an audio source to the queue, to be played in the channel managed by `handler`.
+    ///
+    /// Returns all errors returned by [`try_add`].
+    ///
+    /// [`try_add`]: TrackQueue::try_add
+    pub fn try_add_source(&self, source: Input, handler: &mut Driver) -> TrackResult<()> {
+        let (audio, _) = tracks::create_player(source);
+        self.try_add(audio, handler)
+    }
+
+    /// Adds a [`Track`] object to the queue, to be played in the channel managed by `handler`.
+    ///
+    /// This is used with [`create_player`] if additional configuration or event handlers
+    /// are required before enqueueing the audio track.
+    ///
+    /// If the track cannot be queued, it is dropped and the reason is logged:
+    /// use [`try_add`] to handle this instead.
+    ///
+    /// [`Track`]: Track
+    /// [`create_player`]: super::create_player
+    /// [`try_add`]: TrackQueue::try_add
+    pub fn add(&self, track: Track, handler: &mut Driver) {
+        let uuid = track.uuid;
+
+        if let Err(e) = self.try_add(track, handler) {
+            warn!("Track {} could not be queued: {}.", uuid, e);
+        }
+    }
+
+    /// Adds a [`Track`] object to the queue, to be played in the channel managed by `handler`.
+    ///
+    /// Returns [`TrackError::NotConnected`] if `handler` has no active connection, unless
+    /// [buffering while disconnected] is enabled.
+    ///
+    /// Returns [`TrackError::DuplicateUuid`] if the queue already holds a track with the
+    /// same UUID, or [`TrackError::TooManyTracks`] if `handler` already holds its
+    /// [maximum number of tracks].
+    ///
+    /// [`Track`]: Track
+    /// [`TrackError::NotConnected`]: TrackError::NotConnected
+    /// [buffering while disconnected]: TrackQueue::set_buffer_while_disconnected
+    /// [`TrackError::DuplicateUuid`]: TrackError::DuplicateUuid
+    /// [`TrackError::TooManyTracks`]: TrackError::TooManyTracks
+    /// [maximum number of tracks]: crate::Config::max_concurrent_tracks
+    pub fn try_add(&self, track: Track, handler: &mut Driver) -> TrackResult<()> {
+        self.admit(track, handler, AddOptions::default())
+    }
+
+    /// Adds a [`Track`] object to the queue without pausing it, to be played in the
+    /// channel managed by `handler`.
+    ///
+    /// Tracks added by [`add`] are paused until they reach the head of the queue.
+    /// Tracks added here play as soon as `handler` receives them, mixed alongside
+    /// the head and any other unpaused tracks, e.g., to layer ambient sounds.
+    ///
+    /// **This breaks the queue's usual guarantee that only its head is audible.**
+    /// The queue still moves on only once the head ends, so an unpaused track may
+    /// finish before it reaches the head, and is then discarded as unplayable.
+    /// Callers are responsible for stopping or dequeuing these tracks as needed.
+    ///
+    /// Returns all errors returned by [`try_add`].
+    ///
+    /// [`Track`]: Track
+    /// [`add`]: TrackQueue::add
+    /// [`try_add`]: TrackQueue::try_add
+    pub fn add_unpaused(&self, track: Track, handler: &mut Driver) -> TrackResult<()> {
+        let opts = AddOptions {
+            unpaused: true,
+            ..Default::default()
+        };
+
+        self.admit(track, handler, opts)
+    }
+
+    /// Adds a [`Track`] object to the queue on behalf of `requester` (e.g., a Discord
+    /// user ID), to be played in the channel managed by `handler`.
+    ///
+    /// This is a shorthand for setting the track's [requester] before calling [`try_add`].
+    ///
+    /// [`Track`]: Track
+    /// [requester]: Track::set_requester
+    /// [`try_add`]: TrackQueue::try_add
+    pub fn add_for_requester(
+        &self,
+        mut track: Track,
+        requester: u64,
+        handler: &mut Driver,
+    ) -> TrackResult<()> {
+        track.set_requester(Some(requester));
+
+        self.try_add(track, handler)
+    }
+
+    /// Moves the tracks waiting in `other` onto the end of this queue, to be played
+    /// in the channel managed by `handler`.
+    ///
+    /// A track's live source cannot be handed between queues (or drivers), so each
+    /// moved track is stopped, and replaced here by a new lazy [`Restartable`] which is
+    /// recreated from the [`SourceDescriptor`] of the input it was added with. Moved
+    /// tracks keep their metadata, [requester] and [pinned] status, but start again
+    /// from the beginning. Tracks whose inputs had no descriptor cannot be recreated,
+    /// and are left in `other` untouched.
+    ///
+    /// Event handlers, effects and per-track volumes set on the old tracks are not
+    /// carried over, so any which are still needed must be added to the returned handles.
+    ///
+    /// If `include_head` is set, the track playing in `other` is moved too (restarting
+    /// it here), and `other` moves on to its next remaining track. Otherwise, it is
+    /// left to play out in `other`.
+    ///
+    /// `other` is emptied in a single step, so none of its users can observe it partly
+    /// merged. Tracks are then appended to this queue one by one, in their original
+    /// order. Returns handles to the new tracks, or [`TrackError::NotConnected`] (in
+    /// which case neither queue is modified) if `handler` has no active connection and
+    /// [buffering while disconnected] is disabled.
+    ///
+    /// [`Restartable`]: crate::input::Restartable
+    /// [`SourceDescriptor`]: crate::input::SourceDescriptor
+    /// [requester]: Queued::set_requester
+    /// [pinned]: Queued::set_pinned
+    /// [`TrackError::NotConnected`]: TrackError::NotConnected
+    /// [buffering while disconnected]: TrackQueue::set_buffer_while_disconnected
+    pub fn append_queue(
+        &self,
+        other: &TrackQueue,
+        include_head: bool,
+        handler: &mut Driver,
+    ) -> TrackResult<Vec<TrackHandle>> {
+        if Arc::ptr_eq(&self.inner, &other.inner) {
+            return Ok(vec![]);
+        }
+
+        self.inner.lock().check_connected(handler)?;
+
+        let moved = {
+            let mut inner = other.inner.lock();
+
+            let previous = inner.tracks.front().map(|q| q.uuid());
+            let before = inner.uuids();
+            let first_moved = if include_head { 0 } else { 1 };
+
+            let movable = inner
+                .tracks
+                .iter()
+                .skip(first_moved)
+                .filter(|q| q.source.is_some())
+                .count();
+            handler.reserve_track_room(movable)?;
+
+            let mut moved = vec![];
+            for (i, queued) in std::mem::take(&mut inner.tracks).into_iter().enumerate() {
+                let meta = queued
+                    .refreshed
+                    .clone()
+                    .unwrap_or_else(|| queued.metadata().clone());
+                let source = queued
+                    .source
+                    .as_ref()
+                    .filter(|_| i >= first_moved)
+                    .and_then(|source| source.recreate(meta));
+
+                match source {
+                    Some(source) => moved.push((queued, source)),
+                    None => inner.tracks.push_back(queued),
+                }
+            }
+
+            // Tracks whose decoders could not be recreated stay behind in `other`.
+            handler.release_track_room(movable - moved.len());
+
+            if inner.tracks.front().map(|q| q.uuid()) != previous {
+                inner.preloading_for = None;
+                inner.start_playable_head(&other.inner);
+            }
+            inner.refresh_preload();
+            inner.notify_changes(&before);
+
+            moved
+        };
+
+        let mut handles = Vec::with_capacity(moved.len());
+        let mut carried = HashMap::new();
+        for (old, source) in moved {
+            let _ = old.stop();
+
+            let (mut track, handle) = tracks::create_player(source);
+            track.set_requester(old.requester);
+            self.add_raw(&mut track);
+            handler.play_reserved(track);
+
+            carried.insert(handle.uuid(), old.pinned);
+            handles.push(handle);
+        }
+
+        self.modify_queue(|vq| {
+            for queued in vq.iter_mut() {
+                if let Some(pinned) = carried.get(&queued.uuid()) {
+                    queued.pinned = *pinned;
+                }
+            }
+        });
+
+        Ok(handles)
+    }
+
+    /// Adds an audio source to the queue with a given `uuid`, to be played in the channel
+    /// managed by `handler`.
+    ///
+    /// This allows the track to be found and removed using an ID from an external
+    /// system, via [`position_of`] and [`dequeue_uuid`].
+    ///
+    /// Returns [`TrackError::DuplicateUuid`] if the queue already holds a track with
+    /// this UUID, or [`TrackError::NotConnected`] if `handler` has no active connection
+    /// (unless [buffering while disconnected] is enabled).
+    ///
+    /// [`position_of`]: TrackQueue::position_of
+    /// [`dequeue_uuid`]: TrackQueue::dequeue_uuid
+    /// [`TrackError::DuplicateUuid`]: TrackError::DuplicateUuid
+    /// [`TrackError::NotConnected`]: TrackError::NotConnected
+    /// [buffering while disconnected]: TrackQueue::set_buffer_while_disconnected
+    pub fn add_source_with_uuid(
+        &self,
+        source: Input,
+        uuid: Uuid,
+        handler: &mut Driver,
+    ) -> TrackResult<TrackHandle> {
+        let (track, handle) = tracks::create_player_with_uuid(source, uuid);
+        self.try_add(track, handler).map(|_| handle)
+    }
+
+    /// Places a track which is already playing, e.g., via [`Driver::play`], at the
+    /// head of the queue without restarting it.
+    ///
+    /// The previous head (if any) is paused, and resumes once the adopted track ends.
+    /// If `preload_time` is given, upcoming tracks are readied once the adopted track
+    /// has played for this much longer.
+    ///
+    /// Returns [`TrackError::DuplicateUuid`] if this queue already holds the track,
+    /// or [`TrackError::Finished`] if it has ended. Queues cannot see one another's
+    /// contents: adopting the same track into two queues will leave both in an
+    /// inconsistent state.
+    ///
+    /// [`Driver::play`]: crate::driver::Driver::play
+    /// [`TrackError::DuplicateUuid`]: TrackError::DuplicateUuid
+    /// [`TrackError::Finished`]: TrackError::Finished
+    pub fn adopt(&self, handle: TrackHandle, preload_time: Option<Duration>) -> TrackResult<()> {
+        let mut inner = self.inner.lock();
+
+        let uuid = handle.uuid();
+        inner.check_unique(uuid)?;
+
+        // Checked up front, so that no handlers are attached to a track we then refuse.
+        // A track can still end between the calls below, but then it and any handlers
+        // already sent to it are dropped by the driver, and never fire.
+        if handle.is_finished() {
+            return Err(TrackError::Finished);
+        }
+
+        let remote_lock = self.inner.clone();
+        handle.add_event(Event::Track(TrackEvent::End), QueueHandler { remote_lock })?;
+        let remote_lock = self.inner.clone();
+        handle.add_event(Event::Track(TrackEvent::Loop), LoopCounter { remote_lock })?;
+        let remote_lock = self.inner.clone();
+        handle.add_event(
+            Event::Track(TrackEvent::Started),
+            TransitionTimer { remote_lock },
+        )?;
+        for event in &[TrackEvent::Play, TrackEvent::Pause] {
+            let remote_lock = self.inner.clone();
+            handle.add_event(Event::Track(*event), PlayModeNotifier { remote_lock })?;
+        }
+
+        if let Some(time) = preload_time {
+            let remote_lock = self.inner.clone();
+            handle.add_event(Event::Delayed(time), SongPreloader { remote_lock })?;
+        }
+
+        if let Some(volume) = inner.volume {
+            handle.set_volume(volume)?;
+        }
+
+        let previous = inner.tracks.front().map(|q| q.uuid());
+        if let Some(head) = inner.tracks.front_mut() {
+            // As in `stop`, an error just implies the track is already gone.
+            let _ = head.pause();
+            head.mode = PlayMode::Pause;
+        }
+
+        // The track is already playing, so its `TrackEvent::Ready` may have
+        // passed before the queue could listen for it.
+        let mut queued = Queued::from_handle(handle);
+        queued.ready = true;
+        queued.mode = PlayMode::Play;
+        inner.tracks.push_front(queued);
+        inner.preloading_for = None;
+
+        inner.notify(QueueUpdate::Added { uuid, index: 0 });
+        inner.notify_head_change(previous);
+
+        Ok(())
+    }
+
+    /// Adds one track per line of `reader` to the queue, to be played in the channel
+    /// managed by `handler`.
+    ///
+    /// Each line is trimmed and passed to `make_input`, e.g., to build an [`Input`] from
+    /// a URL or file path. Blank lines, and lines beginning with `#`, are skipped.
+    ///
+    /// Returns the outcome of enqueueing each line, alongside its (1-indexed) line number.
+    /// Reading stops at the first I/O error, which is returned instead.
+    ///
+    /// [`Input`]: Input
+    pub fn add_from_reader<R, F>(
+        &self,
+        reader: R,
+        make_input: F,
+        handler: &mut Driver,
+    ) -> IoResult<Vec<(usize, TrackResult<TrackHandle>)>>
+    where
+        R: BufRead,
+        F: Fn(String) -> Input,
+    {
+        let mut out = vec![];
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (track, handle) = tracks::create_player(make_input(line.to_string()));
+            out.push((i + 1, self.try_add(track, handler).map(|_| handle)));
+        }
+
+        Ok(out)
+    }
+
+    /// Exports the contents of the queue as an extended M3U playlist, for use by
+    /// other media players.
+    ///
+    /// Each track is listed with its title and duration (from the same metadata as
+    /// [`current_metadata`]), and its [`Metadata::source_url`] as its location.
+    /// Tracks without a source URL cannot be located by other players: these are
+    /// noted in a comment, and otherwise skipped.
+    ///
+    /// The output can be read back via [`add_from_reader`], which ignores all comments.
+    ///
+    /// [`current_metadata`]: TrackQueue::current_metadata
+    /// [`Metadata::source_url`]: crate::input::Metadata::source_url
+    /// [`add_from_reader`]: TrackQueue::add_from_reader
+    pub fn to_m3u(&self) -> String {
+        let inner = self.inner.lock();
+
+        let mut out = String::from("#EXTM3U\n");
+
+        for queued in inner.tracks.iter() {
+            let meta = queued
+                .refreshed
+                .as_ref()
+                .unwrap_or_else(|| queued.metadata());
+            let title = meta.title.as_ref().or(meta.track.as_ref());
+            let name = match (&meta.artist, title) {
+                (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+                (None, Some(title)) => title.clone(),
+                _ => queued.uuid().to_string(),
+            };
+            // Line breaks would end the entry early, and could forge new entries.
+            let name = name.replace(['\r', '\n'].as_ref(), " ");
+
+            match meta
+                .source_url
+                .as_ref()
+                .filter(|url| !url.contains(['\r', '\n'].as_ref()))
+            {
+                Some(url) => {
+                    let secs = meta.duration.map(|d| d.as_secs() as i64).unwrap_or(-1);
+                    out.push_str(&format!("#EXTINF:{},{}\n{}\n", secs, name, url));
+                },
+                None => out.push_str(&format!("# Skipped {}: no source URL\n", name)),
+            }
+        }
+
+        out
+    }
+
+    /// Adds one new track to the built-in queue of each driver in `handlers`, all
+    /// created from the same source `descriptor`.
+    ///
+    /// Requires the `"builtin-queue"` feature.
+    ///
+    /// As an [`Input`] cannot be shared between drivers, only sources which
+    /// can be rebuilt from a cloneable [`Restart`] implementation qualify.
+    /// Each driver receives a lazy [`Restartable`] made from its own copy of
+    /// `descriptor`, which does not start its source until it reaches the head of
+    /// that driver's queue.
+    ///
+    /// Returns an error if any source could not be created, in which case no tracks
+    /// are queued. Otherwise, returns the outcome of queueing in each driver, in the
+    /// order of `handlers`: see [`Driver::try_enqueue`].
+    ///
+    /// [`Input`]: Input
+    /// [`Restart`]: crate::input::restartable::Restart
+    /// [`Restartable`]: crate::input::Restartable
+    /// [`Driver::try_enqueue`]: crate::driver::Driver::try_enqueue
+    #[cfg(feature = "builtin-queue")]
+    pub async fn add_source_multi<R>(
+        descriptor: R,
+        handlers: &mut [&mut Driver],
+    ) -> InputResult<Vec<TrackResult<TrackHandle>>>
+    where
+        R: Restart + Clone + Send + 'static,
+    {
+        let mut sources = Vec::with_capacity(handlers.len());
+
+        for _ in 0..handlers.len() {
+            sources.push(Restartable::new(descriptor.clone(), true).await?);
+        }
+
+        Ok(handlers
+            .iter_mut()
+            .zip(sources)
+            .map(|(handler, source)| {
+                let (track, handle) = tracks::create_player(source.into());
+                handler.try_enqueue(track).map(|_| handle)
+            })
+            .collect())
+    }
+
+    /// Adds an audio source to play immediately after the current track, in the
+    /// channel managed by `handler`.
+    ///
+    /// If the queue is empty, the source begins playing straight away.
+    /// This position is used even if [shuffle on add] is enabled.
+    ///
+    /// Returns [`TrackError::NotConnected`] if `handler` has no active connection, unless
+    /// [buffering while disconnected] is enabled.
+    ///
+    /// [shuffle on add]: TrackQueue::set_shuffle_on_add
+    /// [`TrackError::NotConnected`]: TrackError::NotConnected
+    /// [buffering while disconnected]: TrackQueue::set_buffer_while_disconnected
+    pub fn add_next(&self, source: Input, handler: &mut Driver) -> TrackResult<TrackHandle> {
+        let (audio, handle) = tracks::create_player(source);
+        let opts = AddOptions {
+            next: true,
+            ..Default::default()
+        };
+        self.admit(audio, handler, opts)?;
+
+        Ok(handle)
+    }
+
+    /// Adds an audio source to the queue, which will not start playing until `not_before`.
+    ///
+    /// If this track reaches the head of the queue early, the queue waits (playing
+    /// nothing) until the scheduled time. Scheduling never reorders the queue: if
+    /// several scheduled tracks are queued, each waits for its own time only once it
+    /// reaches the head, so a track due *earlier* than the one ahead of it plays
+    /// late, directly after it.
+    ///
+    /// Waiting requires an async runtime to be active when the track reaches the head:
+    /// otherwise, it starts immediately.
+    ///
+    /// Returns [`TrackError::NotConnected`] if `handler` has no active connection, unless
+    /// [buffering while disconnected] is enabled.
+    ///
+    /// [`TrackError::NotConnected`]: TrackError::NotConnected
+    /// [buffering while disconnected]: TrackQueue::set_buffer_while_disconnected
+    pub fn add_scheduled(
+        &self,
+        source: Input,
+        handler: &mut Driver,
+        not_before: Instant,
+    ) -> TrackResult<TrackHandle> {
+        let (audio, handle) = tracks::create_player(source);
+        let opts = AddOptions {
+            not_before: Some(not_before),
+            ..Default::default()
+        };
+        self.admit(audio, handler, opts)?;
+
+        Ok(handle)
+    }
+
+    /// Adds an audio source to the queue, which will be stopped once it has played for
+    /// `max_duration` (if given), regardless of the length of its input.
+    ///
+    /// The queue then moves on to the next track, exactly as though this track had
+    /// ended naturally. Time is counted only while the track plays, and includes any
+    /// loops. If the track ends before this point, the limit has no effect.
+    ///
+    /// Returns [`TrackError::NotConnected`] if `handler` has no active connection, unless
+    /// [buffering while disconnected] is enabled.
+    ///
+    /// [`TrackError::NotConnected`]: TrackError::NotConnected
+    /// [buffering while disconnected]: TrackQueue::set_buffer_while_disconnected
+    pub fn add_with_max_duration(
+        &self,
+        source: Input,
+        handler: &mut Driver,
+        max_duration: Option<Duration>,
+    ) -> TrackResult<TrackHandle> {
+        let (mut audio, handle) = tracks::create_player(source);
+        if let Some(max_duration) = max_duration {
+            self.limit_duration(&mut audio, max_duration);
+        }
+        self.admit(audio, handler, AddOptions::default())?;
+
+        Ok(handle)
+    }
+
+    /// Adds only the section of an audio source between `start` and `end` to the queue,
+    /// to be played in the channel managed by `handler`.
+    ///
+    /// The track is seeked to `start` once it reaches the driver, and stopped once its
+    /// position reaches `end`: the queue then moves on as though the track had ended naturally.
+    /// The track's position is checked as it plays, so seeking within the clip (or
+    /// before its start) is honoured. Clips which reach the end of their input simply
+    /// end there.
+    ///
+    /// Returns [`TrackError::InvalidClip`] unless `start < end`, and `end` is at most
+    /// the track's duration (if known). Returns [`TrackError::SeekUnsupported`] if `start`
+    /// is non-zero and the input cannot seek, and [`TrackError::NotConnected`] if `handler`
+    /// has no active connection, unless [buffering while disconnected] is enabled.
+    ///
+    /// [`TrackError::InvalidClip`]: TrackError::InvalidClip
+    /// [`TrackError::SeekUnsupported`]: TrackError::SeekUnsupported
+    /// [`TrackError::NotConnected`]: TrackError::NotConnected
+    /// [buffering while disconnected]: TrackQueue::set_buffer_while_disconnected
+    pub fn add_clip(
+        &self,
+        source: Input,
+        handler: &mut Driver,
+        start: Duration,
+        end: Duration,
+    ) -> TrackResult<TrackHandle> {
+        let (mut audio, handle) = tracks::create_player(source);
+        self.clip(&mut audio, start, end)?;
+        self.admit(audio, handler, AddOptions::default())?;
+
+        Ok(handle)
+    }
+
+    /// Restricts `track` to the section between `start` and `end`, for [`add_clip`].
+    ///
+    /// [`add_clip`]: TrackQueue::add_clip
+    fn clip(&self, track: &mut Track, start: Duration, end: Duration) -> TrackResult<()> {
+        let too_long = matches!(track.source.metadata.duration, Some(length) if end > length);
+        if start >= end || too_long {
+            return Err(TrackError::InvalidClip);
+        }
+
+        // Lazy sources can only seek once the driver has given them a runtime,
+        // so this is carried out by the mixer, before the track produces any audio.
+        if start > Duration::default() {
+            if !track.source.is_seekable() {
+                return Err(TrackError::SeekUnsupported);
+            }
+
+            // Only fails if the track's command receiver is gone, which `track` holds.
+            let _ = track.handle.seek_time(start);
+        }
+
+        let remote_lock = self.inner.clone();
+
+        // Queued tracks only play once they reach the head, and will be at `start`.
+        let now = track.play_time;
+        add_track_events(
+            track,
+            vec![EventData::new(
+                Event::Delayed(end - start),
+                ClipLimiter { remote_lock, end },
+            )],
+            now,
+        );
+
+        Ok(())
+    }
+
+    /// Stops `track` once it has played for `max_duration`, if it is still queued.
+    fn limit_duration(&self, track: &mut Track, max_duration: Duration) {
+        let remote_lock = self.inner.clone();
+
+        // Queued tracks only accumulate play time once they reach the head, so
+        // this is equivalent to scheduling the limit when that happens.
+        let now = track.play_time;
+        add_track_events(
+            track,
+            vec![EventData::new(
+                Event::Delayed(max_duration),
+                DurationLimiter { remote_lock },
+            )],
+            now,
+        );
+    }
+
+    /// Refuses new tracks whose source fails the queue's filter, if any.
+    ///
+    /// The filter is run without holding the queue's lock, in case it inspects the queue.
+    fn check_source(&self, source: &Input) -> TrackResult<()> {
+        let filter = self.inner.lock().source_filter.clone();
+
+        match filter {
+            Some(SourceFilter(filter)) => filter(source),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that `track` may join the queue, then adds it to the queue and `handler`.
+    ///
+    /// Every add method taking a caller's track must go through here. Only the source
+    /// filter is run beforehand, as it looks at the track alone and may inspect the queue.
+    /// All other checks, room in the mixer, and the insertion are then made under a
+    /// single lock, so that concurrent adds cannot each pass a check meant for one.
+    fn admit(&self, mut track: Track, handler: &mut Driver, opts: AddOptions) -> TrackResult<()> {
+        self.check_source(&track.source)?;
+
+        let mut inner = self.inner.lock();
+        inner.check_connected(handler)?;
+        inner.check_unique(track.uuid)?;
+        handler.reserve_track_room(1)?;
+
+        if let Some(requester) = track.requester {
+            if let Err(e) = inner.take_enqueue_slot(requester, Instant::now()) {
+                handler.release_track_room(1);
+                return Err(e);
+            }
+        }
+
+        self.insert(&mut inner, &mut track, opts);
+        drop(inner);
+
+        handler.play_reserved(track);
+
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn add_raw(&self, track: &mut Track) {
+        self.add_raw_at(track, AddOptions::default());
+    }
+
+    /// Registers a track with the queue, without any checks.
+    fn add_raw_at(&self, track: &mut Track, opts: AddOptions) {
+        let mut inner = self.inner.lock();
+
+        self.insert(&mut inner, track, opts);
+    }
+
+    /// Places a track in the queue, whose lock `inner` is held, as described by `opts`.
+    ///
+    /// The entry's requester is taken from the track before it is inserted, so that the
+    /// track is never visible to other queue users (or its own end event) without it.
+    /// `unpaused` tracks are played as soon as they arrive, wherever they are placed.
+    fn insert(&self, inner: &mut TrackQueueCore, track: &mut Track, opts: AddOptions) {
+        info!(label = ?inner.label, "Track added to queue.");
+
+        let track_handle = track.handle.clone();
+
+        if let Some(volume) = inner.volume {
+            track.set_volume(volume);
+        }
+
+        if opts.unpaused {
+            track.play();
+        } else if !inner.tracks.is_empty() || !inner.autostart {
+            track.pause();
+        } else if let Some(at) = opts.not_before.filter(|at| *at > Instant::now()) {
+            if schedule_start(self.inner.clone(), track_handle.uuid(), at) {
+                track.pause();
+            }
+        }
+
+        self.attach_events(track);
+
+        let uuid = track_handle.uuid();
+        let len = inner.tracks.len();
+        let index = if opts.next {
+            1.min(len)
+        } else if inner.shuffle_on_add && len > 1 {
+            inner.rng.gen_range(1..=len)
+        } else {
+            len
+        };
+        let mut queued = Queued::from_handle(track_handle);
+        queued.source = RetainedSource::of(&track.source);
+        queued.not_before = opts.not_before;
+        queued.requester = track.requester;
+        queued.preparing = track.playing == PlayMode::Play;
+        queued.mode = track.playing;
+        inner.tracks.insert(index, queued);
+
+        // A new entry at the front of the queue may now be a preload target.
+        if opts.next {
+            inner.refresh_preload();
+        }
+
+        inner.notify(QueueUpdate::Added { uuid, index });
+
+        if index == 0 {
+            inner.notify_head_change(None);
+        }
+    }
+
+    /// Registers the event handlers which advance this queue and preload its entries.
+    fn attach_events(&self, track: &mut Track) {
+        let mut events = vec![
+            EventData::new(
+                Event::Track(TrackEvent::End),
+                QueueHandler {
+                    remote_lock: self.inner.clone(),
+                },
+            ),
+            EventData::new(
+                Event::Track(TrackEvent::Loop),
+                LoopCounter {
+                    remote_lock: self.inner.clone(),
+                },
+            ),
+            EventData::new(
+                Event::Track(TrackEvent::Started),
+                TransitionTimer {
+                    remote_lock: self.inner.clone(),
+                },
+            ),
+            EventData::new(
+                Event::Track(TrackEvent::Ready),
+                ReadyNotifier {
+                    remote_lock: self.inner.clone(),
+                },
+            ),
+            EventData::new(
+                Event::Track(TrackEvent::Play),
+                PlayModeNotifier {
+                    remote_lock: self.inner.clone(),
+                },
+            ),
+            EventData::new(
+                Event::Track(TrackEvent::Pause),
+                PlayModeNotifier {
+                    remote_lock: self.inner.clone(),
+                },
+            ),
+            EventData::new(
+                Event::Track(TrackEvent::End),
+                DiscardNotifier {
+                    remote_lock: self.inner.clone(),
+                    uuid: track.uuid,
+                    ended: AtomicBool::new(false),
+                },
+            ),
+        ];
+
+        // Attempts to start loading the next track before this one ends.
+        // Idea is to provide as close to gapless playback as possible,
+        // while minimising memory use.
+        if let Some(time) = track.source.metadata.duration {
+            let preload_time = time.checked_sub(Duration::from_secs(5)).unwrap_or_default();
+
+            events.push(EventData::new(
+                Event::Delayed(preload_time),
+                SongPreloader {
+                    remote_lock: self.inner.clone(),
+                },
+            ));
+        }
+
+        let now = track.position;
+        add_track_events(track, events, now);
+    }
+
+    /// Replaces the entire contents of the queue with `tracks`, to be played in the
+    /// channel managed by `handler`.
+    ///
+    /// All existing tracks are stopped (including any which are [pinned]), and the first
+    /// new track begins playing, unless [autostart] is disabled. This happens under a
+    /// single lock, so no other user of the queue can observe it while empty. Subscribers
+    /// from [`watch`] receive [`QueueUpdate::Removed`] and [`QueueUpdate::Added`] for each
+    /// changed entry, rather than [`QueueUpdate::Cleared`].
+    ///
+    /// Returns handles to the new tracks, in queue order, or [`TrackError::NotConnected`]
+    /// if `handler` has no active connection and [buffering while disconnected] is disabled.
+    /// Returns [`TrackError::DuplicateUuid`] if any two tracks in `tracks` share a UUID,
+    /// or if one shares a UUID with a track being replaced, whose end would otherwise be
+    /// mistaken for its own. Nothing is stopped if any error is returned.
+    ///
+    /// [pinned]: Queued::set_pinned
+    /// [autostart]: TrackQueue::set_autostart
+    /// [`TrackError::NotConnected`]: TrackError::NotConnected
+    /// [`TrackError::DuplicateUuid`]: TrackError::DuplicateUuid
+    /// [buffering while disconnected]: TrackQueue::set_buffer_while_disconnected
+    /// [`watch`]: TrackQueue::watch
+    /// [`QueueUpdate::Removed`]: QueueUpdate::Removed
+    /// [`QueueUpdate::Added`]: QueueUpdate::Added
+    /// [`QueueUpdate::Cleared`]: QueueUpdate::Cleared
+    pub fn replace_all(
+        &self,
+        tracks: Vec<Track>,
+        handler: &mut Driver,
+    ) -> TrackResult<Vec<TrackHandle>> {
+        for track in &tracks {
+            self.check_source(&track.source)?;
+        }
+
+        let mut inner = self.inner.lock();
+        inner.check_connected(handler)?;
+
+        let mut seen = HashSet::with_capacity(tracks.len());
+        for track in &tracks {
+            inner.check_unique(track.uuid)?;
+            if !seen.insert(track.uuid) {
+                return Err(TrackError::DuplicateUuid);
+            }
+        }
+
+        // The mixer only removes the replaced tracks after the new ones arrive.
+        handler.reserve_track_room(tracks.len())?;
+
+        let before = inner.uuids();
+
+        for track in inner.tracks.drain(..) {
+            // As in `stop`, an error just implies the track is already gone.
+            let _ = track.stop();
+        }
+        inner.preloading_for = None;
+        inner.finished.clear();
+
+        let mut handles = Vec::with_capacity(tracks.len());
+        for (i, mut track) in tracks.into_iter().enumerate() {
+            // As in `add_raw_at`, but with every entry placed in order.
+            if let Some(volume) = inner.volume {
+                track.set_volume(volume);
+            }
+
+            if i > 0 || !inner.autostart {
+                track.pause();
+            }
+
+            self.attach_events(&mut track);
+
+            handles.push(track.handle.clone());
+            let mut queued = Queued::from_handle(track.handle.clone());
+            queued.source = RetainedSource::of(&track.source);
+            queued.preparing = track.playing == PlayMode::Play;
+            queued.mode = track.playing;
+            inner.tracks.push_back(queued);
+            handler.play_reserved(track);
+        }
+
+        inner.notify_changes(&before);
+
+        Ok(handles)
+    }
+
+    /// Moves every track from this queue to the back of `other`, to be played in the
+    /// channel managed by `handler`.
+    ///
+    /// Each track keeps its volume, loop state, [pinned] status and position. Rather than
+    /// seeking, each track's live [`Input`] is taken from its old driver, so that both
+    /// seekable and live sources continue from where they left off. Event handlers added
+    /// to the old tracks are not carried over.
+    ///
+    /// This queue is emptied under one lock, so it can never be observed part-way
+    /// through a transfer. Tracks then join `other` one at a time, as each is handed
+    /// back by the old driver's mixer: any which end before this happens are dropped.
+    /// The old mixer only does this while its driver is connected. Any tracks it has not
+    /// handed back within 500ms are returned to the back of this queue, untouched, once
+    /// the rest have moved.
+    ///
+    /// Returns handles to the moved tracks, in queue order, or [`TrackError::NotConnected`]
+    /// if `handler` has no active connection and `other` is not [buffering while
+    /// disconnected]. In the latter case, no tracks are moved.
+    ///
+    /// [pinned]: Queued::set_pinned
+    /// [`Input`]: Input
+    /// [`TrackError::NotConnected`]: TrackError::NotConnected
+    /// [buffering while disconnected]: TrackQueue::set_buffer_while_disconnected
+    pub async fn transfer_to(
+        &self,
+        other: &TrackQueue,
+        handler: &mut Driver,
+    ) -> TrackResult<Vec<TrackHandle>> {
+        other.inner.lock().check_connected(handler)?;
+
+        let entries: Vec<Queued> = {
+            let mut inner = self.inner.lock();
+            handler.reserve_track_room(inner.tracks.len())?;
+
+            let before = inner.uuids();
+            let entries = inner.tracks.drain(..).collect();
+            inner.preloading_for = None;
+            inner.notify_changes(&before);
+
+            entries
+        };
+
+        let reserved = entries.len();
+        let mut pending = Vec::with_capacity(entries.len());
+
+        for queued in entries {
+            let slot = Arc::new(Mutex::new(TransferSlot::Waiting));
+            let (tx, rx) = flume::bounded(1);
+
+            let remote_slot = slot.clone();
+            let requested = queued.action(move |track| {
+                let mut slot = remote_slot.lock();
+                if !matches!(*slot, TransferSlot::Waiting) {
+                    return;
+                }
+
+                let blank = Input::new(
+                    true,
+                    Vec::<u8>::new().into(),
+                    Codec::FloatPcm,
+                    Container::Raw,
+                    None,
+                );
+
+                *slot = TransferSlot::Moved(Box::new(MovedTrack {
+                    source: std::mem::replace(&mut track.source, blank),
+                    volume: track.volume,
+                    loops: track.loops,
+                    position: track.position,
+                }));
+                let _ = tx.send(());
+
+                track.stop();
+            });
+
+            // Tracks which have already ended cannot be moved.
+            if requested.is_ok() {
+                pending.push((queued, slot, rx));
+            }
+        }
+
+        let deadline = Instant::now() + TRANSFER_TIMEOUT;
+        let mut handles = Vec::with_capacity(pending.len());
+        let mut unanswered = vec![];
+
+        for (queued, slot, rx) in pending {
+            let _ = timeout(
+                deadline.saturating_duration_since(Instant::now()),
+                rx.recv_async(),
+            )
+            .await;
+
+            let moved = {
+                let mut slot = slot.lock();
+                match std::mem::replace(&mut *slot, TransferSlot::Abandoned) {
+                    TransferSlot::Moved(moved) => moved,
+                    _ => {
+                        unanswered.push(queued);
+                        continue;
+                    },
+                }
+            };
+
+            let (mut track, handle) = tracks::create_player(moved.source);
+            track.volume = moved.volume;
+            track.loops = moved.loops;
+            track.position = moved.position;
+
+            other.add_raw(&mut track);
+            if queued.pinned {
+                other.modify_queue(|vq| {
+                    vq.iter_mut()
+                        .filter(|q| q.uuid() == handle.uuid())
+                        .for_each(|q| q.set_pinned(true))
+                });
+            }
+
+            handler.play_reserved(track);
+            handles.push(handle);
+        }
+
+        // Only the tracks handed back by the old driver were sent on.
+        handler.release_track_room(reserved - handles.len());
+
+        if !unanswered.is_empty() {
+            let mut inner = self.inner.lock();
+
+            let before = inner.uuids();
+            inner.tracks.extend(unanswered);
+            inner.notify_changes(&before);
+        }
+
+        Ok(handles)
+    }
+
+    /// Returns a handle to the currently playing track.
+    pub fn current(&self) -> Option<TrackHandle> {
+        let inner = self.inner.lock();
+
+        inner.tracks.front().map(|h| h.handle())
+    }
+
+    /// Returns the metadata of the currently playing track.
+    ///
+    /// This is the most recent result of [`refresh_current_metadata`] for this track,
+    /// if any, or else the metadata given by its [`TrackHandle`].
+    ///
+    /// [`refresh_current_metadata`]: TrackQueue::refresh_current_metadata
+    /// [`TrackHandle`]: TrackHandle
+    pub fn current_metadata(&self) -> Option<Metadata> {
+        let inner = self.inner.lock();
+
+        inner
+            .tracks
+            .front()
+            .map(|q| q.refreshed.as_ref().unwrap_or_else(|| q.metadata()).clone())
+    }
+
+    /// Fetches new metadata for the currently playing track, e.g., to update the
+    /// title shown for a live radio stream.
+    ///
+    /// Tracks created with a `source_url` (such as those from [`ytdl`] or
+    /// [`Restartable::ytdl`]) are queried again via youtube-dl. The result replaces
+    /// the value returned by [`current_metadata`], unless the queue has moved on to
+    /// another track by the time the query completes.
+    ///
+    /// For other tracks, finished tracks, or if the query fails, this returns the
+    /// cached value from [`current_metadata`] instead. Returns `None` if the queue
+    /// is empty.
+    ///
+    /// [`ytdl`]: crate::input::ytdl
+    /// [`Restartable::ytdl`]: crate::input::Restartable::ytdl
+    /// [`current_metadata`]: TrackQueue::current_metadata
+    pub fn refresh_current_metadata(&self) -> impl Future<Output = Option<Metadata>> {
+        let remote_lock = self.inner.clone();
+        let current = self.with_current(|handle| handle.map(|h| (h.uuid(), h.is_finished())));
+        let cached = self.current_metadata();
+
+        async move {
+            let (uuid, finished) = current?;
+            let cached = cached?;
+
+            let url = match &cached.source_url {
+                Some(url) if !finished => url,
+                _ => return Some(cached),
+            };
+
+            let fresh = match _ytdl_metadata(url).await {
+                Ok(fresh) => fresh,
+                Err(e) => {
+                    warn!("Failed to refresh metadata for track {}: {:?}", uuid, e);
+                    return Some(cached);
+                },
+            };
+
+            remote_lock.lock().cache_refreshed(uuid, &fresh);
+
+            Some(fresh)
+        }
+    }
+
+    /// Lends the currently playing track's handle to `f`, without cloning it.
+    ///
+    /// This suits frequent, cheap reads (e.g., [`TrackHandle::uuid`]) where the
+    /// cost of [`current`] adds up.
+    ///
+    /// The queue remains locked while `f` runs. `f` must not call any other
+    /// methods on this queue, or it will deadlock.
+    ///
+    /// [`TrackHandle::uuid`]: TrackHandle::uuid
+    /// [`current`]: TrackQueue::current
+    pub fn with_current<R>(&self, f: impl FnOnce(Option<&TrackHandle>) -> R) -> R {
+        let inner = self.inner.lock();
+
+        f(inner.tracks.front().map(|q| &q.handle))
+    }
+
+    /// Returns how much of the currently playing track remains.
+    ///
+    /// This is the track's reported duration (from its [`Metadata`]) minus its current
+    /// position. Returns `None` if the queue is empty, the head's duration is unknown,
+    /// or the head has already ended.
+    ///
+    /// [`Metadata`]: crate::input::Metadata
+    pub async fn time_remaining(&self) -> Option<Duration> {
+        let head = self.current()?;
+        let duration = head.metadata().duration?;
+        let state = head.get_info().await.ok()?;
+
+        Some(duration.checked_sub(state.position).unwrap_or_default())
+    }
+
+    /// Returns whether the currently playing track can be seeked, without attempting a seek.
+    ///
+    /// This is `false` exactly when seeks on this track fail with
+    /// [`TrackError::SeekUnsupported`]. Returns `None` if the queue is empty,
+    /// or the head has already ended.
+    ///
+    /// [`TrackError::SeekUnsupported`]: TrackError::SeekUnsupported
+    pub async fn current_seekable(&self) -> Option<bool> {
+        let head = self.current()?;
+        head.get_info().await.ok()?;
+
+        Some(head.is_seekable())
+    }
+
+    /// Attempts to remove a track from the specified index.
+    ///
+    /// The returned entry can be readded to *this* queue via [`modify_queue`].
+    /// Returns `None` both if `index` is out of range and if the track at `index`
+    /// is [pinned]: use [`try_dequeue`] to tell these apart.
+    ///
+    /// [`modify_queue`]: TrackQueue::modify_queue
+    /// [pinned]: Queued::set_pinned
+    /// [`try_dequeue`]: TrackQueue::try_dequeue
+    pub fn dequeue(&self, index: usize) -> Option<Queued> {
+        self.try_dequeue(index).ok().flatten()
+    }
+
+    /// Attempts to remove a track from the specified index, as in [`dequeue`].
+    ///
+    /// Returns `Ok(None)` if `index` is out of range, or [`TrackError::Pinned`] if
+    /// the track at `index` is [pinned].
+    ///
+    /// [`dequeue`]: TrackQueue::dequeue
+    /// [`TrackError::Pinned`]: TrackError::Pinned
+    /// [pinned]: Queued::set_pinned
+    pub fn try_dequeue(&self, index: usize) -> TrackResult<Option<Queued>> {
+        self.modify_queue(|vq| match vq.get(index) {
+            Some(queued) if queued.pinned => Err(TrackError::Pinned),
+            _ => Ok(vq.remove(index)),
+        })
+    }
+
+    /// Returns the index of the track with the given `uuid`, if it is queued.
+    pub fn position_of(&self, uuid: Uuid) -> Option<usize> {
+        let inner = self.inner.lock();
+
+        inner.tracks.iter().position(|q| q.uuid() == uuid)
+    }
+
+    /// Attempts to remove the track with the given `uuid`, as in [`try_dequeue`].
+    ///
+    /// Returns `Ok(None)` if no such track is queued, or [`TrackError::Pinned`]
+    /// if the track is [pinned].
+    ///
+    /// [`try_dequeue`]: TrackQueue::try_dequeue
+    /// [`TrackError::Pinned`]: TrackError::Pinned
+    /// [pinned]: Queued::set_pinned
+    pub fn dequeue_uuid(&self, uuid: Uuid) -> TrackResult<Option<Queued>> {
+        self.modify_queue(|vq| match vq.iter().position(|q| q.uuid() == uuid) {
+            Some(index) if vq[index].pinned => Err(TrackError::Pinned),
+            Some(index) => Ok(vq.remove(index)),
+            None => Ok(None),
+        })
+    }
+
+    /// Reverses the order of all tracks after the current head of the queue.
+    ///
+    /// The playing track is unaffected. If preloading has already begun for the
+    /// head, then the new [preload targets] are readied: as with [`modify_queue`],
+    /// tracks which were readied earlier are left as they are.
+    ///
+    /// [preload targets]: TrackQueue::preload_targets
+    /// [`modify_queue`]: TrackQueue::modify_queue
+    pub fn reverse(&self) {
+        self.modify_queue(|vq| {
+            if vq.len() > 2 {
+                vq.make_contiguous()[1..].reverse();
+            }
+        });
+    }
+
+    /// Removes all tracks after the head which have already finished or failed,
+    /// returning the number removed.
+    ///
+    /// Tracks can end out of band if, e.g., they were stopped via their handles
+    /// or hit an error while preloading. Tracks which have not yet been handed to
+    /// a driver's mixer, or which are still preparing their inputs, are kept. This
+    /// checks every handle while holding the queue's lock, but never waits on the
+    /// driver. [Pinned] tracks are kept, and are discarded once they reach the head.
+    ///
+    /// [Pinned]: Queued::set_pinned
+    pub fn prune(&self) -> usize {
+        self.modify_queue(|vq| {
+            let before = vq.len();
+            let mut index = 0;
+
+            vq.retain(|queued| {
+                index += 1;
+                index == 1 || queued.pinned || !queued.is_finished()
+            });
+
+            before - vq.len()
+        })
+    }
+
+    /// Removes and stops each track whose source is the same as the track directly
+    /// before it, returning the number removed.
+    ///
+    /// Tracks share a source if both have the same [`Metadata::source_url`]: tracks
+    /// without a known URL are never considered duplicates. The head of the queue, and
+    /// any [pinned] tracks, are never removed. Duplicates which are not adjacent are
+    /// intentionally kept.
+    ///
+    /// [`Metadata::source_url`]: crate::input::Metadata::source_url
+    /// [pinned]: Queued::set_pinned
+    pub fn dedup_consecutive(&self) -> usize {
+        self.modify_queue(|vq| {
+            let mut removed = 0;
+            let mut i = 1;
+
+            while i < vq.len() {
+                let prev = &vq[i - 1].metadata().source_url;
+                let curr = &vq[i];
+
+                if !curr.pinned && prev.is_some() && *prev == curr.metadata().source_url {
+                    if let Some(dupe) = vq.remove(i) {
+                        let _ = dupe.stop();
+                    }
+                    removed += 1;
+                } else {
+                    i += 1;
+                }
+            }
+
+            removed
+        })
+    }
+
+    /// Returns the number of tracks currently in the queue.
+    pub fn len(&self) -> usize {
+        let inner = self.inner.lock();
+
+        inner.tracks.len()
+    }
+
+    /// Returns whether there are no tracks currently in the queue.
+    pub fn is_empty(&self) -> bool {
+        let inner = self.inner.lock();
+
+        inner.tracks.is_empty()
+    }
+
+    /// Returns the combined duration of all queued tracks whose duration is known.
+    ///
+    /// Durations are read from each track's [`Metadata`], and tracks without one
+    /// (e.g., live streams) are skipped, so this is a lower bound on the time needed
+    /// to play the whole queue. This includes the full length of the current head,
+    /// regardless of how much has already played.
+    ///
+    /// [`Metadata`]: crate::input::Metadata
+    pub fn known_duration(&self) -> Duration {
+        let inner = self.inner.lock();
+
+        inner
+            .tracks
+            .iter()
+            .filter_map(|q| q.metadata().duration)
+            .sum()
+    }
+
+    /// Returns a summary of the queue's contents and settings.
+    ///
+    /// All values are read under a single lock, so are consistent with one another,
+    /// unlike those from separate calls to [`len`], [`known_duration`], and so on.
+    ///
+    /// [`len`]: TrackQueue::len
+    /// [`known_duration`]: TrackQueue::known_duration
+    pub fn stats(&self) -> QueueStats {
+        let inner = self.inner.lock();
+        let len = inner.tracks.len();
+
+        QueueStats {
+            len,
+            upcoming: len.saturating_sub(1),
+            known_duration: inner
+                .tracks
+                .iter()
+                .filter_map(|q| q.metadata().duration)
+                .sum(),
+            loop_count: inner.loop_count,
+            max_loops: inner.max_loops,
+            volume: inner.volume,
+            failure_count: inner.failure_count,
+            head: inner.tracks.front().map(Queued::ready_state),
+            head_mode: inner.tracks.front().map(|q| q.mode),
+        }
+    }
+
+    /// Sets whether newly added tracks are placed at a random position in the queue.
+    ///
+    /// When enabled, tracks added via [`add`] or [`add_source`] are inserted at a random
+    /// point among the pending tracks, keeping the queue shuffled without reordering
+    /// existing entries. The head of the queue is never displaced. Methods with explicit
+    /// positions, such as [`add_next`] and [`replace_all`], are unaffected.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`add`]: TrackQueue::add
+    /// [`add_source`]: TrackQueue::add_source
+    /// [`add_next`]: TrackQueue::add_next
+    /// [`replace_all`]: TrackQueue::replace_all
+    pub fn set_shuffle_on_add(&self, shuffle: bool) {
+        let mut inner = self.inner.lock();
+
+        inner.shuffle_on_add = shuffle;
+    }
+
+    /// Returns whether newly added tracks are placed at a random position in the queue.
+    ///
+    /// See [`set_shuffle_on_add`] for more details.
+    ///
+    /// [`set_shuffle_on_add`]: TrackQueue::set_shuffle_on_add
+    pub fn shuffle_on_add(&self) -> bool {
+        let inner = self.inner.lock();
+
+        inner.shuffle_on_add
+    }
+
+    /// Sets whether the queue shares out turns between requesters, rather than
+    /// playing tracks strictly in order.
+    ///
+    /// When enabled, each time the head of the queue ends, the next track is taken
+    /// from whichever [requester] has waited longest since their last track finished
+    /// (with requesters yet to be served going first). Each requester's own tracks
+    /// still play in queue order, and tracks without a requester are treated as
+    /// belonging to one shared requester. Moving a track to the head is reported to
+    /// [`watch`] subscribers as [`QueueUpdate::Reordered`].
+    ///
+    /// This may start a track other than the one [preloaded] for the new head.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [requester]: Queued::set_requester
+    /// [`watch`]: TrackQueue::watch
+    /// [`QueueUpdate::Reordered`]: QueueUpdate::Reordered
+    /// [preloaded]: TrackQueue::set_preload_depth
+    pub fn set_fair_mode(&self, fair: bool) {
+        let mut inner = self.inner.lock();
+
+        inner.fair_mode = fair;
+    }
+
+    /// Limits each requester to adding at most `per_requester` tracks in any period
+    /// of length `window`, e.g., to prevent spam.
+    ///
+    /// Every track added with a [requester] counts towards this, whichever add method
+    /// is used. Further tracks are refused with [`TrackError::Throttled`], which reports
+    /// how long the requester must wait. Tracks without a requester are not counted,
+    /// nor are those which are recreated or moved by the queue itself.
+    /// A `per_requester` of `0` removes the limit.
+    ///
+    /// Defaults to no limit.
+    ///
+    /// [requester]: Track::set_requester
+    /// [`TrackError::Throttled`]: TrackError::Throttled
+    pub fn set_enqueue_rate_limit(&self, per_requester: u32, window: Duration) {
+        let mut inner = self.inner.lock();
+
+        inner.enqueue_limit = if per_requester == 0 {
+            None
+        } else {
+            Some((per_requester, window))
+        };
+        inner.enqueue_times.clear();
+    }
+
+    /// Returns whether the queue shares out turns between requesters.
+    ///
+    /// See [`set_fair_mode`] for more details.
+    ///
+    /// [`set_fair_mode`]: TrackQueue::set_fair_mode
+    pub fn fair_mode(&self) -> bool {
+        let inner = self.inner.lock();
+
+        inner.fair_mode
+    }
+
+    /// Sets whether a track added to an empty queue (e.g., after [`stop`]) begins
+    /// playing immediately.
+    ///
+    /// When disabled, newly added tracks are held paused at the head of the queue
+    /// until [`resume`] is called, so that a new playlist can be built up before
+    /// it starts. Once started, the queue moves on from each ended track as usual.
+    ///
+    /// Defaults to `true`.
+    ///
+    /// [`stop`]: TrackQueue::stop
+    /// [`resume`]: TrackQueue::resume
+    pub fn set_autostart(&self, autostart: bool) {
+        let mut inner = self.inner.lock();
+
+        inner.autostart = autostart;
+    }
+
+    /// Returns whether a track added to an empty queue begins playing immediately.
+    ///
+    /// See [`set_autostart`] for more details.
+    ///
+    /// [`set_autostart`]: TrackQueue::set_autostart
+    pub fn autostart(&self) -> bool {
+        let inner = self.inner.lock();
+
+        inner.autostart
+    }
+
+    /// Sets whether a head track which can no longer be played is skipped, rather
+    /// than leaving the queue stalled.
+    ///
+    /// The queue normally moves on when its head ends, but a head whose [`Track`]
+    /// was discarded before it could play (e.g., because it was never handed to
+    /// a driver) never ends, and holds back every track behind it. When enabled,
+    /// a head is checked as soon as its track is dropped without ending: a
+    /// discarded head is removed, recorded as a [failure], and the next playable
+    /// track is started in its place. Checks require an async runtime.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`Track`]: Track
+    /// [failure]: TrackQueue::drain_failures
+    pub fn set_skip_failed_head(&self, skip: bool) {
+        let mut inner = self.inner.lock();
+
+        inner.skip_failed_head = skip;
+
+        // The head may have been discarded before this was enabled.
+        if skip {
+            inner.skip_failed_head(&self.inner);
+        }
+    }
+
+    /// Returns whether a head track which can no longer be played is skipped.
+    ///
+    /// See [`set_skip_failed_head`] for more details.
+    ///
+    /// [`set_skip_failed_head`]: TrackQueue::set_skip_failed_head
+    pub fn skip_failed_head(&self) -> bool {
+        let inner = self.inner.lock();
+
+        inner.skip_failed_head
+    }
+
+    /// Returns whether the current track is set to loop again once it ends.
+    ///
+    /// Returns `false` if the queue is empty, or the head has already ended.
+    pub async fn is_looping(&self) -> bool {
+        let head = match self.current() {
+            Some(head) => head,
+            None => return false,
+        };
+
+        match head.get_info().await {
+            Ok(state) => state.loops != LoopState::Finite(0),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the number of times the current track has looped.
+    ///
+    /// This is reset whenever the head of the queue changes.
+    pub fn loop_count(&self) -> usize {
+        let inner = self.inner.lock();
+
+        inner.loop_count
+    }
+
+    /// Sets the maximum number of times the current track may loop.
+    ///
+    /// Once the head has looped `max` times, its looping is disabled (as though by
+    /// [`TrackHandle::disable_loop`]) so that the queue moves on after its next play.
+    /// The count restarts for each new head. `None` places no limit on looping.
+    ///
+    /// Defaults to `None`.
+    ///
+    /// [`TrackHandle::disable_loop`]: TrackHandle::disable_loop
+    pub fn set_max_loops(&self, max: Option<usize>) {
+        let mut inner = self.inner.lock();
+
+        inner.max_loops = max;
+    }
+
+    /// Sets a check which the source of every track added to the queue must pass.
+    ///
+    /// This runs before each track is enqueued by [`add`], [`add_source`] and their
+    /// variants (including `Driver::enqueue` for the driver's built-in queue), as well
+    /// as on each track given to [`replace_all`]. Returning an error (such as
+    /// [`TrackError::Rejected`], with a reason for the caller) refuses the track, and
+    /// passes the error on to the caller without modifying the queue. This allows
+    /// policies (e.g., blocking certain hosts, or limiting track lengths) to be
+    /// enforced in one place.
+    ///
+    /// Sources have already been created when this runs, so any [`Metadata`] fetched
+    /// while doing so (e.g., a [`ytdl`] source's URL and duration) can be inspected.
+    /// Tracks moved in by [`append_queue`] or [`transfer_to`] were already accepted by
+    /// a queue, so are not checked again. Tracks given to [`adopt`] are already playing,
+    /// and their sources cannot be inspected, so they are not checked either.
+    ///
+    /// [`add`]: TrackQueue::add
+    /// [`add_source`]: TrackQueue::add_source
+    /// [`replace_all`]: TrackQueue::replace_all
+    /// [`TrackError::Rejected`]: TrackError::Rejected
+    /// [`Metadata`]: crate::input::Metadata
+    /// [`ytdl`]: crate::input::ytdl
+    /// [`append_queue`]: TrackQueue::append_queue
+    /// [`transfer_to`]: TrackQueue::transfer_to
+    /// [`adopt`]: TrackQueue::adopt
+    pub fn set_source_filter<F>(&self, filter: F)
+    where
+        F: Fn(&Input) -> TrackResult<()> + Send + Sync + 'static,
+    {
+        let mut inner = self.inner.lock();
+
+        inner.source_filter = Some(SourceFilter(Arc::new(filter)));
+    }
+
+    /// Removes the check set by [`set_source_filter`], if any.
+    ///
+    /// [`set_source_filter`]: TrackQueue::set_source_filter
+    pub fn clear_source_filter(&self) {
+        let mut inner = self.inner.lock();
+
+        inner.source_filter = None;
+    }
+
+    /// Sets what the queue should do once its last track ends.
+    ///
+    /// This is not triggered by [`stop`], nor by removing the remaining tracks via
+    /// [`modify_queue`]. Defaults to [`OnComplete::Stop`].
+    ///
+    /// [`stop`]: TrackQueue::stop
+    /// [`modify_queue`]: TrackQueue::modify_queue
+    /// [`OnComplete::Stop`]: OnComplete::Stop
+    pub fn set_on_complete(&self, on_complete: OnComplete) {
+        let mut inner = self.inner.lock();
+
+        inner.on_complete = on_complete;
+        inner.finished.clear();
+    }
+
+    /// Stores an opaque token identifying this queue's "now playing" display, such as
+    /// the ID of a message to edit whenever the current track changes.
+    ///
+    /// The queue never interprets this value: it is included in every
+    /// [`QueueUpdate::HeadChanged`] sent to subscribers of [`watch`].
+    ///
+    /// [`QueueUpdate::HeadChanged`]: QueueUpdate::HeadChanged
+    /// [`watch`]: TrackQueue::watch
+    pub fn set_now_playing(&self, token: Option<u64>) {
+        let mut inner = self.inner.lock();
+
+        inner.now_playing = token;
+    }
+
+    /// Returns the token set by [`set_now_playing`], if any.
+    ///
+    /// [`set_now_playing`]: TrackQueue::set_now_playing
+    pub fn now_playing(&self) -> Option<u64> {
+        let inner = self.inner.lock();
+
+        inner.now_playing
+    }
+
+    /// Sets a human-readable label for this queue, such as a playlist name.
+    ///
+    /// The label is attached to this queue's log messages, to tell apart many queues
+    /// managed by one process.
+    pub fn set_label(&self, label: String) {
+        let mut inner = self.inner.lock();
+
+        inner.label = Some(label);
+    }
+
+    /// Returns the label set by [`set_label`], if any.
+    ///
+    /// [`set_label`]: TrackQueue::set_label
+    pub fn label(&self) -> Option<String> {
+        let inner = self.inner.lock();
+
+        inner.label.clone()
+    }
+
+    /// Mirrors the order and metadata of all queued tracks into `backend`.
+    ///
+    /// `backend` is first brought up to date with every track currently in
+    /// the queue, replacing any entries it held before. This returns any
+    /// previously set backend.
+    ///
+    /// See [`QueueBackend`] for more details.
+    ///
+    /// [`QueueBackend`]: QueueBackend
+    pub fn set_backend(&self, mut backend: Box<dyn QueueBackend>) -> Option<Box<dyn QueueBackend>> {
+        let mut inner = self.inner.lock();
+
+        backend.reorder(&[]);
+        for (index, queued) in inner.tracks.iter().enumerate() {
+            backend.push(index, queued.entry());
+        }
+
+        inner.backend.replace(backend)
+    }
+
+    /// Removes the backend set by [`set_backend`], if any.
+    ///
+    /// [`set_backend`]: TrackQueue::set_backend
+    pub fn take_backend(&self) -> Option<Box<dyn QueueBackend>> {
+        let mut inner = self.inner.lock();
+
+        inner.backend.take()
+    }
+
+    /// Returns the entries held by the backend set by [`set_backend`], if any.
+    ///
+    /// [`set_backend`]: TrackQueue::set_backend
+    pub fn backend_snapshot(&self) -> Option<Vec<QueueEntry>> {
+        let inner = self.inner.lock();
+
+        inner.backend.as_ref().map(|backend| backend.snapshot())
+    }
+
+    /// Sets whether tracks may be added while the target driver has no active connection.
+    ///
+    /// By default, adding a track to a disconnected driver returns
+    /// [`TrackError::NotConnected`], as the track may otherwise never play. Enabling this
+    /// allows tracks to be queued up ahead of (or while awaiting) a connection: they
+    /// will begin playing once the driver connects.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`TrackError::NotConnected`]: TrackError::NotConnected
+    pub fn set_buffer_while_disconnected(&self, buffer: bool) {
+        let mut inner = self.inner.lock();
+
+        inner.buffer_while_disconnected = buffer;
+    }
+
+    /// Replaces the random source used to place shuffled tracks, for reproducible tests.
+    #[cfg(test)]
+    fn seed_shuffle(&self, seed: u64) {
+        let mut inner = self.inner.lock();
+
+        inner.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Returns a cheap hash of the queue's contents, for detecting changes.
+    ///
+    /// This covers the identity and order of all queued tracks, whether each
+    /// is [pinned], the queue's [volume], its [completion policy], [loop limit],
+    /// and how many times the current track has looped. Changes made directly to
+    /// a track's own state through its [`TrackHandle`] are held by the driver
+    /// rather than the queue, and are not included.
+    ///
+    /// Fingerprints are only comparable within a single run of a program.
+    ///
+    /// [pinned]: Queued::set_pinned
+    /// [volume]: TrackQueue::set_volume_percent
+    /// [completion policy]: TrackQueue::set_on_complete
+    /// [loop limit]: TrackQueue::set_max_loops
+    /// [`TrackHandle`]: TrackHandle
+    pub fn fingerprint(&self) -> u64 {
+        let inner = self.inner.lock();
+
+        let mut hasher = DefaultHasher::new();
+        for queued in inner.tracks.iter() {
+            queued.uuid().hash(&mut hasher);
+            queued.pinned.hash(&mut hasher);
+        }
+
+        inner.volume.map(f32::to_bits).hash(&mut hasher);
+        std::mem::discriminant(&inner.on_complete).hash(&mut hasher);
+        inner.max_loops.hash(&mut hasher);
+        inner.loop_count.hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    /// Sets the number of upcoming tracks to ready shortly before the head ends.
+    ///
+    /// Five seconds before the current track finishes, the next `depth` tracks
+    /// (i.e., queue indices `1..=depth`) are sent [`make_playable`]. This is useful
+    /// on unreliable networks, where a lazy source may take several seconds to start.
+    ///
+    /// Each preloaded track holds its live source (e.g., a running `ffmpeg` or
+    /// `youtube-dl` process and its pipe buffers) until it is played or removed, so
+    /// memory use grows with this value. A depth of `0` disables preloading.
+    ///
+    /// Tracks which have already ended when preloaded are removed from the queue,
+    /// and recorded alongside other [failures].
+    ///
+    /// Defaults to `1`.
+    ///
+    /// [`make_playable`]: TrackHandle::make_playable
+    /// [failures]: TrackQueue::drain_failures
+    pub fn set_preload_depth(&self, depth: usize) {
+        let mut inner = self.inner.lock();
+
+        inner.preload_depth = depth;
+        inner.refresh_preload();
+    }
+
+    /// Returns the number of upcoming tracks which will be preloaded.
+    ///
+    /// See [`set_preload_depth`] for more details.
+    ///
+    /// [`set_preload_depth`]: TrackQueue::set_preload_depth
+    pub fn preload_depth(&self) -> usize {
+        let inner = self.inner.lock();
+
+        inner.preload_depth
+    }
+
+    /// Returns handles to the tracks which will be preloaded before the current
+    /// track ends, in queue order.
+    ///
+    /// This does not modify the queue, nor does it trigger any preloading.
+    pub fn preload_targets(&self) -> Vec<TrackHandle> {
+        let inner = self.inner.lock();
+
+        inner.preload_targets().map(|q| q.handle()).collect()
+    }
+
+    /// Returns handles to all queued tracks whose input is in the given `state`,
+    /// in queue order.
+    ///
+    /// States are taken from the queue's record of each track, which may lag
+    /// slightly behind the mixer: see [`Queued::ready_state`] for more details.
+    ///
+    /// [`Queued::ready_state`]: Queued::ready_state
+    pub fn tracks_in_state(&self, state: ReadyState) -> Vec<TrackHandle> {
+        let inner = self.inner.lock();
+
+        inner
+            .tracks
+            .iter()
+            .filter(|q| q.ready_state() == state)
+            .map(|q| q.handle())
+            .collect()
+    }
+
+    /// Returns whether the track promoted by the queue's most recent advance
+    /// had its input ready to play, i.e., whether the handoff was gapless.
+    ///
+    /// A `false` value suggests that [`preload_depth`] or the input's preload
+    /// time should be raised. The queue emptying does not count as a
+    /// transition. Returns `None` if the queue has not yet advanced.
+    ///
+    /// [`preload_depth`]: TrackQueue::set_preload_depth
+    pub fn last_transition_gapless(&self) -> Option<bool> {
+        let inner = self.inner.lock();
+
+        inner.last_transition_gapless
+    }
+
+    /// Returns how long the head of the queue has been waiting for its input
+    /// to become ready, i.e., for its [`TrackEvent::Ready`].
+    ///
+    /// This is measured from when the track reached the head of the queue, and
+    /// is `None` if the queue is empty or the head is already ready to play.
+    /// Sources which take a long time to resolve can be found using this.
+    ///
+    /// [`TrackEvent::Ready`]: TrackEvent::Ready
+    pub fn current_prepare_elapsed(&self) -> Option<Duration> {
+        let inner = self.inner.lock();
+
+        inner
+            .tracks
+            .front()
+            .filter(|q| !q.ready)
+            .and(inner.head_since)
+            .map(|since| since.elapsed())
+    }
+
+    /// Allows modification of the inner queue (i.e., deletion, reordering).
+    ///
+    /// Users must be careful to `stop` removed tracks, so as to prevent
+    /// resource leaks.
+    ///
+    /// [Pinned] tracks cannot be removed here: any which `func` removes are put back
+    /// at their original positions (or the back of the queue, if it is now shorter)
+    /// once it returns. Pinned tracks may still be reordered, or unpinned first.
+    ///
+    /// If the current track is already close enough to its end that preloading
+    /// has begun, then any tracks which have become preload targets are readied
+    /// once `func` returns.
+    ///
+    /// [Pinned]: Queued::set_pinned
+    pub fn modify_queue<F, O>(&self, func: F) -> O
+    where
+        F: FnOnce(&mut VecDeque<Queued>) -> O,
+    {
+        let mut inner = self.inner.lock();
+
+        let previous = inner.tracks.front().map(|q| q.uuid());
+        let before = if inner.watchers.is_empty() && inner.backend.is_none() {
+            None
+        } else {
+            Some(inner.uuids())
+        };
+        let pinned: Vec<_> = inner
+            .tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, q)| q.pinned)
+            .map(|(index, q)| (index, q.duplicate()))
+            .collect();
+
+        let out = func(&mut inner.tracks);
+        inner.restore_pinned(pinned);
+        inner.refresh_preload();
+
+        if let Some(before) = before {
+            inner.notify_changes(&before);
+        } else {
+            inner.notify_head_change(previous);
+        }
+
+        out
+    }
+
+    /// Subscribes to all future changes to this queue's contents.
+    ///
+    /// Each receiver buffers a limited number of updates: if a subscriber falls
+    /// too far behind, newer updates are dropped until it catches up. Dropping the
+    /// receiver unsubscribes it.
+    ///
+    /// Changes made via [`modify_queue`] are reported by comparing the queue's
+    /// contents before and after the closure runs.
+    ///
+    /// [`modify_queue`]: TrackQueue::modify_queue
+    pub fn watch(&self) -> Receiver<QueueUpdate> {
+        let mut inner = self.inner.lock();
+
+        let (tx, rx) = flume::bounded(WATCH_BUFFER_LEN);
+        inner.watchers.push(tx);
+
+        rx
+    }
+
+    /// Pauses the head of the queue, and prevents the queue from advancing until
+    /// the returned guard is dropped.
+    ///
+    /// This allows the queue to be edited (e.g., reordered by a user) without the
+    /// head ending and being replaced partway through. If the head does end in the
+    /// meantime, the queue moves on from it once the guard is dropped, so long as it
+    /// is still the head. Otherwise, the (possibly new) head is resumed on drop only
+    /// if the head was playing when editing began: a paused queue stays paused.
+    ///
+    /// Guards may be held concurrently: the queue resumes once all have been dropped,
+    /// according to the head's state when the first guard was created.
+    pub fn begin_edit(&self) -> QueueEditGuard<'_> {
+        let mut inner = self.inner.lock();
+
+        inner.editors += 1;
+        if inner.editors == 1 {
+            inner.resume_after_edit =
+                inner.tracks.front().map(|head| head.mode) == Some(PlayMode::Play);
+        }
+
+        if let Some(head) = inner.tracks.front_mut() {
+            let _ = head.pause();
+            head.mode = PlayMode::Pause;
+        }
+
+        QueueEditGuard { queue: self }
+    }
+
+    /// Pause the track at the head of the queue.
+    pub fn pause(&self) -> TrackResult<()> {
+        let mut inner = self.inner.lock();
+
+        if let Some(handle) = inner.tracks.front_mut() {
+            handle.pause()?;
+            handle.mode = PlayMode::Pause;
+        }
+
+        Ok(())
+    }
+
+    /// Resume the track at the head of the queue.
+    pub fn resume(&self) -> TrackResult<()> {
+        let mut inner = self.inner.lock();
+
+        if let Some(handle) = inner.tracks.front_mut() {
+            handle.play()?;
+            handle.mode = PlayMode::Play;
+        }
+
+        Ok(())
+    }
+
+    /// Pause the live track at the head of the queue, while still consuming its input.
+    ///
+    /// Audio received while paused is discarded, so that [`resume_to_live`] picks
+    /// up from the live edge of a stream or radio feed rather than from wherever
+    /// it was paused. Discarded audio does not count towards the track's position.
+    ///
+    /// Seekable inputs have no live edge: these are paused and resumed as normal.
+    /// See [`Track::pause_live`] for details.
+    ///
+    /// [`resume_to_live`]: TrackQueue::resume_to_live
+    /// [`Track::pause_live`]: crate::tracks::Track::pause_live
+    pub fn pause_live(&self) -> TrackResult<()> {
+        let mut inner = self.inner.lock();
+
+        if let Some(handle) = inner.tracks.front_mut() {
+            handle.pause_live()?;
+            handle.mode = PlayMode::Pause;
+        }
+
+        Ok(())
+    }
+
+    /// Resume the track at the head of the queue from its live edge, after
+    /// a call to [`pause_live`].
+    ///
+    /// [`pause_live`]: TrackQueue::pause_live
+    pub fn resume_to_live(&self) -> TrackResult<()> {
+        self.resume()
+    }
+
+    /// Sets the volume of every track in the queue as a percentage, where `100` is
+    /// unity gain.
+    ///
+    /// This uses the same mapping as [`TrackHandle::set_volume_percent`], and also applies
+    /// to all tracks added to the queue afterwards. [`set_volume_percent_with`] accepts
+    /// other curves such as [`VolumeCurve::Perceptual`].
+    ///
+    /// [`TrackHandle::set_volume_percent`]: TrackHandle::set_volume_percent
+    /// [`set_volume_percent_with`]: TrackQueue::set_volume_percent_with
+    /// [`VolumeCurve::Perceptual`]: VolumeCurve::Perceptual
+    pub fn set_volume_percent(&self, percent: u8) {
+        self.set_volume_percent_with(VolumeCurve::Linear, percent);
+    }
+
+    /// Sets the volume of every track in the queue as a percentage on the given `curve`,
+    /// where `100` is unity gain.
+    ///
+    /// As in [`set_volume_percent`], this also applies to all tracks added to the
+    /// queue afterwards.
+    ///
+    /// [`set_volume_percent`]: TrackQueue::set_volume_percent
+    pub fn set_volume_percent_with(&self, curve: VolumeCurve, percent: u8) {
+        let mut inner = self.inner.lock();
+
+        let volume = curve.gain(percent);
+        inner.volume = Some(volume);
+
+        for track in inner.tracks.iter() {
+            // As in `stop`, an error just implies the track is already gone.
+            let _ = track.set_volume(volume);
+        }
+    }
+
+    /// Stop the currently playing track, and clears the queue.
+    ///
+    /// This removes all tracks, including any which are [pinned].
+    ///
+    /// [pinned]: Queued::set_pinned
+    pub fn stop(&self) {
+        self.stop_returning();
+    }
+
+    /// Stop the currently playing track, and clears the queue, as in [`stop`].
+    ///
+    /// Returns the number of tracks which were still live in the driver, and so
+    /// were actually stopped. This is `0` if the queue was already empty, or
+    /// every track within it had already ended.
+    ///
+    /// [`stop`]: TrackQueue::stop
+    pub fn stop_returning(&self) -> usize {
+        let mut inner = self.inner.lock();
+
+        let previous = inner.tracks.front().map(|q| q.uuid());
+        inner.last_end = None;
+        inner.finished.clear();
+
+        // Errors when removing tracks don't really make
+        // a difference: an error just implies it's already gone.
+        let stopped = inner
+            .tracks
+            .drain(..)
+            .filter(|track| track.stop().is_ok())
+            .count();
+
+        inner.notify(QueueUpdate::Cleared);
+        inner.notify_head_change(previous);
+
+        stopped
+    }
+
+    /// Skip to the next track in the queue, if it exists.
+    ///
+    /// Returns [`TrackError::Pinned`] if the current track is [pinned].
+    ///
+    /// [`TrackError::Pinned`]: TrackError::Pinned
+    /// [pinned]: Queued::set_pinned
+    pub fn skip(&self) -> TrackResult<()> {
+        let inner = self.inner.lock();
+
+        match inner.tracks.front() {
+            Some(queued) if queued.pinned => Err(TrackError::Pinned),
+            _ => inner.stop_current(),
+        }
+    }
+
+    /// Skip to the next track in the queue by seeking to the final frame of the
+    /// current track, rather than stopping it.
+    ///
+    /// The head then ends as though it had played out, so the queue moves on through
+    /// the same path as it would after a full playthrough. A head which is looping
+    /// will instead loop. If the head cannot be seeked or has no known duration,
+    /// this falls back to [`skip`].
+    ///
+    /// Returns [`TrackError::Pinned`] if the current track is [pinned].
+    ///
+    /// [`skip`]: TrackQueue::skip
+    /// [`TrackError::Pinned`]: TrackError::Pinned
+    /// [pinned]: Queued::set_pinned
+    pub fn skip_to_end_of_current(&self) -> TrackResult<()> {
+        let inner = self.inner.lock();
+
+        match inner.tracks.front() {
+            Some(queued) if queued.pinned => Err(TrackError::Pinned),
+            Some(queued) if queued.is_seekable() => match queued.metadata().duration {
+                Some(duration) =>
+                    queued.seek_time(duration.checked_sub(TIMESTEP_LENGTH).unwrap_or_default()),
+                None => inner.stop_current(),
+            },
+            _ => inner.stop_current(),
+        }
+    }
+
+    /// Returns a list of currently queued tracks.
+    ///
+    /// Does not allow for modification of the queue, instead returns a snapshot of the queue at the time of calling.
+    ///
+    /// Use [`modify_queue`] for direct modification of the queue.
+    ///
+    /// [`modify_queue`]: TrackQueue::modify_queue
+    pub fn current_queue(&self) -> Vec<TrackHandle> {
+        let inner = self.inner.lock();
+
+        inner.tracks.iter().map(|q| q.handle()).collect()
+    }
+
+    /// Returns the currently playing track, and all tracks queued after it.
+    ///
+    /// This is read under a single lock, so the two halves are always consistent
+    /// with one another, unlike separate calls to [`current`] and [`current_queue`].
+    ///
+    /// [`current`]: TrackQueue::current
+    /// [`current_queue`]: TrackQueue::current_queue
+    pub fn split(&self) -> (Option<TrackHandle>, Vec<TrackHandle>) {
+        let inner = self.inner.lock();
+
+        let mut tracks = inner.tracks.iter().map(|q| q.handle());
+
+        (tracks.next(), tracks.collect())
+    }
+}
+
+impl TrackQueueCore {
+    /// Refuses new tracks if `handler` is disconnected and buffering has not been requested.
+    ///
+    /// Otherwise, `handler` is remembered as the driver for [`OnComplete::LoopQueue`].
+    ///
+    /// [`OnComplete::LoopQueue`]: OnComplete::LoopQueue
+    fn check_connected(&mut self, handler: &Driver) -> TrackResult<()> {
+        if self.buffer_while_disconnected || handler.is_connected() {
+            self.driver = Some(handler.track_sink());
+            Ok(())
+        } else {
+            Err(TrackError::NotConnected)
+        }
+    }
+
+    /// Records that `requester` is adding a track at `now`, unless this would exceed
+    /// the enqueue rate limit.
+    ///
+    /// Additions which have left the window are forgotten for all requesters.
+    fn take_enqueue_slot(&mut self, requester: u64, now: Instant) -> TrackResult<()> {
+        let (limit, window) = match self.enqueue_limit {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+
+        self.enqueue_times.retain(|_, times| {
+            while matches!(times.front(), Some(t) if now.duration_since(*t) >= window) {
+                times.pop_front();
+            }
+
+            !times.is_empty()
+        });
+
+        let times = self.enqueue_times.entry(requester).or_default();
+        if times.len() >= limit as usize {
+            let elapsed = times
+                .front()
+                .map(|t| now.duration_since(*t))
+                .unwrap_or_default();
+
+            return Err(TrackError::Throttled {
+                retry_after: window - elapsed,
+            });
+        }
+
+        times.push_back(now);
+
+        Ok(())
+    }
+
+    /// Records that a track from `requester` has finished, and (in fair mode) moves
+    /// the next track from the longest-waiting requester to the head of the queue.
+    ///
+    /// Requesters with no tracks left are forgotten once they have waited longer than
+    /// every requester with queued tracks, so that the queue does not grow with every
+    /// requester it has ever served. Should they return, they are treated as unserved,
+    /// which still places them ahead of everyone who has been served since.
+    fn take_fair_turn(&mut self, requester: Option<u64>) {
+        self.requester_turns.insert(requester, self.turn);
+        self.turn += 1;
+
+        let tracks = &self.tracks;
+        let oldest_queued = tracks
+            .iter()
+            .filter_map(|q| self.requester_turns.get(&q.requester))
+            .min()
+            .copied();
+        self.requester_turns.retain(|requester, turn| {
+            tracks.iter().any(|q| q.requester == *requester)
+                || matches!(oldest_queued, Some(oldest) if *turn >= oldest)
+        });
+
+        if !self.fair_mode {
+            return;
+        }
+
+        let turns = &self.requester_turns;
+        let next = self
+            .tracks
+            .iter()
+            .enumerate()
+            // Unserved requesters (`None`) sort first.
+            .min_by_key(|(i, q)| (turns.get(&q.requester).copied(), *i))
+            .map(|(i, _)| i);
+
+        if let Some(i) = next.filter(|i| *i != 0) {
+            if let Some(queued) = self.tracks.remove(i) {
+                self.tracks.push_front(queued);
+                self.notify(QueueUpdate::Reordered);
+            }
+        }
+    }
+
+    /// Skip to the next track in the queue, if it exists.
+    fn stop_current(&self) -> TrackResult<()> {
+        if let Some(handle) = self.tracks.front() {
+            handle.stop()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the entries following the head which should be kept ready.
+    fn preload_targets(&self) -> impl Iterator<Item = &Queued> {
+        self.tracks.iter().skip(1).take(self.preload_depth)
+    }
+
+    /// Puts back any of the `pinned` entries (alongside their former indices) which are
+    /// no longer in the queue.
+    fn restore_pinned(&mut self, pinned: Vec<(usize, Queued)>) {
+        for (index, queued) in pinned {
+            let uuid = queued.uuid();
+            if self.tracks.iter().any(|q| q.uuid() == uuid) {
+                continue;
+            }
+
+            warn!(label = ?self.label, "Pinned track {} cannot be removed: restoring it.", uuid);
+            let index = index.min(self.tracks.len());
+            self.tracks.insert(index, queued);
+        }
+    }
+
+    /// Re-readies preload targets after the queue changes shape, if the head
+    /// has already passed its preload point.
+    ///
+    /// Tracks are not un-readied if they stop being targets: they will be
+    /// played (or removed) eventually, and recreating them would be wasteful.
+    fn refresh_preload(&mut self) {
+        let head = self.tracks.front().map(|q| q.uuid());
+
+        if self.preloading_for.is_some() && self.preloading_for == head {
+            self.preload_upcoming();
+        } else {
+            self.preloading_for = None;
+        }
+    }
+
+    /// Starts the head of the queue, discarding (and recording) tracks which
+    /// cannot be played until one works, or the queue runs out.
+    fn start_playable_head(&mut self, remote_lock: &Arc<Mutex<TrackQueueCore>>) {
+        while let Some(new) = self.tracks.front_mut() {
+            match start_head(new, remote_lock) {
+                Ok(mode) => {
+                    new.preparing = true;
+                    new.mode = mode;
+                    break;
+                },
+                Err(e) => {
+                    // Discard files which cannot be used for whatever reason.
+                    warn!("Track in Queue couldn't be played...");
+                    if let Some(old) = self.tracks.pop_front() {
+                        self.failures.push((old.uuid(), e));
+                        self.failure_count += 1;
+                        self.notify(QueueUpdate::Removed { uuid: old.uuid() });
+                    }
+                },
+            }
+        }
+    }
+
+    /// Removes the head of the queue if its track has been discarded, and
+    /// moves on to the next playable track. Returns whether the head was removed.
+    ///
+    /// A head which ended normally during an edit is left for the edit to advance past.
+    pub(super) fn skip_failed_head(&mut self, remote_lock: &Arc<Mutex<TrackQueueCore>>) -> bool {
+        let deferred = self.deferred_end.as_ref().map(|(_, handle)| handle.uuid());
+        let failed = match self.tracks.front() {
+            Some(head) if Some(head.uuid()) != deferred && head.is_finished() => head.uuid(),
+            _ => return false,
+        };
+
+        warn!(label = ?self.label, "Head of queue couldn't be played...");
+        self.tracks.pop_front();
+        self.preloading_for = None;
+        self.failures.push((failed, TrackError::Finished));
+        self.failure_count += 1;
+        self.notify(QueueUpdate::Removed { uuid: failed });
+
+        self.start_playable_head(remote_lock);
+
+        let new_head = self.tracks.front().map(|q| q.uuid());
+        self.notify(QueueUpdate::Advanced { new_head });
+        self.notify_head_change(Some(failed));
+
+        true
+    }
+
+    /// Refuses new tracks whose UUID matches a track already in the queue.
+    ///
+    /// Queues match ended tracks to their entries by UUID, so duplicates would
+    /// allow one track's end to advance past the other.
+    ///
+    /// Every method taking a caller's [`Track`] or [`TrackHandle`] must check this.
+    /// [`append_queue`] and [`transfer_to`] do not, as they only enqueue new tracks
+    /// made by [`create_player`], each with a fresh random UUID.
+    ///
+    /// [`append_queue`]: TrackQueue::append_queue
+    /// [`transfer_to`]: TrackQueue::transfer_to
+    /// [`create_player`]: tracks::create_player
+    fn check_unique(&self, uuid: Uuid) -> TrackResult<()> {
+        if self.tracks.iter().any(|q| q.uuid() == uuid) {
+            Err(TrackError::DuplicateUuid)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Caches refreshed metadata for the track `uuid`, if it is still the head.
+    ///
+    /// Metadata fetched for a track which has since advanced is discarded.
+    fn cache_refreshed(&mut self, uuid: Uuid, fresh: &Metadata) -> bool {
+        match self.tracks.front_mut() {
+            Some(head) if head.uuid() == uuid => {
+                head.refreshed = Some(fresh.clone());
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns the identifiers of all queued tracks, in order.
+    fn uuids(&self) -> Vec<Uuid> {
+        self.tracks.iter().map(|q| q.uuid()).collect()
+    }
+
+    /// Sends an update to all subscribers, removing any who have hung up.
+    fn notify(&mut self, update: QueueUpdate) {
+        self.mirror(update);
+
+        self.watchers
+            .retain(|tx| !matches!(tx.try_send(update), Err(TrySendError::Disconnected(_))));
+    }
+
+    /// Applies an update to the backend, if one is set.
+    ///
+    /// The queue's head is removed from the backend directly when it ends, so
+    /// that any failed tracks removed alongside it are not confused for it.
+    fn mirror(&mut self, update: QueueUpdate) {
+        let backend = match self.backend.as_mut() {
+            Some(backend) => backend,
+            None => return,
+        };
+
+        match update {
+            QueueUpdate::Added { index, .. } =>
+                if let Some(queued) = self.tracks.get(index) {
+                    backend.push(index, queued.entry());
+                },
+            QueueUpdate::Removed { .. } | QueueUpdate::Reordered | QueueUpdate::Cleared => {
+                let order: Vec<Uuid> = self.tracks.iter().map(|q| q.uuid()).collect();
+                backend.reorder(&order);
+            },
+            QueueUpdate::Advanced { .. }
+            | QueueUpdate::HeadChanged { .. }
+            | QueueUpdate::Transition { .. }
+            | QueueUpdate::HeadReady { .. } => {},
+        }
+    }
+
+    /// Reports a change of head to all subscribers, if the head is no longer `previous`.
+    ///
+    /// This also resets all state tied to the current head.
+    fn notify_head_change(&mut self, previous: Option<Uuid>) {
+        let current = self.tracks.front().map(|q| q.uuid());
+
+        if current != previous {
+            self.loop_count = 0;
+            self.head_since = current.map(|_| Instant::now());
+
+            self.notify(QueueUpdate::HeadChanged {
+                previous,
+                current,
+                now_playing: self.now_playing,
+            });
+
+            if let Some(head) = self.tracks.front().filter(|q| q.ready) {
+                let uuid = head.uuid();
+                self.notify(QueueUpdate::HeadReady { uuid });
+            }
+        }
+    }
+
+    /// Reports the differences between the queue's current contents and `before`.
+    fn notify_changes(&mut self, before: &[Uuid]) {
+        let after = self.uuids();
+
+        let previous = before.first().copied();
+
+        if self.tracks.is_empty() && !before.is_empty() {
+            self.notify(QueueUpdate::Cleared);
+            self.notify_head_change(previous);
+            return;
+        }
+
+        for uuid in before.iter().filter(|uuid| !after.contains(uuid)) {
+            self.notify(QueueUpdate::Removed { uuid: *uuid });
+        }
+
+        for (index, uuid) in after.iter().enumerate() {
+            if !before.contains(uuid) {
+                self.notify(QueueUpdate::Added { uuid: *uuid, index });
+            }
+        }
+
+        let kept_before = before.iter().filter(|uuid| after.contains(uuid));
+        let kept_after = after.iter().filter(|uuid| before.contains(uuid));
+
+        if !kept_before.eq(kept_after) {
+            self.notify(QueueUpdate::Reordered);
+        }
+
+        self.notify_head_change(previous);
+    }
+
+    /// Asks all preload targets to ready their inputs.
+    ///
+    /// Tracks which cannot be preloaded are discarded and recorded as failures,
+    /// and the following track takes their place.
+    fn preload_upcoming(&mut self) {
+        let mut index = 1;
+
+        while index <= self.preload_depth && index < self.tracks.len() {
+            let track = &mut self.tracks[index];
+
+            match track.make_playable() {
+                Ok(()) => {
+                    track.preparing = true;
+                    index += 1;
+                },
+                Err(e) => {
+                    let uuid = track.uuid();
+                    warn!("Queued track {} could not be preloaded: {}.", uuid, e);
+
+                    self.tracks.remove(index);
+                    self.failures.push((uuid, e));
+                    self.failure_count += 1;
+                    self.notify(QueueUpdate::Removed { uuid });
+                },
+            }
+        }
+    }
+}
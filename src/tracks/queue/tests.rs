@@ -0,0 +1,1940 @@
+use super::*;
+use crate::{
+    constants::*,
+    driver::test_harness,
+    input::{restartable::Restart, Codec, Container, Metadata},
+    test_utils::*,
+    tracks::{MemoryBackend, TrackCommand, TrackState},
+};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn sine_track() -> (Track, TrackHandle) {
+    sine_track_from(None)
+}
+
+fn sine_track_from(source_url: Option<&str>) -> (Track, TrackHandle) {
+    let data = make_sine(50 * MONO_FRAME_SIZE, true);
+    let metadata = Metadata {
+        source_url: source_url.map(String::from),
+        ..Default::default()
+    };
+    let mut input = Input::new(
+        true,
+        data.into(),
+        Codec::FloatPcm,
+        Container::Raw,
+        Some(metadata),
+    );
+
+    // As with sources made by `ytdl`.
+    input.set_descriptor(source_url.map(|url| SourceDescriptor::Ytdl(url.into())));
+
+    tracks::create_player(input)
+}
+
+/// Returns the handlers a queue attaches for each track becoming ready, and ending.
+fn ready_and_end_handlers(queue: &TrackQueue) -> (ReadyNotifier, QueueHandler) {
+    let ready = ReadyNotifier {
+        remote_lock: queue.inner.clone(),
+    };
+    let end = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+
+    (ready, end)
+}
+
+/// Runs `handler` as though fired by `handle`'s track.
+fn fire(handler: &dyn EventHandler, handle: &TrackHandle) {
+    let state = TrackState::default();
+
+    futures::executor::block_on(handler.act(&EventContext::Track(&[(&state, handle)])));
+}
+
+/// Lazily recreates a [`sine_track`]'s input.
+#[derive(Clone)]
+struct SineRestarter;
+
+#[async_trait]
+impl Restart for SineRestarter {
+    async fn call_restart(
+        &mut self,
+        _time: Option<Duration>,
+    ) -> crate::input::error::Result<Input> {
+        Ok(sine_track().0.source)
+    }
+
+    async fn lazy_init(
+        &mut self,
+    ) -> crate::input::error::Result<(Option<Metadata>, Codec, Container)> {
+        Ok((None, Codec::FloatPcm, Container::Raw))
+    }
+}
+
+fn queued_track(queue: &TrackQueue) -> TrackHandle {
+    let (mut track, handle) = sine_track();
+
+    queue.add_raw(&mut track);
+
+    handle
+}
+
+#[test]
+fn watch_reports_modifications() {
+    let queue = TrackQueue::new();
+    let updates = queue.watch();
+
+    let first = queued_track(&queue).uuid();
+    let second = queued_track(&queue).uuid();
+
+    queue.modify_queue(|vq| vq.swap(0, 1));
+    let _ = queue.dequeue(1);
+
+    let seen: Vec<_> = updates.try_iter().collect();
+    assert_eq!(
+        seen,
+        vec![
+            QueueUpdate::Added {
+                uuid: first,
+                index: 0
+            },
+            QueueUpdate::HeadChanged {
+                previous: None,
+                current: Some(first),
+                now_playing: None,
+            },
+            QueueUpdate::Added {
+                uuid: second,
+                index: 1
+            },
+            QueueUpdate::Reordered,
+            QueueUpdate::HeadChanged {
+                previous: Some(first),
+                current: Some(second),
+                now_playing: None,
+            },
+            QueueUpdate::Removed { uuid: first },
+        ]
+    );
+}
+
+#[test]
+fn head_changes_carry_now_playing_token() {
+    let queue = TrackQueue::new();
+    queue.set_now_playing(Some(1234));
+    let updates = queue.watch();
+
+    let head = queued_track(&queue).uuid();
+    queue.stop();
+
+    let heads: Vec<_> = updates
+        .try_iter()
+        .filter(|update| matches!(update, QueueUpdate::HeadChanged { .. }))
+        .collect();
+    assert_eq!(
+        heads,
+        vec![
+            QueueUpdate::HeadChanged {
+                previous: None,
+                current: Some(head),
+                now_playing: Some(1234),
+            },
+            QueueUpdate::HeadChanged {
+                previous: Some(head),
+                current: None,
+                now_playing: Some(1234),
+            },
+        ]
+    );
+}
+
+fn pin_head(queue: &TrackQueue) {
+    queue.modify_queue(|vq| vq[0].set_pinned(true));
+}
+
+#[test]
+fn pinned_head_blocks_skip() {
+    let queue = TrackQueue::new();
+    queued_track(&queue);
+    pin_head(&queue);
+
+    assert_eq!(queue.skip(), Err(TrackError::Pinned));
+    assert_eq!(queue.len(), 1);
+}
+
+#[test]
+fn pinned_track_blocks_dequeue() {
+    let queue = TrackQueue::new();
+    let pinned = queued_track(&queue).uuid();
+    let other = queued_track(&queue).uuid();
+    pin_head(&queue);
+
+    assert!(matches!(queue.try_dequeue(0), Err(TrackError::Pinned)));
+    assert!(queue.dequeue(0).is_none());
+    assert_eq!(queue.try_dequeue(1).unwrap().map(|q| q.uuid()), Some(other));
+    assert_eq!(queue.current().map(|h| h.uuid()), Some(pinned));
+
+    // Only `try_dequeue` tells a pinned track apart from an out-of-range index.
+    assert!(queue.dequeue(1).is_none());
+    assert!(matches!(queue.try_dequeue(1), Ok(None)));
+}
+
+#[test]
+fn pinned_tracks_survive_modify_queue() {
+    let queue = TrackQueue::new();
+    queued_track(&queue);
+    let pinned = queued_track(&queue).uuid();
+    queued_track(&queue);
+    queue.modify_queue(|vq| vq[1].set_pinned(true));
+
+    queue.modify_queue(|vq| vq.clear());
+    let order: Vec<_> = queue.current_queue().iter().map(|h| h.uuid()).collect();
+    assert_eq!(order, vec![pinned]);
+
+    // `queued_track` drops each `Track`, so both of these have finished.
+    let finished_pinned = queued_track(&queue).uuid();
+    queue.modify_queue(|vq| vq[1].set_pinned(true));
+    queued_track(&queue);
+
+    assert_eq!(queue.prune(), 1);
+    let order: Vec<_> = queue.current_queue().iter().map(|h| h.uuid()).collect();
+    assert_eq!(order, vec![pinned, finished_pinned]);
+}
+
+#[test]
+fn stop_clears_pinned_tracks() {
+    let queue = TrackQueue::new();
+    queued_track(&queue);
+    pin_head(&queue);
+
+    queue.stop();
+
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn adds_refused_at_track_limit() {
+    let queue = TrackQueue::new();
+    queue.set_buffer_while_disconnected(true);
+
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let config = crate::Config::default().max_concurrent_tracks(Some(2));
+        let mut driver = Driver::new(config);
+
+        queue
+            .try_add_source(sine_track().0.source, &mut driver)
+            .unwrap();
+        queue
+            .try_add_source(sine_track().0.source, &mut driver)
+            .unwrap();
+        assert_eq!(
+            queue.try_add_source(sine_track().0.source, &mut driver),
+            Err(TrackError::TooManyTracks)
+        );
+        assert_eq!(queue.len(), 2);
+
+        let (track, _handle) = sine_track();
+        assert_eq!(
+            queue.replace_all(vec![track], &mut driver).err(),
+            Some(TrackError::TooManyTracks)
+        );
+        assert_eq!(queue.len(), 2);
+
+        #[cfg(feature = "builtin-queue")]
+        {
+            driver.queue().set_buffer_while_disconnected(true);
+            assert_eq!(
+                driver.try_enqueue(sine_track().0),
+                Err(TrackError::TooManyTracks)
+            );
+            assert!(driver.queue().is_empty());
+        }
+    });
+}
+
+#[test]
+fn track_room_is_reserved_atomically() {
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let config = crate::Config::default().max_concurrent_tracks(Some(2));
+        let driver = Driver::new(config.clone());
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let sink = driver.track_sink();
+                let track = sine_track().0;
+                std::thread::spawn(move || sink.play(track).is_ok())
+            })
+            .collect();
+        let accepted = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .filter(|accepted| *accepted)
+            .count();
+        assert_eq!(accepted, 2);
+
+        // Refused adds hand back any room they reserved.
+        let mut driver = Driver::new(config);
+        let queue = TrackQueue::new();
+        queue.set_buffer_while_disconnected(true);
+        queue.set_enqueue_rate_limit(1, Duration::from_secs(60));
+
+        queue
+            .add_for_requester(sine_track().0, 1, &mut driver)
+            .unwrap();
+        assert!(matches!(
+            queue.add_for_requester(sine_track().0, 1, &mut driver),
+            Err(TrackError::Throttled { .. })
+        ));
+        queue
+            .add_for_requester(sine_track().0, 2, &mut driver)
+            .unwrap();
+        assert_eq!(queue.len(), 2);
+    });
+}
+
+#[test]
+fn replace_all_checks_and_prepares_tracks() {
+    let queue = TrackQueue::new();
+    queue.set_buffer_while_disconnected(true);
+
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let mut driver = Driver::default();
+        let uuids = |handles: Vec<TrackHandle>| -> Vec<Uuid> {
+            handles.iter().map(TrackHandle::uuid).collect()
+        };
+
+        let (old, old_handle) = sine_track();
+        queue.try_add(old, &mut driver).unwrap();
+
+        let uuid = Uuid::new_v4();
+        let first = tracks::create_player_with_uuid(sine_track().0.source, uuid).0;
+        let copy = tracks::create_player_with_uuid(sine_track().0.source, uuid).0;
+        assert_eq!(
+            queue.replace_all(vec![first, copy], &mut driver).err(),
+            Some(TrackError::DuplicateUuid)
+        );
+        assert_eq!(uuids(queue.current_queue()), vec![old_handle.uuid()]);
+
+        let first = tracks::create_player_with_uuid(sine_track().0.source, uuid).0;
+        let (second, second_handle) = sine_track();
+        queue.replace_all(vec![first, second], &mut driver).unwrap();
+
+        assert_eq!(
+            uuids(queue.tracks_in_state(ReadyState::Preparing)),
+            vec![uuid]
+        );
+        assert_eq!(
+            uuids(queue.tracks_in_state(ReadyState::Uninitialised)),
+            vec![second_handle.uuid()]
+        );
+    });
+}
+
+#[test]
+fn add_next_follows_head() {
+    let queue = TrackQueue::new();
+    queue.set_buffer_while_disconnected(true);
+
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let mut driver = Driver::default();
+
+        // An empty queue plays the track straight away.
+        let head = queue
+            .add_next(sine_track().0.source, &mut driver)
+            .unwrap()
+            .uuid();
+        let (track, last) = sine_track();
+        queue.try_add(track, &mut driver).unwrap();
+        let last = last.uuid();
+        let next = queue
+            .add_next(sine_track().0.source, &mut driver)
+            .unwrap()
+            .uuid();
+
+        let order: Vec<_> = queue.current_queue().iter().map(|h| h.uuid()).collect();
+        assert_eq!(order, vec![head, next, last]);
+
+        let targets: Vec<_> = queue.preload_targets().iter().map(|h| h.uuid()).collect();
+        assert_eq!(targets, vec![next]);
+    });
+}
+
+#[test]
+fn reverse_keeps_head_in_place() {
+    let queue = TrackQueue::new();
+    queue.reverse();
+
+    let uuids: Vec<_> = (0..4).map(|_| queued_track(&queue).uuid()).collect();
+    queue.reverse();
+
+    let order: Vec<_> = queue.current_queue().iter().map(|h| h.uuid()).collect();
+    assert_eq!(order, vec![uuids[0], uuids[3], uuids[2], uuids[1]]);
+
+    let targets: Vec<_> = queue.preload_targets().iter().map(|h| h.uuid()).collect();
+    assert_eq!(targets, vec![uuids[3]]);
+}
+
+#[test]
+fn prune_removes_finished_tracks_after_head() {
+    let queue = TrackQueue::new();
+
+    // `queued_track` drops each `Track`, as a driver does once it has finished.
+    let head = queued_track(&queue).uuid();
+    queued_track(&queue);
+
+    let (mut live, live_handle) = sine_track();
+    queue.add_raw(&mut live);
+    queued_track(&queue);
+
+    assert_eq!(queue.prune(), 2);
+
+    let order: Vec<_> = queue.current_queue().iter().map(|h| h.uuid()).collect();
+    assert_eq!(order, vec![head, live_handle.uuid()]);
+}
+
+#[test]
+fn dedup_consecutive_keeps_head_and_distant_repeats() {
+    let queue = TrackQueue::new();
+    let urls = [
+        Some("a"),
+        Some("a"),
+        Some("a"),
+        Some("b"),
+        Some("b"),
+        None,
+        None,
+        Some("a"),
+    ];
+    let handles: Vec<_> = urls
+        .iter()
+        .map(|url| {
+            let (mut track, handle) = sine_track_from(*url);
+            queue.add_raw(&mut track);
+            handle.uuid()
+        })
+        .collect();
+
+    assert_eq!(queue.dedup_consecutive(), 3);
+
+    let order: Vec<_> = queue.current_queue().iter().map(|h| h.uuid()).collect();
+    assert_eq!(
+        order,
+        vec![handles[0], handles[3], handles[5], handles[6], handles[7]]
+    );
+}
+
+#[test]
+fn shuffle_on_add_never_displaces_head() {
+    let queue = TrackQueue::new();
+    queue.seed_shuffle(0x5eed);
+    queue.set_shuffle_on_add(true);
+
+    let head = queued_track(&queue).uuid();
+    let mut added: Vec<_> = (0..16).map(|_| queued_track(&queue).uuid()).collect();
+
+    let mut order: Vec<_> = queue.current_queue().iter().map(|h| h.uuid()).collect();
+    assert_eq!(order.remove(0), head);
+    assert_ne!(order, added);
+
+    order.sort();
+    added.sort();
+    assert_eq!(order, added);
+}
+
+#[test]
+fn unplayable_tracks_are_counted_as_failures() {
+    let queue = TrackQueue::new();
+    let head = queued_track(&queue);
+    // The mixer-side `Track`s are dropped by `queued_track`, so neither can be played.
+    let broken = [queued_track(&queue).uuid(), queued_track(&queue).uuid()];
+
+    let handler = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+    let state = TrackState::default();
+    futures::executor::block_on(handler.act(&EventContext::Track(&[(&state, &head)])));
+
+    assert!(queue.is_empty());
+    assert_eq!(queue.failure_count(), 2);
+
+    let drained = queue.drain_failures();
+    assert_eq!(
+        drained,
+        vec![
+            (broken[0], TrackError::Finished),
+            (broken[1], TrackError::Finished),
+        ]
+    );
+    assert!(queue.drain_failures().is_empty());
+
+    queue.stop();
+    assert_eq!(queue.failure_count(), 2);
+
+    queue.clear_failures();
+    assert_eq!(queue.failure_count(), 0);
+}
+
+#[test]
+fn failed_head_is_skipped_when_enabled() {
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let queue = TrackQueue::new();
+        queue.set_skip_failed_head(true);
+
+        // The first track is discarded before it can play, so never ends.
+        let broken = queued_track(&queue).uuid();
+        let (mut track, handle) = sine_track();
+        queue.add_raw(&mut track);
+
+        tokio::task::yield_now().await;
+
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.current().unwrap().uuid(), handle.uuid());
+        assert!(matches!(track.commands.try_recv(), Ok(TrackCommand::Play)));
+        assert_eq!(queue.drain_failures(), vec![(broken, TrackError::Finished)]);
+    });
+
+    // Without the flag, new tracks wait behind the stalled head.
+    rt.block_on(async {
+        let queue = TrackQueue::new();
+        let broken = queued_track(&queue).uuid();
+        let (mut track, _handle) = sine_track();
+        queue.add_raw(&mut track);
+
+        tokio::task::yield_now().await;
+
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.current().unwrap().uuid(), broken);
+        assert!(track.commands.try_recv().is_err());
+        assert_eq!(queue.failure_count(), 0);
+    });
+}
+
+#[test]
+fn enabling_skip_failed_head_unblocks_queue() {
+    let queue = TrackQueue::new();
+    let broken = queued_track(&queue).uuid();
+    let (mut track, handle) = sine_track();
+    queue.add_raw(&mut track);
+    assert_eq!(queue.current().unwrap().uuid(), broken);
+
+    queue.set_skip_failed_head(true);
+
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue.current().unwrap().uuid(), handle.uuid());
+    assert!(matches!(track.commands.try_recv(), Ok(TrackCommand::Play)));
+    assert_eq!(queue.drain_failures(), vec![(broken, TrackError::Finished)]);
+}
+
+#[test]
+fn deferred_head_is_not_skipped_as_failed() {
+    let queue = TrackQueue::new();
+
+    let (mut head_track, head) = sine_track();
+    queue.add_raw(&mut head_track);
+    let (mut next_track, next) = sine_track();
+    queue.add_raw(&mut next_track);
+
+    let handler = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+    let state = TrackState::default();
+    let edit = queue.begin_edit();
+
+    // The head ends normally, and its track is dropped by the mixer.
+    futures::executor::block_on(handler.act(&EventContext::Track(&[(&state, &head)])));
+    drop(head_track);
+
+    queue.set_skip_failed_head(true);
+    assert_eq!(queue.current().unwrap().uuid(), head.uuid());
+
+    drop(edit);
+    assert_eq!(queue.current().unwrap().uuid(), next.uuid());
+    assert_eq!(queue.failure_count(), 0);
+}
+
+#[test]
+fn edit_guard_defers_advance_until_dropped() {
+    let queue = TrackQueue::new();
+    let (mut head_track, head) = sine_track();
+    queue.add_raw(&mut head_track);
+    let (mut next_track, next) = sine_track();
+    queue.add_raw(&mut next_track);
+
+    let handler = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+    let state = TrackState::default();
+
+    {
+        let edit = queue.begin_edit();
+        let _nested = queue.begin_edit();
+        assert!(matches!(
+            head_track.commands.try_recv(),
+            Ok(TrackCommand::Pause)
+        ));
+
+        futures::executor::block_on(handler.act(&EventContext::Track(&[(&state, &head)])));
+        assert_eq!(edit.len(), 2);
+        assert!(next_track.commands.try_recv().is_err());
+    }
+
+    assert_eq!(queue.current().unwrap().uuid(), next.uuid());
+    assert!(matches!(
+        next_track.commands.try_recv(),
+        Ok(TrackCommand::Play)
+    ));
+
+    // With no end while editing, the head is simply resumed.
+    drop(queue.begin_edit());
+    assert!(matches!(
+        next_track.commands.try_recv(),
+        Ok(TrackCommand::Pause)
+    ));
+    assert!(matches!(
+        next_track.commands.try_recv(),
+        Ok(TrackCommand::Play)
+    ));
+
+    // A head paused before editing is left paused.
+    queue.pause().unwrap();
+    drop(queue.begin_edit());
+    assert!(matches!(
+        next_track.commands.try_recv(),
+        Ok(TrackCommand::Pause)
+    ));
+    assert!(matches!(
+        next_track.commands.try_recv(),
+        Ok(TrackCommand::Pause)
+    ));
+    assert!(next_track.commands.try_recv().is_err());
+
+    // This includes pauses made directly through the track's handle.
+    queue.resume().unwrap();
+    let _ = next_track.commands.try_recv();
+    let notifier = PlayModeNotifier {
+        remote_lock: queue.inner.clone(),
+    };
+    let paused = TrackState {
+        playing: PlayMode::Pause,
+        ..Default::default()
+    };
+    futures::executor::block_on(notifier.act(&EventContext::Track(&[(&paused, &next)])));
+
+    drop(queue.begin_edit());
+    assert!(matches!(
+        next_track.commands.try_recv(),
+        Ok(TrackCommand::Pause)
+    ));
+    assert!(next_track.commands.try_recv().is_err());
+}
+
+#[test]
+fn known_duration_skips_unknown_tracks() {
+    let queue = TrackQueue::new();
+    assert_eq!(queue.known_duration(), Duration::default());
+
+    for duration in &[Some(3), None, Some(4)] {
+        let metadata = Metadata {
+            duration: duration.map(Duration::from_secs),
+            ..Default::default()
+        };
+        let input = Input::new(
+            true,
+            Vec::<u8>::new().into(),
+            Codec::FloatPcm,
+            Container::Raw,
+            Some(metadata),
+        );
+        let (mut track, _) = tracks::create_player(input);
+        queue.add_raw(&mut track);
+    }
+
+    assert_eq!(queue.known_duration(), Duration::from_secs(7));
+}
+
+#[test]
+fn history_remembers_finished_tracks() {
+    let queue = TrackQueue::new();
+    queue.set_history_len(2);
+
+    let handler = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+    let state = TrackState::default();
+
+    let mut tracks = vec![];
+    for url in &[None, Some("a"), Some("b")] {
+        let (mut track, handle) = sine_track_from(*url);
+        queue.add_raw(&mut track);
+        tracks.push((track, handle));
+    }
+
+    for (_, handle) in &tracks {
+        futures::executor::block_on(handler.act(&EventContext::Track(&[(&state, handle)])));
+    }
+
+    // The oldest track has been forgotten.
+    let history: Vec<_> = queue.history().iter().map(|entry| entry.uuid).collect();
+    assert_eq!(history, vec![tracks[1].1.uuid(), tracks[2].1.uuid()]);
+
+    let replayed = |uuid| {
+        queue
+            .replay_source(uuid)
+            .map(|input| input.metadata.source_url.clone())
+    };
+    assert_eq!(replayed(tracks[2].1.uuid()), Ok(Some("b".into())));
+    assert_eq!(replayed(tracks[0].1.uuid()), Err(TrackError::NotInHistory));
+
+    queue.set_history_len(1);
+    assert_eq!(queue.history().len(), 1);
+
+    // A source URL alone does not say how to recreate a track.
+    let (mut track, handle) = sine_track_from(Some("c"));
+    track.source.set_descriptor(None);
+    queue.add_raw(&mut track);
+    futures::executor::block_on(handler.act(&EventContext::Track(&[(&state, &handle)])));
+    assert_eq!(replayed(handle.uuid()), Err(TrackError::NotReplayable));
+}
+
+#[test]
+fn head_ready_sent_once_per_head() {
+    let queue = TrackQueue::new();
+    let (mut track, first) = sine_track();
+    queue.add_raw(&mut track);
+    let (mut track, second) = sine_track();
+    queue.add_raw(&mut track);
+    let updates = queue.watch();
+
+    let (ready, end) = ready_and_end_handlers(&queue);
+    let head_ready = || {
+        updates
+            .try_iter()
+            .filter(|update| matches!(update, QueueUpdate::HeadReady { .. }))
+            .collect::<Vec<_>>()
+    };
+
+    // A preloaded track is only reported once it becomes the head.
+    fire(&ready, &second);
+    assert!(head_ready().is_empty());
+
+    fire(&ready, &first);
+    fire(&ready, &first);
+    assert_eq!(
+        head_ready(),
+        vec![QueueUpdate::HeadReady { uuid: first.uuid() }]
+    );
+
+    fire(&end, &first);
+    assert_eq!(
+        head_ready(),
+        vec![QueueUpdate::HeadReady {
+            uuid: second.uuid()
+        }]
+    );
+}
+
+#[test]
+fn last_transition_reports_head_readiness() {
+    let queue = TrackQueue::new();
+    let mut tracks = Vec::new();
+    let mut handles = Vec::new();
+    for _ in 0..3 {
+        let (mut track, handle) = sine_track();
+        queue.add_raw(&mut track);
+        tracks.push(track);
+        handles.push(handle);
+    }
+
+    let (ready, end) = ready_and_end_handlers(&queue);
+
+    assert_eq!(queue.last_transition_gapless(), None);
+
+    fire(&ready, &handles[1]);
+    fire(&end, &handles[0]);
+    assert_eq!(queue.last_transition_gapless(), Some(true));
+
+    fire(&end, &handles[1]);
+    assert_eq!(queue.last_transition_gapless(), Some(false));
+
+    // Emptying the queue leaves the last handoff in place.
+    fire(&end, &handles[2]);
+    assert_eq!(queue.last_transition_gapless(), Some(false));
+}
+
+#[test]
+fn prepare_elapsed_counts_until_head_ready() {
+    let queue = TrackQueue::new();
+    assert_eq!(queue.current_prepare_elapsed(), None);
+
+    let (mut track, first) = sine_track();
+    queue.add_raw(&mut track);
+    let (mut second_track, second) = sine_track();
+    queue.add_raw(&mut second_track);
+
+    let (ready, end) = ready_and_end_handlers(&queue);
+
+    std::thread::sleep(Duration::from_millis(10));
+    let waited = queue.current_prepare_elapsed().unwrap();
+    assert!(waited >= Duration::from_millis(10));
+
+    fire(&ready, &first);
+    assert_eq!(queue.current_prepare_elapsed(), None);
+
+    // The wait restarts for each new head.
+    fire(&end, &first);
+    assert!(queue.current_prepare_elapsed().unwrap() < waited);
+
+    fire(&ready, &second);
+    assert_eq!(queue.current_prepare_elapsed(), None);
+}
+
+#[test]
+fn adopted_track_becomes_head() {
+    let queue = TrackQueue::new();
+    let (mut queued_track, queued) = sine_track();
+    queue.add_raw(&mut queued_track);
+
+    let (_adopted_track, adopted) = sine_track();
+    queue.adopt(adopted.clone(), None).unwrap();
+    assert_eq!(
+        queue.adopt(adopted.clone(), None),
+        Err(TrackError::DuplicateUuid)
+    );
+    assert_eq!(queue.current().map(|h| h.uuid()), Some(adopted.uuid()));
+    assert_eq!(queue.len(), 2);
+
+    // The queue now moves on once the adopted track ends.
+    let end = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+    let state = TrackState::default();
+    futures::executor::block_on(end.act(&EventContext::Track(&[(&state, &adopted)])));
+    assert_eq!(queue.current().map(|h| h.uuid()), Some(queued.uuid()));
+
+    let (ended_track, ended) = sine_track();
+    drop(ended_track);
+    assert_eq!(queue.adopt(ended, None), Err(TrackError::Finished));
+    assert_eq!(queue.len(), 1);
+}
+
+#[test]
+fn unpaused_tracks_mix_alongside_head() {
+    let (_rt, mut mixer, _channels) = test_harness::runtime_with_mixer();
+
+    let queue = TrackQueue::new();
+    let (mut head, _) = sine_track();
+    queue.add_raw(&mut head);
+    let (mut paused, _) = sine_track();
+    queue.add_raw(&mut paused);
+    let (mut layered, layered_handle) = sine_track();
+    queue.add_raw_at(
+        &mut layered,
+        AddOptions {
+            unpaused: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(queue.len(), 3);
+
+    let preparing: Vec<_> = queue
+        .tracks_in_state(ReadyState::Preparing)
+        .iter()
+        .map(TrackHandle::uuid)
+        .collect();
+    assert_eq!(preparing[1..], [layered_handle.uuid()]);
+
+    mixer.tracks.extend(vec![head, paused, layered]);
+    mixer.cycle().unwrap();
+
+    let played: Vec<_> = mixer
+        .tracks
+        .iter()
+        .map(|t| t.position > Duration::default())
+        .collect();
+    assert_eq!(played, vec![true, false, true]);
+}
+
+#[test]
+fn stats_snapshot_queue_state() {
+    let queue = TrackQueue::new();
+    assert_eq!(queue.stats().head, None);
+
+    let mut tracks = Vec::new();
+    let mut handles = Vec::new();
+    for secs in &[10, 20] {
+        let (mut source, _) = sine_track();
+        source.source.metadata.duration = Some(Duration::from_secs(*secs));
+        let (mut track, handle) = tracks::create_player(source.source);
+        queue.add_raw(&mut track);
+        tracks.push(track);
+        handles.push(handle);
+    }
+    queue.set_max_loops(Some(2));
+
+    let stats = queue.stats();
+    assert_eq!(stats.len, 2);
+    assert_eq!(stats.upcoming, 1);
+    assert_eq!(stats.known_duration, Duration::from_secs(30));
+    assert_eq!(stats.max_loops, Some(2));
+    assert_eq!(stats.failure_count, 0);
+    assert_eq!(stats.head, Some(ReadyState::Preparing));
+    assert_eq!(stats.head_mode, Some(PlayMode::Play));
+
+    let (ready, _) = ready_and_end_handlers(&queue);
+    fire(&ready, &handles[0]);
+    queue.pause().unwrap();
+
+    let stats = queue.stats();
+    assert_eq!(stats.head, Some(ReadyState::Ready));
+    assert_eq!(stats.head_mode, Some(PlayMode::Pause));
+}
+
+#[test]
+fn skip_to_end_seeks_when_possible() {
+    let queue = TrackQueue::new();
+    let (mut source, _) = sine_track();
+    source.source.metadata.duration = Some(Duration::from_secs(10));
+    let (mut track, _) = tracks::create_player(source.source);
+    queue.add_raw(&mut track);
+
+    queue.skip_to_end_of_current().unwrap();
+    assert!(matches!(
+        track.commands.try_recv(),
+        Ok(TrackCommand::Seek(pos)) if pos == Duration::from_secs(10) - TIMESTEP_LENGTH
+    ));
+
+    // Without a known duration, the head is stopped instead.
+    let queue = TrackQueue::new();
+    let (mut track, _) = sine_track();
+    queue.add_raw(&mut track);
+
+    queue.skip_to_end_of_current().unwrap();
+    assert!(matches!(track.commands.try_recv(), Ok(TrackCommand::Stop)));
+}
+
+#[test]
+fn disabled_autostart_holds_head_until_resumed() {
+    let queue = TrackQueue::new();
+    queue.set_autostart(false);
+    assert!(!queue.autostart());
+
+    let (mut track, _) = sine_track();
+    queue.add_raw(&mut track);
+    assert_eq!(track.playing, PlayMode::Pause);
+
+    queue.resume().unwrap();
+    assert!(matches!(track.commands.try_recv(), Ok(TrackCommand::Play)));
+}
+
+#[test]
+fn preload_depth_readies_upcoming_tracks() {
+    let queue = TrackQueue::new();
+    queue.set_preload_depth(3);
+
+    let mut tracks = Vec::new();
+    for _ in 0..5 {
+        let (mut track, _handle) = sine_track();
+        queue.add_raw(&mut track);
+        tracks.push(track);
+    }
+    // Discard any commands sent while adding.
+    for track in &tracks {
+        let _ = track.commands.try_iter().count();
+    }
+
+    queue.inner.lock().preload_upcoming();
+
+    let preloaded: Vec<_> = tracks
+        .iter()
+        .map(|track| {
+            track
+                .commands
+                .try_iter()
+                .any(|cmd| matches!(cmd, TrackCommand::MakePlayable))
+        })
+        .collect();
+    assert_eq!(preloaded, vec![false, true, true, true, false]);
+
+    // A closed track is recorded, and the next track is preloaded in its place.
+    let closed = tracks.remove(2).handle.uuid();
+    let next = tracks[3].handle.uuid();
+    queue.inner.lock().preload_upcoming();
+
+    assert_eq!(queue.drain_failures(), vec![(closed, TrackError::Finished)]);
+    assert_eq!(queue.failure_count(), 1);
+    assert_eq!(queue.len(), 4);
+    assert!(tracks[3]
+        .commands
+        .try_iter()
+        .any(|cmd| matches!(cmd, TrackCommand::MakePlayable)));
+    assert!(queue
+        .tracks_in_state(ReadyState::Preparing)
+        .iter()
+        .any(|handle| handle.uuid() == next));
+}
+
+#[test]
+fn tracks_are_listed_by_ready_state() {
+    let queue = TrackQueue::new();
+    let mut tracks = Vec::new();
+    let mut handles = Vec::new();
+    for _ in 0..3 {
+        let (mut track, handle) = sine_track();
+        queue.add_raw(&mut track);
+        tracks.push(track);
+        handles.push(handle);
+    }
+    let uuids = |state| {
+        queue
+            .tracks_in_state(state)
+            .iter()
+            .map(TrackHandle::uuid)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(uuids(ReadyState::Preparing), vec![handles[0].uuid()]);
+    assert_eq!(
+        uuids(ReadyState::Uninitialised),
+        vec![handles[1].uuid(), handles[2].uuid()]
+    );
+
+    queue.inner.lock().preload_upcoming();
+    let (ready, _) = ready_and_end_handlers(&queue);
+    fire(&ready, &handles[0]);
+
+    assert_eq!(uuids(ReadyState::Ready), vec![handles[0].uuid()]);
+    assert_eq!(uuids(ReadyState::Preparing), vec![handles[1].uuid()]);
+    assert_eq!(uuids(ReadyState::Uninitialised), vec![handles[2].uuid()]);
+}
+
+#[test]
+fn source_filter_rejects_tracks() {
+    let queue = TrackQueue::new();
+    queue.set_buffer_while_disconnected(true);
+    queue.set_source_filter(|source| match source.metadata.source_url.as_deref() {
+        Some(url) if url.starts_with("https://allowed/") => Ok(()),
+        url => Err(TrackError::Rejected {
+            reason: format!("host not allowed: {}", url.unwrap_or("none")).into(),
+        }),
+    });
+
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let mut driver = Driver::default();
+
+        let allowed = sine_track_from(Some("https://allowed/a")).0;
+        assert_eq!(queue.try_add(allowed, &mut driver), Ok(()));
+
+        let blocked = sine_track_from(Some("https://blocked/b")).0;
+        assert_eq!(
+            queue.try_add(blocked, &mut driver),
+            Err(TrackError::Rejected {
+                reason: "host not allowed: https://blocked/b".into()
+            })
+        );
+        let blocked = sine_track_from(None).0.source;
+        assert!(matches!(
+            queue.add_next(blocked, &mut driver),
+            Err(TrackError::Rejected { .. })
+        ));
+
+        queue.clear_source_filter();
+        let unchecked = sine_track_from(None).0.source;
+        assert!(queue.add_next(unchecked, &mut driver).is_ok());
+
+        #[cfg(feature = "builtin-queue")]
+        {
+            driver.queue().set_buffer_while_disconnected(true);
+            driver.queue().set_source_filter(|_| {
+                Err(TrackError::Rejected {
+                    reason: "no adds".into(),
+                })
+            });
+            assert!(matches!(
+                driver.try_enqueue_source(sine_track().0.source),
+                Err(TrackError::Rejected { .. })
+            ));
+            assert!(driver.queue().is_empty());
+        }
+    });
+
+    assert_eq!(queue.len(), 2);
+}
+
+#[test]
+fn m3u_export_round_trips_locations() {
+    let file_track = |path: &str, title: Option<&str>, secs: u64| {
+        let (mut track, _) = sine_track_from(Some(path));
+        track.source.metadata.title = title.map(String::from);
+        track.source.metadata.duration = Some(Duration::from_secs(secs));
+        tracks::create_player(track.source)
+    };
+
+    let queue = TrackQueue::new();
+    let (mut first, _) = file_track("/music/intro.flac", Some("Intro"), 95);
+    let (mut second, _) = sine_track();
+    let (mut third, _) = file_track("/music/outro.flac", None, 200);
+    queue.add_raw(&mut first);
+    queue.add_raw(&mut second);
+    queue.add_raw(&mut third);
+
+    let m3u = queue.to_m3u();
+    let lines: Vec<_> = m3u.lines().collect();
+    assert_eq!(
+        lines[..3],
+        ["#EXTM3U", "#EXTINF:95,Intro", "/music/intro.flac"]
+    );
+    assert!(lines[3].starts_with("# Skipped"));
+    assert_eq!(lines[5], "/music/outro.flac");
+
+    let rt = test_harness::test_runtime();
+    let reread = TrackQueue::new();
+    reread.set_buffer_while_disconnected(true);
+
+    rt.block_on(async {
+        let mut driver = Driver::default();
+        let outcomes = reread
+            .add_from_reader(
+                m3u.as_bytes(),
+                |path| sine_track_from(Some(&path)).0.source,
+                &mut driver,
+            )
+            .unwrap();
+        assert_eq!(outcomes.len(), 2);
+    });
+
+    let locations = |m3u: &str| {
+        m3u.lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(locations(&reread.to_m3u()), locations(&m3u));
+}
+
+#[test]
+fn add_from_reader_reports_each_line() {
+    let queue = TrackQueue::new();
+    queue.set_buffer_while_disconnected(true);
+    queue.set_source_filter(|source| match source.metadata.source_url.as_deref() {
+        Some("bad") => Err(TrackError::Rejected {
+            reason: "bad line".into(),
+        }),
+        _ => Ok(()),
+    });
+
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let mut driver = Driver::default();
+        let make_input = |line: String| sine_track_from(Some(&line)).0.source;
+
+        let list = "first\n\n# comment\n  bad  \nlast\n";
+        let outcomes = queue
+            .add_from_reader(list.as_bytes(), make_input, &mut driver)
+            .unwrap();
+
+        let lines: Vec<_> = outcomes.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![1, 4, 5]);
+        assert!(outcomes[0].1.is_ok());
+        assert_eq!(
+            outcomes[1].1.as_ref().map(|_| ()),
+            Err(&TrackError::Rejected {
+                reason: "bad line".into()
+            })
+        );
+        assert!(outcomes[2].1.is_ok());
+
+        // Invalid UTF-8 cannot be read as a line.
+        let broken = &b"first\n\xff\n"[..];
+        assert!(queue
+            .add_from_reader(broken, make_input, &mut driver)
+            .is_err());
+    });
+
+    let urls: Vec<_> = queue
+        .current_queue()
+        .iter()
+        .map(|h| h.metadata().source_url.clone().unwrap())
+        .collect();
+    assert_eq!(urls, vec!["first", "last", "first"]);
+}
+
+#[test]
+fn transfer_leaves_unanswered_tracks_in_place() {
+    let source = TrackQueue::new();
+    let target = TrackQueue::new();
+    target.set_buffer_while_disconnected(true);
+
+    let (mut moving, _) = sine_track();
+    source.add_raw(&mut moving);
+    let (mut stuck, stuck_handle) = sine_track();
+    source.add_raw(&mut stuck);
+
+    let ic = test_harness::disconnected_interconnect();
+
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let mut driver = Driver::default();
+
+        // Only the first track's old mixer hands it back: the second acts as
+        // though its driver were disconnected.
+        let (moved, ()) = futures::join!(source.transfer_to(&target, &mut driver), async {
+            moving.process_commands(0, &ic);
+        });
+
+        let moved = moved.unwrap();
+        assert_eq!(moved.len(), 1);
+        assert_eq!(target.current().map(|h| h.uuid()), Some(moved[0].uuid()));
+    });
+
+    assert_eq!(moving.playing, PlayMode::Stop);
+    let left: Vec<_> = source.current_queue().iter().map(|h| h.uuid()).collect();
+    assert_eq!(left, vec![stuck_handle.uuid()]);
+
+    // A late reply from the old mixer must not take the returned track's source.
+    stuck.process_commands(0, &ic);
+    assert_ne!(stuck.playing, PlayMode::Stop);
+}
+
+#[test]
+fn fair_mode_remembers_returning_requesters() {
+    let queue = TrackQueue::new();
+    queue.set_fair_mode(true);
+
+    let mut live = vec![];
+    let mut add = |requester| {
+        let (mut track, _) = sine_track();
+        track.set_requester(Some(requester));
+        queue.add_raw(&mut track);
+        live.push(track);
+    };
+    for requester in &[2, 1, 2, 3] {
+        add(*requester);
+    }
+
+    let handler = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+    let end_head = || {
+        let head = queue.current().unwrap();
+        fire(&handler, &head);
+        queue
+            .inner
+            .lock()
+            .tracks
+            .front()
+            .and_then(Queued::requester)
+    };
+
+    assert_eq!(end_head(), Some(1));
+    assert_eq!(end_head(), Some(3));
+
+    // Requester 1 was served after requester 2, so must wait behind them.
+    add(1);
+    assert_eq!(end_head(), Some(2));
+    assert_eq!(end_head(), Some(1));
+}
+
+#[cfg(feature = "builtin-queue")]
+#[test]
+fn take_queue_detaches_and_stops_tracks() {
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let mut driver = Driver::default();
+        driver.queue().set_buffer_while_disconnected(true);
+        driver.queue().set_preload_depth(3);
+        driver.try_enqueue(sine_track().0).unwrap();
+        driver.try_enqueue(sine_track().0).unwrap();
+
+        let taken = driver.take_queue();
+        assert!(taken.is_empty());
+        assert_eq!(taken.preload_depth(), 3);
+        assert!(driver.queue().is_empty());
+
+        // The replacement queue is never hidden from later takes.
+        driver.queue().set_buffer_while_disconnected(true);
+        driver.try_enqueue(sine_track().0).unwrap();
+        assert_eq!(driver.queue().len(), 1);
+        assert!(driver.take_queue().is_empty());
+
+        driver.set_queue(taken);
+        assert_eq!(driver.take_queue().preload_depth(), 3);
+    });
+}
+
+#[test]
+fn enqueue_rate_limit_throttles_each_requester() {
+    let queue = TrackQueue::new();
+    queue.set_buffer_while_disconnected(true);
+    queue.set_enqueue_rate_limit(2, Duration::from_secs(60));
+
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let mut driver = Driver::default();
+        let mut add = |requester| queue.add_for_requester(sine_track().0, requester, &mut driver);
+
+        assert_eq!(add(1), Ok(()));
+        assert_eq!(add(1), Ok(()));
+        assert!(matches!(
+            add(1),
+            Err(TrackError::Throttled { retry_after })
+                if retry_after <= Duration::from_secs(60) && retry_after > Duration::from_secs(59)
+        ));
+        assert_eq!(add(2), Ok(()));
+
+        // Any add path is throttled once the track carries a requester.
+        let (mut track, _) = sine_track();
+        track.set_requester(Some(1));
+        assert!(matches!(
+            queue.add_unpaused(track, &mut driver),
+            Err(TrackError::Throttled { .. })
+        ));
+        assert_eq!(queue.try_add(sine_track().0, &mut driver), Ok(()));
+    });
+
+    queue.modify_queue(|vq| {
+        let requesters: Vec<_> = vq.iter().map(Queued::requester).collect();
+        assert_eq!(requesters, vec![Some(1), Some(1), Some(2), None]);
+    });
+
+    // Additions are forgotten once they leave the window.
+    let mut inner = queue.inner.lock();
+    let later = Instant::now() + Duration::from_secs(61);
+    assert_eq!(inner.take_enqueue_slot(1, later), Ok(()));
+    assert!(!inner.enqueue_times.contains_key(&2));
+}
+
+#[test]
+fn append_queue_moves_recreatable_tracks() {
+    let queue = TrackQueue::new();
+    queue.set_buffer_while_disconnected(true);
+    queued_track(&queue);
+
+    let other = TrackQueue::new();
+    let mut tracks = vec![];
+    for url in &[Some("a"), Some("b"), None, Some("d")] {
+        let (mut track, handle) = sine_track_from(*url);
+        other.add_raw(&mut track);
+        tracks.push((track, handle));
+    }
+    other.modify_queue(|vq| vq[1].set_requester(Some(7)));
+
+    let rt = test_harness::test_runtime();
+    let urls = |queue: &TrackQueue| {
+        queue.modify_queue(|vq| {
+            vq.iter()
+                .map(|q| q.metadata().source_url.clone())
+                .collect::<Vec<_>>()
+        })
+    };
+    let stopped = |track: &Track| {
+        track
+            .commands
+            .try_iter()
+            .any(|cmd| matches!(cmd, TrackCommand::Stop))
+    };
+
+    rt.block_on(async {
+        let mut driver = Driver::default();
+
+        let moved = queue.append_queue(&other, false, &mut driver).unwrap();
+        assert_eq!(moved.len(), 2);
+        assert_eq!(urls(&other), vec![Some("a".into()), None]);
+        assert_eq!(urls(&queue), vec![None, Some("b".into()), Some("d".into())]);
+        assert_eq!(queue.modify_queue(|vq| vq[1].requester()), Some(7));
+        assert!(stopped(&tracks[1].0) && stopped(&tracks[3].0));
+        assert!(!stopped(&tracks[0].0) && !stopped(&tracks[2].0));
+
+        // Moving the head starts the next remaining track in its place.
+        queue.append_queue(&other, true, &mut driver).unwrap();
+        assert_eq!(urls(&other), vec![None]);
+        assert!(stopped(&tracks[0].0));
+        assert!(tracks[2]
+            .0
+            .commands
+            .try_iter()
+            .any(|cmd| matches!(cmd, TrackCommand::Play)));
+    });
+}
+
+#[test]
+fn loop_queue_requeues_replayable_tracks() {
+    let queue = TrackQueue::new();
+    queue.set_buffer_while_disconnected(true);
+    queue.set_on_complete(OnComplete::LoopQueue);
+
+    let rt = test_harness::test_runtime();
+    let made = Arc::new(AtomicUsize::new(0));
+
+    rt.block_on(async {
+        let mut driver = Driver::default();
+        let mut add = |(track, handle): (Track, TrackHandle)| {
+            queue.try_add(track, &mut driver).unwrap();
+            handle
+        };
+
+        let first = add(sine_track_from(Some("https://a/")));
+        let broken = add(sine_track_from(Some("https://broken/")));
+        let local = add(sine_track_from(None));
+
+        // A source URL alone does not say how to recreate a track.
+        let (mut track, handle) = sine_track_from(Some("https://custom/"));
+        track.source.set_descriptor(None);
+        let undescribed = add((track, handle));
+
+        let (mut track, handle) = sine_track_from(Some("https://custom/"));
+        let factory_calls = made.clone();
+        track
+            .source
+            .set_descriptor(Some(SourceDescriptor::custom(move || {
+                factory_calls.fetch_add(1, Ordering::SeqCst);
+                SineRestarter
+            })));
+        let custom = add((track, handle));
+
+        let last = add(sine_track_from(Some("https://b/")));
+
+        let handler = QueueHandler {
+            remote_lock: queue.inner.clone(),
+        };
+        let played = TrackState {
+            first_frame_at: Some(Instant::now()),
+            ..Default::default()
+        };
+        let silent = TrackState::default();
+
+        for (state, handle) in &[
+            (&played, &first),
+            (&silent, &broken),
+            (&played, &local),
+            (&played, &undescribed),
+            (&played, &custom),
+        ] {
+            handler
+                .act(&EventContext::Track(&[(*state, *handle)]))
+                .await;
+        }
+        assert_eq!(queue.len(), 1);
+
+        handler.act(&EventContext::Track(&[(&played, &last)])).await;
+    });
+
+    let urls: Vec<_> = queue.modify_queue(|vq| {
+        vq.iter()
+            .map(|q| q.metadata().source_url.clone().unwrap())
+            .collect()
+    });
+    assert_eq!(urls, vec!["https://a/", "https://custom/", "https://b/"]);
+    assert_eq!(made.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn on_complete_callback_runs_when_queue_empties() {
+    struct Counter(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl EventHandler for Counter {
+        async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            None
+        }
+    }
+
+    let queue = TrackQueue::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    queue.set_on_complete(OnComplete::Callback(Arc::new(Counter(calls.clone()))));
+
+    let handler = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+    let state = TrackState::default();
+    let end = |handle: &TrackHandle| {
+        futures::executor::block_on(handler.act(&EventContext::Track(&[(&state, handle)])))
+    };
+
+    let first = queued_track(&queue);
+    let (mut track, second) = sine_track();
+    queue.add_raw(&mut track);
+
+    end(&first);
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    end(&second);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn loop_count_resets_on_head_change() {
+    let queue = TrackQueue::new();
+    queue.set_max_loops(Some(2));
+
+    let (mut track, head) = sine_track();
+    queue.add_raw(&mut track);
+    let next = queued_track(&queue);
+
+    let counter = LoopCounter {
+        remote_lock: queue.inner.clone(),
+    };
+    let state = TrackState::default();
+    let fire = |handle: &TrackHandle| {
+        futures::executor::block_on(counter.act(&EventContext::Track(&[(&state, handle)])))
+    };
+
+    fire(&head);
+    fire(&next);
+    assert_eq!(queue.loop_count(), 1);
+
+    // Reaching the limit asks the head to stop looping.
+    fire(&head);
+    assert_eq!(queue.loop_count(), 2);
+    assert!(matches!(
+        track.commands.try_recv(),
+        Ok(TrackCommand::Loop(LoopState::Finite(0)))
+    ));
+
+    queue.modify_queue(|vq| vq.swap(0, 1));
+    assert_eq!(queue.loop_count(), 0);
+}
+
+#[cfg(not(feature = "tokio-02-marker"))]
+#[test]
+fn scheduled_track_waits_at_head() {
+    let rt = test_harness::test_runtime();
+
+    let queue = TrackQueue::new();
+    let first = queued_track(&queue);
+    let (mut track, _) = sine_track();
+    let wait = Duration::from_millis(50);
+    queue.add_raw_at(
+        &mut track,
+        AddOptions {
+            not_before: Some(Instant::now() + wait),
+            ..Default::default()
+        },
+    );
+
+    let handler = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+    let state = TrackState::default();
+
+    rt.block_on(async {
+        handler.act(&EventContext::Track(&[(&state, &first)])).await;
+
+        assert!(matches!(track.commands.try_recv(), Ok(TrackCommand::Pause)));
+        assert!(track.commands.try_recv().is_err());
+
+        tsleep(2 * wait).await;
+
+        assert!(matches!(track.commands.try_recv(), Ok(TrackCommand::Play)));
+    });
+}
+
+#[test]
+fn volume_percent_applies_to_later_tracks() {
+    let queue = TrackQueue::new();
+    queue.set_volume_percent(50);
+
+    let (mut track, _handle) = sine_track();
+    queue.add_raw(&mut track);
+
+    assert!((track.volume() - 0.5).abs() < f32::EPSILON);
+
+    queue.set_volume_percent_with(VolumeCurve::Perceptual, 50);
+
+    let (mut track, _handle) = sine_track();
+    queue.add_raw(&mut track);
+
+    let expected = VolumeCurve::Perceptual.gain(50);
+    assert!((track.volume() - expected).abs() < f32::EPSILON);
+}
+
+#[test]
+fn fingerprint_tracks_order_volume_and_looping() {
+    let queue = TrackQueue::new();
+    queued_track(&queue);
+    queued_track(&queue);
+
+    let mut seen = vec![queue.fingerprint()];
+    let mut check_changed = |queue: &TrackQueue| {
+        let print = queue.fingerprint();
+        assert!(!seen.contains(&print));
+        seen.push(print);
+    };
+
+    queue.modify_queue(|vq| vq.swap(0, 1));
+    check_changed(&queue);
+
+    queue.set_volume_percent(50);
+    check_changed(&queue);
+
+    queue.set_on_complete(OnComplete::LoopQueue);
+    check_changed(&queue);
+
+    queue.set_max_loops(Some(2));
+    check_changed(&queue);
+}
+
+#[test]
+fn clips_are_validated_and_stop_at_end() {
+    let ms = Duration::from_millis;
+    let clip_track = || {
+        let metadata = Metadata {
+            duration: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let data = make_sine(50 * MONO_FRAME_SIZE, true);
+        let input = Input::new(
+            true,
+            data.into(),
+            Codec::FloatPcm,
+            Container::Raw,
+            Some(metadata),
+        );
+
+        tracks::create_player(input)
+    };
+
+    let queue = TrackQueue::new();
+    let (mut track, _) = clip_track();
+    assert_eq!(
+        queue.clip(&mut track, ms(500), ms(500)),
+        Err(TrackError::InvalidClip)
+    );
+    assert_eq!(
+        queue.clip(&mut track, ms(0), ms(1001)),
+        Err(TrackError::InvalidClip)
+    );
+
+    // Clips may run up to the very end of a track.
+    let (mut track, handle) = clip_track();
+    queue.clip(&mut track, ms(200), ms(1000)).unwrap();
+    assert!(matches!(track.commands.try_recv(), Ok(TrackCommand::Seek(t)) if t == ms(200)));
+    queue.add_raw(&mut track);
+
+    let mut state = track.state();
+    let mut store = track.events.take().unwrap();
+    let mut fire_due = |play_time, position| {
+        state.play_time = play_time;
+        state.position = position;
+
+        futures::executor::block_on(
+            store.process_timed(play_time, EventContext::Track(&[(&state, &handle)])),
+        );
+    };
+
+    // A seek back within the clip postpones its end.
+    fire_due(ms(800), ms(700));
+    assert!(track.commands.try_recv().is_err());
+
+    fire_due(ms(1080), ms(980));
+    assert!(track.commands.try_recv().is_err());
+
+    fire_due(ms(1100), ms(1000));
+    assert!(matches!(track.commands.try_recv(), Ok(TrackCommand::Stop)));
+}
+
+#[test]
+fn lazy_sources_seek_to_clip_start_in_driver() {
+    let rt = test_harness::test_runtime();
+    let source = futures::executor::block_on(Restartable::new(SineRestarter, true)).unwrap();
+
+    let queue = TrackQueue::new();
+    let (mut track, _handle) = tracks::create_player(source.into());
+    queue
+        .clip(
+            &mut track,
+            Duration::from_millis(200),
+            Duration::from_millis(600),
+        )
+        .unwrap();
+
+    // As done by the mixer, once the track is added to it.
+    track.source.prep_with_handle(rt.handle().clone(), None);
+    track.process_commands(0, &test_harness::disconnected_interconnect());
+    assert_eq!(track.position, Duration::from_millis(200));
+}
+
+#[test]
+fn max_duration_stops_track_once_reached() {
+    let queue = TrackQueue::new();
+    let (mut track, handle) = sine_track();
+    queue.limit_duration(&mut track, Duration::from_secs(1));
+    queue.add_raw(&mut track);
+
+    let mut state = track.state();
+    let mut store = track.events.take().unwrap();
+    let mut fire_due = |play_time| {
+        state.play_time = play_time;
+
+        futures::executor::block_on(
+            store.process_timed(play_time, EventContext::Track(&[(&state, &handle)])),
+        );
+    };
+
+    fire_due(Duration::from_millis(980));
+    assert!(track.commands.try_recv().is_err());
+
+    fire_due(Duration::from_secs(1));
+    assert!(matches!(track.commands.try_recv(), Ok(TrackCommand::Stop)));
+}
+
+#[test]
+fn split_separates_head_from_upcoming() {
+    let queue = TrackQueue::new();
+    assert!(matches!(queue.split(), (None, ref rest) if rest.is_empty()));
+
+    let uuids: Vec<_> = (0..3).map(|_| queued_track(&queue).uuid()).collect();
+    let (head, rest) = queue.split();
+
+    assert_eq!(head.map(|h| h.uuid()), Some(uuids[0]));
+    assert_eq!(
+        rest.iter().map(|h| h.uuid()).collect::<Vec<_>>(),
+        &uuids[1..]
+    );
+}
+
+#[test]
+fn with_current_lends_head() {
+    let queue = TrackQueue::new();
+    assert!(queue.with_current(|head| head.is_none()));
+
+    let first = queued_track(&queue).uuid();
+    queued_track(&queue);
+
+    assert_eq!(
+        queue.with_current(|head| head.map(|h| h.uuid())),
+        Some(first)
+    );
+}
+
+#[test]
+fn backend_mirrors_queue_order() {
+    let queue = TrackQueue::new();
+    let first = queued_track(&queue).uuid();
+
+    assert!(queue
+        .set_backend(Box::new(MemoryBackend::default()))
+        .is_none());
+
+    let second = queued_track(&queue).uuid();
+    let third = queued_track(&queue).uuid();
+    let backend_order = |queue: &TrackQueue| {
+        queue
+            .backend_snapshot()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.uuid)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(backend_order(&queue), vec![first, second, third]);
+
+    queue.modify_queue(|vq| vq.swap(1, 2));
+    assert_eq!(backend_order(&queue), vec![first, third, second]);
+
+    queue.dequeue(0).unwrap();
+    assert_eq!(backend_order(&queue), vec![third, second]);
+
+    queue.stop();
+    assert!(backend_order(&queue).is_empty());
+    assert!(queue.take_backend().is_some());
+    assert!(queue.backend_snapshot().is_none());
+}
+
+#[test]
+fn stop_returning_counts_live_tracks() {
+    let queue = TrackQueue::new();
+    assert_eq!(queue.stop_returning(), 0);
+
+    let (mut live, _handle) = sine_track();
+    queue.add_raw(&mut live);
+    queued_track(&queue);
+    assert_eq!(queue.stop_returning(), 1);
+
+    assert!(queue.is_empty());
+    assert_eq!(queue.stop_returning(), 0);
+}
+
+#[test]
+fn advancing_reports_transition_gap() {
+    let queue = TrackQueue::new();
+    let (mut first_track, first) = sine_track();
+    let (mut second_track, second) = sine_track();
+    queue.add_raw(&mut first_track);
+    queue.add_raw(&mut second_track);
+
+    let updates = queue.watch();
+    let last_frame = Instant::now();
+    let ended = TrackState {
+        last_frame_at: Some(last_frame),
+        ..Default::default()
+    };
+    let started = TrackState {
+        first_frame_at: Some(last_frame + Duration::from_millis(70)),
+        ..Default::default()
+    };
+
+    let ender = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+    let timer = TransitionTimer {
+        remote_lock: queue.inner.clone(),
+    };
+
+    futures::executor::block_on(ender.act(&EventContext::Track(&[(&ended, &first)])));
+    futures::executor::block_on(timer.act(&EventContext::Track(&[(&started, &second)])));
+    // Each transition is only reported once.
+    futures::executor::block_on(timer.act(&EventContext::Track(&[(&started, &second)])));
+
+    let transitions: Vec<_> = updates
+        .try_iter()
+        .filter_map(|update| match update {
+            QueueUpdate::Transition {
+                previous,
+                current,
+                gap,
+            } => Some((previous, current, gap)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        transitions,
+        vec![(first.uuid(), second.uuid(), Duration::from_millis(50))]
+    );
+}
+
+#[test]
+fn refreshed_metadata_only_cached_for_head() {
+    let queue = TrackQueue::new();
+    let (mut first_track, first) = sine_track();
+    let (mut second_track, second) = sine_track();
+    queue.add_raw(&mut first_track);
+    queue.add_raw(&mut second_track);
+
+    let fresh = Metadata {
+        title: Some("fresh".into()),
+        ..Default::default()
+    };
+
+    assert!(!queue.inner.lock().cache_refreshed(second.uuid(), &fresh));
+    assert!(queue.current_metadata().unwrap().title.is_none());
+
+    assert!(queue.inner.lock().cache_refreshed(first.uuid(), &fresh));
+    assert_eq!(queue.current_metadata(), Some(fresh));
+}
+
+#[test]
+fn metadata_refresh_returns_cache_without_source_url() {
+    let queue = TrackQueue::new();
+    assert!(futures::executor::block_on(queue.refresh_current_metadata()).is_none());
+
+    let (mut track, _handle) = sine_track();
+    queue.add_raw(&mut track);
+
+    let refreshed = futures::executor::block_on(queue.refresh_current_metadata());
+    assert_eq!(refreshed, queue.current_metadata());
+    assert!(refreshed.unwrap().source_url.is_none());
+}
+
+#[test]
+fn custom_uuids_locate_and_dequeue_tracks() {
+    let queue = TrackQueue::new();
+    let uuid = Uuid::new_v4();
+    queued_track(&queue);
+
+    let input = sine_track().0.source;
+    let (mut track, _handle) = tracks::create_player_with_uuid(input, uuid);
+    queue.add_raw(&mut track);
+
+    assert_eq!(queue.position_of(uuid), Some(1));
+    assert_eq!(
+        queue.inner.lock().check_unique(uuid),
+        Err(TrackError::DuplicateUuid)
+    );
+
+    let removed = queue.dequeue_uuid(uuid).unwrap();
+    assert_eq!(removed.map(|q| q.uuid()), Some(uuid));
+    assert_eq!(queue.position_of(uuid), None);
+    assert!(queue.dequeue_uuid(uuid).unwrap().is_none());
+    assert!(queue.inner.lock().check_unique(uuid).is_ok());
+}
+
+#[test]
+fn fair_mode_interleaves_requesters() {
+    let queue = TrackQueue::new();
+    queue.set_fair_mode(true);
+
+    let requesters = [1, 1, 1, 2, 2, 3];
+    let mut live = vec![];
+    let mut handles = vec![];
+    for _ in &requesters {
+        let (mut track, handle) = sine_track();
+        queue.add_raw(&mut track);
+        live.push(track);
+        handles.push(handle);
+    }
+    queue.modify_queue(|vq| {
+        for (queued, requester) in vq.iter_mut().zip(&requesters) {
+            queued.set_requester(Some(*requester));
+        }
+    });
+
+    let handler = QueueHandler {
+        remote_lock: queue.inner.clone(),
+    };
+    let state = TrackState::default();
+    let mut order = vec![];
+    while let Some(head) = queue.current() {
+        order.push(
+            handles
+                .iter()
+                .position(|h| h.uuid() == head.uuid())
+                .unwrap(),
+        );
+        futures::executor::block_on(handler.act(&EventContext::Track(&[(&state, &head)])));
+    }
+
+    // By requester: 1, 2, 3, 1, 2, 1.
+    assert_eq!(order, vec![0, 3, 5, 1, 4, 2]);
+    assert!(queue.inner.lock().requester_turns.is_empty());
+}
+
+#[cfg(feature = "builtin-queue")]
+#[test]
+fn add_source_multi_queues_one_track_per_driver() {
+    let rt = test_harness::test_runtime();
+
+    rt.block_on(async {
+        let mut first = Driver::default();
+        let mut second = Driver::default();
+        first.queue().set_buffer_while_disconnected(true);
+
+        let outcomes = TrackQueue::add_source_multi(SineRestarter, &mut [&mut first, &mut second])
+            .await
+            .unwrap();
+
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(
+            first.queue().current().map(|t| t.uuid()),
+            outcomes[0].as_ref().ok().map(TrackHandle::uuid)
+        );
+        assert_eq!(outcomes[1].as_ref().err(), Some(&TrackError::NotConnected));
+        assert!(second.queue().is_empty());
+    });
+}
@@ -1,4 +1,5 @@
 use super::*;
+use std::time::Instant;
 
 /// State of an [`Track`] object, designed to be passed to event handlers
 /// and retrieved remotely via [`TrackHandle::get_info`].
@@ -6,6 +7,7 @@ use super::*;
 /// [`Track`]: Track
 /// [`TrackHandle::get_info`]: TrackHandle::get_info
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
 pub struct TrackState {
     /// Play status (e.g., active, paused, stopped) of this track.
     pub playing: PlayMode,
@@ -20,6 +22,24 @@ pub struct TrackState {
     pub play_time: Duration,
     /// Remaining loops on this track.
     pub loops: LoopState,
+    /// How the mixer handled this track's audio on its most recent cycle.
+    ///
+    /// This is `None` until the track has been played, and is only kept up to date
+    /// in states returned by [`TrackHandle::get_info`].
+    pub passthrough: Option<PassthroughDecision>,
+    /// Whether this track's audio has been passed through as Opus, over its whole lifetime.
+    ///
+    /// This is `None` until the track has played a frame of audio.
+    pub passthrough_usage: Option<PassthroughUsage>,
+    /// When the mixer produced this track's first frame of audio.
+    pub(crate) first_frame_at: Option<Instant>,
+    /// When the mixer produced this track's most recent frame of audio.
+    ///
+    /// Event handlers only see this updated on [`TrackEvent::Started`], and once
+    /// the track has ended or stopped.
+    ///
+    /// [`TrackEvent::Started`]: crate::events::TrackEvent::Started
+    pub(crate) last_frame_at: Option<Instant>,
 }
 
 impl TrackState {
@@ -28,3 +48,30 @@ impl TrackState {
         self.play_time += TIMESTEP_LENGTH;
     }
 }
+
+/// Running totals of the audio produced by a [`Track`], retrieved remotely via
+/// [`TrackHandle::play_stats`].
+///
+/// These counters belong to a single [`Track`]: they are unaffected by seeks and
+/// loops, but a track which is re-created (e.g., by [`TrackQueue::transfer_to`])
+/// starts again from zero.
+///
+/// [`Track`]: Track
+/// [`TrackHandle::play_stats`]: TrackHandle::play_stats
+/// [`TrackQueue::transfer_to`]: TrackQueue::transfer_to
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct PlayStats {
+    /// Number of 20ms frames which were decoded and mixed.
+    pub frames_decoded: u64,
+    /// Number of 20ms frames sent as-is via Opus passthrough.
+    pub frames_passed_through: u64,
+    /// Number of interleaved samples added to the mixer's stereo output buffer.
+    ///
+    /// This does not include any frames sent via passthrough.
+    pub samples_mixed: u64,
+    /// Number of 20ms frames decoded and discarded after seeking, while the Opus
+    /// decoder re-synchronised with the stream.
+    ///
+    /// These frames are never mixed or sent.
+    pub frames_primed: u64,
+}
@@ -0,0 +1,57 @@
+/// Mapping from a volume percentage onto the linear gain used by the mixer.
+///
+/// Under every curve, `0%` is silence, `100%` is unity gain (i.e., the source's
+/// own volume), and percentages above [`MAX_PERCENT`] are treated as `200%`.
+///
+/// [`MAX_PERCENT`]: VolumeCurve::MAX_PERCENT
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum VolumeCurve {
+    /// Gain is directly proportional to the percentage: `50%` halves the
+    /// amplitude of a source, and `200%` doubles it.
+    Linear,
+    /// Gain follows perceived loudness, where doubling the percentage makes a
+    /// source sound twice as loud (a rise of 10 dB).
+    ///
+    /// `50%` is a gain of roughly `0.32` (-10 dB), and `200%` roughly `3.16` (+10 dB).
+    /// This gives finer control at low volumes than [`Linear`].
+    ///
+    /// [`Linear`]: VolumeCurve::Linear
+    Perceptual,
+}
+
+impl VolumeCurve {
+    /// The largest percentage accepted by [`gain`].
+    ///
+    /// [`gain`]: VolumeCurve::gain
+    pub const MAX_PERCENT: u8 = 200;
+
+    /// Returns the linear gain corresponding to `percent` on this curve.
+    pub fn gain(self, percent: u8) -> f32 {
+        let loudness = f32::from(percent.min(Self::MAX_PERCENT)) / 100.0;
+
+        match self {
+            VolumeCurve::Linear => loudness,
+            // +10 dB per doubling of loudness: 10^(10/20) = 2^exponent.
+            VolumeCurve::Perceptual => loudness.powf(0.5 * 10f32.log2()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_agree_at_silence_and_unity() {
+        for curve in [VolumeCurve::Linear, VolumeCurve::Perceptual].iter() {
+            assert_eq!(curve.gain(0), 0.0);
+            assert!((curve.gain(100) - 1.0).abs() < f32::EPSILON);
+            assert_eq!(curve.gain(255), curve.gain(VolumeCurve::MAX_PERCENT));
+        }
+
+        assert!((VolumeCurve::Linear.gain(200) - 2.0).abs() < f32::EPSILON);
+        assert!((VolumeCurve::Perceptual.gain(200) - 10f32.sqrt()).abs() < 1e-4);
+        assert!((VolumeCurve::Perceptual.gain(50) - 10f32.sqrt().recip()).abs() < 1e-4);
+    }
+}